@@ -1,12 +1,84 @@
-use clap::{App, Arg, ArgMatches};
+use chrono::{DateTime, TimeZone, Utc};
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
 use edpdgen_lib::input::input_format::InputFormat;
 use edpdgen_lib::output::output_format::OutputFormat;
+use lazy_static::lazy_static;
 use regex::Regex;
+use std::collections::HashMap;
 use std::ffi::OsStr;
 use std::fs;
 use std::path::Path;
 use std::str::FromStr;
 
+/// The options only some output formats support, with the name of their arg and their help.
+const FORMAT_OPTIONS: &[(&str, &str, &str)] = &[
+    (
+        "concise",
+        "CONCISE",
+        "Use to generate Concise Dictionary.",
+    ),
+    (
+        "collision-report",
+        "COLLISION_REPORT",
+        "Write <short_name>.collisions.tsv listing inflections shared by groups or equal to headwords.",
+    ),
+    (
+        "prefer-headwords",
+        "PREFER_HEADWORDS",
+        "Order .syn so that inflections equal to a headword resolve to that headword first.",
+    ),
+    (
+        "deconstruct",
+        "DECONSTRUCT_PATH",
+        "Text or word list whose unknown forms get sandhi & compound deconstruction articles.",
+    ),
+    (
+        "sutta-links",
+        "SUTTA_LINK_PATTERN",
+        "Link sutta references using this pattern, {id} is replaced by e.g. mn10. E.g. https://suttacentral.net/{id} or bword://{id}",
+    ),
+    (
+        "frequency",
+        "FREQUENCY_PATH",
+        "Tsv of form and its count in each piṭaka (columns form, vinaya, sutta, abhidhamma), shown with each word.",
+    ),
+    (
+        "min-frequency",
+        "MIN_FREQUENCY",
+        "Leave out words whose headword & inflections occur fewer times than this, e.g. for learners' concise builds.",
+    ),
+    (
+        "render-cache",
+        "RENDER_CACHE_PATH",
+        "The path to the render cache db (will be created if it does not exist).",
+    ),
+    (
+        "mdd",
+        "MDD",
+        "Also write <short_name>.mdd with the stylesheet and icon, the articles then link to the stylesheet.",
+    ),
+    (
+        "site-url",
+        "SITE_URL",
+        "Url the site is hosted at, used for the absolute urls of the sitemap. E.g. https://dict.example.org/dpd/ [default: http://localhost:8000/]",
+    ),
+];
+
+lazy_static! {
+    /// The help of each format option, followed by the formats that support it.
+    static ref FORMAT_OPTION_HELPS: HashMap<&'static str, String> = FORMAT_OPTIONS
+        .iter()
+        .map(|(option, _, help)| {
+            let formats = OutputFormat::all()
+                .filter(|f| f.options().contains(option))
+                .map(|f| f.name())
+                .collect::<Vec<&str>>()
+                .join(", ");
+            (*option, format!("{} Formats: {}.", help, formats))
+        })
+        .collect();
+}
+
 pub(crate) struct EdpdArgs<'a> {
     pub csv_path: &'a str,
     pub input_format: InputFormat,
@@ -19,8 +91,84 @@ pub(crate) struct EdpdArgs<'a> {
     pub headings_color: Option<&'a str>,
     pub icon_path: Option<&'a str>,
     pub inflections_db_path: Option<&'a str>,
+    pub render_cache_path: Option<&'a str>,
+    pub time_stamp: Option<&'a str>,
+    pub version: Option<&'a str>,
     pub what_if: bool,
     pub concise: bool,
+    pub package: bool,
+    pub collision_report: bool,
+    pub prefer_headwords: bool,
+    pub deconstruct_path: Option<&'a str>,
+    pub sutta_link_pattern: Option<&'a str>,
+    pub frequency_path: Option<&'a str>,
+    pub min_frequency: u64,
+    pub mdd: bool,
+    pub site_url: Option<&'a str>,
+}
+
+pub(crate) struct DiffArgs<'a> {
+    pub old_csv_path: &'a str,
+    pub new_csv_path: &'a str,
+    pub input_format: InputFormat,
+    pub output_folder: Option<&'a str>,
+}
+
+pub(crate) struct ServeDictArgs<'a> {
+    pub index_path: &'a str,
+    pub dict_path: &'a str,
+    pub port: u16,
+}
+
+pub(crate) struct InflectionsBuildArgs<'a> {
+    pub csv_path: &'a str,
+    pub patterns_path: Option<&'a str>,
+    pub inflections_db_path: &'a str,
+}
+
+pub(crate) fn get_inflections_build_args<'a>(args: &'a ArgMatches) -> InflectionsBuildArgs<'a> {
+    InflectionsBuildArgs {
+        csv_path: args
+            .value_of("CSV_FILE")
+            .expect("This is a required argument"),
+        patterns_path: args.value_of("PATTERNS_PATH"),
+        inflections_db_path: args
+            .value_of("INFLECTION_DB_PATH")
+            .expect("This is a required argument"),
+    }
+}
+
+pub(crate) fn get_serve_dict_args<'a>(args: &'a ArgMatches) -> ServeDictArgs<'a> {
+    ServeDictArgs {
+        index_path: args
+            .value_of("INDEX_FILE")
+            .expect("This is a required argument"),
+        dict_path: args
+            .value_of("DICT_FILE")
+            .expect("This is a required argument"),
+        port: args
+            .value_of("PORT")
+            .expect("This argument has a default value")
+            .parse()
+            .expect("Invalid cases should have been reject by clapp"),
+    }
+}
+
+pub(crate) fn get_diff_args<'a>(args: &'a ArgMatches) -> DiffArgs<'a> {
+    DiffArgs {
+        old_csv_path: args
+            .value_of("OLD_CSV_FILE")
+            .expect("This is a required argument"),
+        new_csv_path: args
+            .value_of("NEW_CSV_FILE")
+            .expect("This is a required argument"),
+        input_format: InputFormat::from_str(
+            args.value_of("INPUT_FORMAT")
+                .expect("This argument has a default value"),
+        )
+        .expect("Invalid cases should have been reject by clapp"),
+        output_folder: args.value_of("OUTPUT_FOLDER"),
+    }
 }
 
 pub(crate) fn get_args<'a>(args: &'a ArgMatches) -> EdpdArgs<'a> {
@@ -46,16 +194,56 @@ pub(crate) fn get_args<'a>(args: &'a ArgMatches) -> EdpdArgs<'a> {
         headings_color: args.value_of("HEADINGS_COLOR"),
         icon_path: args.value_of("ICON_PATH"),
         inflections_db_path: args.value_of("INFLECTION_DB_PATH"),
+        render_cache_path: args.value_of("RENDER_CACHE_PATH"),
+        time_stamp: args.value_of("TIME_STAMP"),
+        version: args.value_of("VERSION"),
         what_if: args.is_present("WHAT_IF"),
         concise: args.is_present("CONCISE"),
+        package: args.is_present("PACKAGE"),
+        collision_report: args.is_present("COLLISION_REPORT"),
+        prefer_headwords: args.is_present("PREFER_HEADWORDS"),
+        deconstruct_path: args.value_of("DECONSTRUCT_PATH"),
+        sutta_link_pattern: args.value_of("SUTTA_LINK_PATTERN"),
+        frequency_path: args.value_of("FREQUENCY_PATH"),
+        min_frequency: args
+            .value_of("MIN_FREQUENCY")
+            .map(|s| {
+                s.parse()
+                    .expect("Invalid cases should have been reject by clapp")
+            })
+            .unwrap_or(0),
+        mdd: args.is_present("MDD"),
+        site_url: args.value_of("SITE_URL"),
     }
 }
 
+pub(crate) fn get_unsupported_options(
+    args: &ArgMatches,
+    output_format: &OutputFormat,
+) -> Vec<&'static str> {
+    FORMAT_OPTIONS
+        .iter()
+        .filter(|(option, arg_name, _)| {
+            args.is_present(arg_name) && !output_format.options().contains(option)
+        })
+        .map(|(option, _, _)| *option)
+        .collect()
+}
+
 pub(crate) fn parse_args<'a>() -> ArgMatches<'a> {
+    create_app().get_matches()
+}
+
+fn create_app<'a, 'b>() -> App<'a, 'b> {
     App::new(env!("CARGO_PKG_NAME"))
         .version(env!("CARGO_PKG_VERSION"))
         .author(env!("CARGO_PKG_AUTHORS"))
         .about(env!("CARGO_PKG_DESCRIPTION"))
+        .setting(AppSettings::SubcommandsNegateReqs)
+        .subcommand(create_diff_subcommand())
+        .subcommand(create_inflections_subcommand())
+        .subcommand(create_serve_dict_subcommand())
+        .subcommand(create_formats_subcommand())
         .arg(create_csv_file_arg())
         .arg(create_input_format_arg())
         .arg(create_output_format_arg())
@@ -67,9 +255,117 @@ pub(crate) fn parse_args<'a>() -> ArgMatches<'a> {
         .arg(create_headings_color_arg())
         .arg(create_icon_path_arg())
         .arg(create_inflection_db_path_arg())
+        .arg(create_render_cache_path_arg())
+        .arg(create_time_stamp_arg())
+        .arg(create_version_arg())
         .arg(create_what_if_arg())
         .arg(create_concise_arg())
-        .get_matches()
+        .arg(create_package_arg())
+        .arg(create_collision_report_arg())
+        .arg(create_prefer_headwords_arg())
+        .arg(create_deconstruct_path_arg())
+        .arg(create_sutta_link_pattern_arg())
+        .arg(create_frequency_path_arg())
+        .arg(create_min_frequency_arg())
+        .arg(create_mdd_arg())
+        .arg(create_site_url_arg())
+}
+
+fn create_diff_subcommand<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("diff")
+        .about("Generates the changelog (markdown & json) between two versions of the csv.")
+        .arg(
+            Arg::with_name("OLD_CSV_FILE")
+                .value_name("OLD_CSV_FILE")
+                .help("CSV of the previous version.")
+                .required(true)
+                .index(1)
+                .validator(|s| validate_file_exists(&s)),
+        )
+        .arg(
+            Arg::with_name("NEW_CSV_FILE")
+                .value_name("NEW_CSV_FILE")
+                .help("CSV of the new version.")
+                .required(true)
+                .index(2)
+                .validator(|s| validate_file_exists(&s)),
+        )
+        .arg(
+            create_input_format_arg()
+                .required(false)
+                .default_value("dpd"),
+        )
+        .arg(create_output_folder_arg())
+}
+
+fn create_inflections_subcommand<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("inflections")
+        .about("Inflections db commands.")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .subcommand(
+            SubCommand::with_name("build")
+                .about("Builds inflections.db from the Stem & Pattern columns of the csv and the declension pattern tables.")
+                .arg(create_csv_file_arg())
+                .arg(
+                    Arg::with_name("PATTERNS_PATH")
+                        .long("patterns")
+                        .value_name("PATTERNS_PATH")
+                        .help("Pattern tables tsv file or folder of tsv files (defaults to the built-in tables).")
+                        .required(false)
+                        .validator(|s| validate_path_exists(&s))
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("INFLECTION_DB_PATH")
+                        .short("o")
+                        .long("output")
+                        .value_name("INFLECTION_DB_PATH")
+                        .help("The path to the inflections.db to create (will be overwritten).")
+                        .required(true)
+                        .takes_value(true),
+                ),
+        )
+}
+
+fn create_serve_dict_subcommand<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("serve-dict")
+        .about("Serves a dictd format dictionary over the DICT protocol (RFC 2229) on localhost.")
+        .arg(
+            Arg::with_name("INDEX_FILE")
+                .value_name("INDEX_FILE")
+                .help("The .index file of the dictionary.")
+                .required(true)
+                .index(1)
+                .validator(|s| validate_file_exists(&s)),
+        )
+        .arg(
+            Arg::with_name("DICT_FILE")
+                .value_name("DICT_FILE")
+                .help("The .dict or .dict.dz file of the dictionary.")
+                .required(true)
+                .index(2)
+                .validator(|s| validate_file_exists(&s)),
+        )
+        .arg(
+            Arg::with_name("PORT")
+                .short("p")
+                .long("port")
+                .value_name("PORT")
+                .help("The port to listen on.")
+                .required(false)
+                .default_value("2628")
+                .validator(|s| {
+                    s.parse::<u16>()
+                        .map(|_| ())
+                        .map_err(|_| "Must be a port number.".to_string())
+                })
+                .takes_value(true),
+        )
+}
+
+fn create_formats_subcommand<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("formats")
+        .about("Lists the output formats with the input formats and options they support.")
 }
 
 fn create_output_folder_arg<'a, 'b>() -> Arg<'a, 'b> {
@@ -160,7 +456,81 @@ fn create_what_if_arg<'a, 'b>() -> Arg<'a, 'b> {
 fn create_concise_arg<'a, 'b>() -> Arg<'a, 'b> {
     Arg::with_name("CONCISE")
         .long("concise")
-        .help("Use to generate Concise Dictionary.")
+        .help(&FORMAT_OPTION_HELPS["concise"])
+}
+
+fn create_package_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("PACKAGE")
+        .long("package")
+        .help("Also bundle the dictionary into .zip and .tar.gz archives with SHA256SUMS.")
+}
+
+fn create_collision_report_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("COLLISION_REPORT")
+        .long("collision-report")
+        .help(&FORMAT_OPTION_HELPS["collision-report"])
+}
+
+fn create_mdd_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("MDD")
+        .long("mdd")
+        .help(&FORMAT_OPTION_HELPS["mdd"])
+}
+
+fn create_site_url_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("SITE_URL")
+        .long("site-url")
+        .value_name("SITE_URL")
+        .help(&FORMAT_OPTION_HELPS["site-url"])
+        .required(false)
+        .validator(validate_site_url)
+        .takes_value(true)
+}
+
+fn create_prefer_headwords_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("PREFER_HEADWORDS")
+        .long("prefer-headwords")
+        .help(&FORMAT_OPTION_HELPS["prefer-headwords"])
+}
+
+fn create_deconstruct_path_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("DECONSTRUCT_PATH")
+        .long("deconstruct")
+        .value_name("DECONSTRUCT_PATH")
+        .help(&FORMAT_OPTION_HELPS["deconstruct"])
+        .required(false)
+        .validator(|s| validate_file_exists(&s))
+        .takes_value(true)
+}
+
+fn create_sutta_link_pattern_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("SUTTA_LINK_PATTERN")
+        .long("sutta-links")
+        .value_name("SUTTA_LINK_PATTERN")
+        .help(&FORMAT_OPTION_HELPS["sutta-links"])
+        .required(false)
+        .validator(validate_sutta_link_pattern)
+        .takes_value(true)
+}
+
+fn create_frequency_path_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("FREQUENCY_PATH")
+        .long("frequency")
+        .value_name("FREQUENCY_PATH")
+        .help(&FORMAT_OPTION_HELPS["frequency"])
+        .required(false)
+        .validator(|s| validate_file_exists(&s))
+        .takes_value(true)
+}
+
+fn create_min_frequency_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("MIN_FREQUENCY")
+        .long("min-frequency")
+        .value_name("MIN_FREQUENCY")
+        .help(&FORMAT_OPTION_HELPS["min-frequency"])
+        .requires("FREQUENCY_PATH")
+        .validator(validate_min_frequency)
+        .takes_value(true)
 }
 
 fn create_inflection_db_path_arg<'a, 'b>() -> Arg<'a, 'b> {
@@ -174,6 +544,35 @@ fn create_inflection_db_path_arg<'a, 'b>() -> Arg<'a, 'b> {
         .takes_value(true)
 }
 
+fn create_render_cache_path_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("RENDER_CACHE_PATH")
+        .long("render-cache")
+        .value_name("RENDER_CACHE_PATH")
+        .help(&FORMAT_OPTION_HELPS["render-cache"])
+        .required(false)
+        .takes_value(true)
+}
+
+fn create_time_stamp_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("TIME_STAMP")
+        .long("timestamp")
+        .value_name("TIME_STAMP")
+        .help("The build time stamp (RFC 3339 or unix seconds, defaults to SOURCE_DATE_EPOCH, then now).")
+        .required(false)
+        .validator(|s| parse_time_stamp(&s).map(|_| ()))
+        .takes_value(true)
+}
+
+fn create_version_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("VERSION")
+        .long("dict-version")
+        .value_name("VERSION")
+        .help("The dictionary version used to name the packages (defaults to the time stamp date).")
+        .required(false)
+        .validator(validate_version)
+        .takes_value(true)
+}
+
 fn create_output_format_arg<'a, 'b>() -> Arg<'a, 'b> {
     Arg::with_name("OUTPUT_FORMAT")
         .short("f")
@@ -181,7 +580,7 @@ fn create_output_format_arg<'a, 'b>() -> Arg<'a, 'b> {
         .value_name("OUTPUT_FORMAT")
         .help("Target dictionary format.")
         .required(true)
-        .possible_values(&["stardict", "ajdict"])
+        .possible_values(&OutputFormat::all().map(|f| f.name()).collect::<Vec<&str>>())
         .takes_value(true)
 }
 
@@ -207,6 +606,19 @@ fn create_csv_file_arg<'a, 'b>() -> Arg<'a, 'b> {
         .takes_value(true)
 }
 
+pub(crate) fn parse_time_stamp(s: &str) -> Result<DateTime<Utc>, String> {
+    if let Ok(secs) = s.trim().parse::<i64>() {
+        return Utc
+            .timestamp_opt(secs, 0)
+            .single()
+            .ok_or_else(|| format!("'{}' is out of range.", s));
+    }
+
+    DateTime::parse_from_rfc3339(s.trim())
+        .map(|dt| dt.with_timezone(&Utc))
+        .map_err(|_| format!("'{}' has invalid format, run --help to see format.", s))
+}
+
 fn validate_html_color(s: String) -> Result<(), String> {
     let re = Regex::new(r"^#(?:[0-9a-fA-F]{3}){1,2}$").expect("is valid regex");
     if re.is_match(&s) {
@@ -242,6 +654,46 @@ fn validate_short_name(s: String) -> Result<(), String> {
     }
 }
 
+fn validate_sutta_link_pattern(s: String) -> Result<(), String> {
+    if s.contains("{id}") {
+        Ok(())
+    } else {
+        Err(format!(
+            "'{}' has invalid format, run --help to see format.",
+            s
+        ))
+    }
+}
+
+fn validate_site_url(s: String) -> Result<(), String> {
+    if s.starts_with("http://") || s.starts_with("https://") {
+        Ok(())
+    } else {
+        Err(format!(
+            "'{}' has invalid format, run --help to see format.",
+            s
+        ))
+    }
+}
+
+fn validate_min_frequency(s: String) -> Result<(), String> {
+    s.parse::<u64>()
+        .map(|_| ())
+        .map_err(|_| format!("'{}' is not a whole number.", s))
+}
+
+fn validate_version(s: String) -> Result<(), String> {
+    let re = Regex::new(r"^[\w.\-]{1,32}$").expect("is valid regex");
+    if re.is_match(&s) {
+        Ok(())
+    } else {
+        Err(format!(
+            "'{}' has invalid format, run --help to see format.",
+            s
+        ))
+    }
+}
+
 fn validate_name(s: String) -> Result<(), String> {
     if s.len() < 65 {
         Ok(())
@@ -273,6 +725,14 @@ fn validate_icon_file(s: String) -> Result<(), String> {
     }
 }
 
+fn validate_path_exists(s: &str) -> Result<(), String> {
+    if Path::new(&s).exists() {
+        Ok(())
+    } else {
+        Err(format!("'{}' does not exist.", s))
+    }
+}
+
 fn validate_file_exists(s: &str) -> Result<(), String> {
     if Path::new(&s).is_file() {
         Ok(())
@@ -280,3 +740,70 @@ fn validate_file_exists(s: &str) -> Result<(), String> {
         Err(format!("'{}' does not exist.", s))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::ErrorKind;
+    use test_case::test_case;
+
+    #[test]
+    fn format_options_are_args_test() {
+        for output_format in OutputFormat::all() {
+            for option in output_format.options() {
+                let ret = create_app().get_matches_from_safe(vec![
+                    env!("CARGO_PKG_NAME").to_string(),
+                    format!("--{}", option),
+                ]);
+
+                assert_ne!(
+                    ret.err().map(|e| e.kind),
+                    Some(ErrorKind::UnknownArgument),
+                    "'{}' of '{}' is not an argument.",
+                    option,
+                    output_format
+                );
+                assert!(
+                    FORMAT_OPTIONS.iter().any(|(o, _, _)| o == option),
+                    "'{}' of '{}' is not in FORMAT_OPTIONS.",
+                    option,
+                    output_format
+                );
+            }
+        }
+    }
+
+    #[test_case("mdd", " Formats: mdict.")]
+    #[test_case("site-url", " Formats: html.")]
+    #[test_case("collision-report", " Formats: stardict.")]
+    fn format_option_helps_list_the_formats_test(option: &str, formats: &str) {
+        assert!(
+            FORMAT_OPTION_HELPS[option].ends_with(formats),
+            "{}",
+            FORMAT_OPTION_HELPS[option]
+        );
+    }
+
+    #[test_case("dsl", &["--deconstruct", "Cargo.toml"] => vec!["deconstruct"])]
+    #[test_case("sqlite", &["--frequency", "Cargo.toml", "--concise"] => vec!["concise", "frequency"])]
+    #[test_case("mdict", &["--mdd", "--site-url", "https://a.b/"] => vec!["site-url"])]
+    #[test_case("stardict", &["--collision-report", "--deconstruct", "Cargo.toml"] => Vec::<&str>::new())]
+    fn get_unsupported_options_test(output_format: &str, options: &[&str]) -> Vec<&'static str> {
+        let mut args = vec![
+            env!("CARGO_PKG_NAME"),
+            "-c",
+            "Cargo.toml",
+            "-t",
+            "dpd",
+            "-f",
+            output_format,
+        ];
+        args.extend_from_slice(options);
+        let matches = create_app().get_matches_from(args);
+
+        get_unsupported_options(
+            &matches,
+            &OutputFormat::from_str(output_format).expect("Unexpected"),
+        )
+    }
+}
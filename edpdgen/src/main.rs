@@ -1,6 +1,8 @@
 use chrono::{Datelike, SecondsFormat, Utc};
 use edpdgen_lib::input::input_format::InputFormat;
+use edpdgen_lib::output::output_format::OutputFormat;
 use edpdgen_lib::DictionaryInfo;
+use std::env;
 use std::fs::File;
 use std::io::Read;
 
@@ -11,8 +13,59 @@ fn main() -> Result<(), String> {
     let l = logger::ColoredConsoleLogger {};
 
     let arg_matches = args::parse_args();
+    if let Some(diff_matches) = arg_matches.subcommand_matches("diff") {
+        let diff_args = args::get_diff_args(diff_matches);
+        print_banner();
+        return edpdgen_lib::diff(
+            diff_args.old_csv_path,
+            diff_args.new_csv_path,
+            &diff_args.input_format,
+            diff_args.output_folder.unwrap_or("dicts"),
+            &l,
+        );
+    }
+
+    if let Some(build_matches) = arg_matches
+        .subcommand_matches("inflections")
+        .and_then(|m| m.subcommand_matches("build"))
+    {
+        let build_args = args::get_inflections_build_args(build_matches);
+        print_banner();
+        return edpdgen_lib::build_inflections_db(
+            build_args.csv_path,
+            build_args.patterns_path,
+            build_args.inflections_db_path,
+            &l,
+        );
+    }
+
+    if let Some(serve_matches) = arg_matches.subcommand_matches("serve-dict") {
+        let serve_args = args::get_serve_dict_args(serve_matches);
+        print_banner();
+        return edpdgen_lib::serve_dict(
+            serve_args.index_path,
+            serve_args.dict_path,
+            serve_args.port,
+            &l,
+        );
+    }
+
+    if arg_matches.subcommand_matches("formats").is_some() {
+        print_banner();
+        print_output_formats();
+        return Ok(());
+    }
+
     let args = args::get_args(&arg_matches);
-    let ts = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true);
+    let unsupported_options = args::get_unsupported_options(&arg_matches, &args.output_format);
+    if !unsupported_options.is_empty() {
+        return Err(format!(
+            "The '{}' format does not support --{}.",
+            args.output_format,
+            unsupported_options.join(", --")
+        ));
+    }
+    let ts = create_time_stamp(args.time_stamp)?;
     let di = create_dictionary_info(&args, &ts);
 
     print_banner();
@@ -51,7 +104,18 @@ fn create_dictionary_info<'a>(args: &'a args::EdpdArgs, time_stamp: &'a str) ->
                 icon_path: args.icon_path,
                 icon: read_icon_bytes(args.icon_path, &args.input_format),
                 inflections_db_path: args.inflections_db_path,
+                render_cache_path: args.render_cache_path,
+                version: args.version.unwrap_or_else(|| time_stamp.get(..10).unwrap_or(time_stamp)),
                 concise: args.concise,
+                package: args.package,
+                collision_report: args.collision_report,
+                prefer_headwords: args.prefer_headwords,
+                deconstruct_path: args.deconstruct_path,
+                sutta_link_pattern: args.sutta_link_pattern,
+                frequency_path: args.frequency_path,
+                min_frequency: args.min_frequency,
+                mdd: args.mdd,
+                site_url: args.site_url,
             }
         }
         InputFormat::Dps => {
@@ -74,12 +138,39 @@ fn create_dictionary_info<'a>(args: &'a args::EdpdArgs, time_stamp: &'a str) ->
                 icon_path: args.icon_path,
                 icon: read_icon_bytes(args.icon_path, &args.input_format),
                 inflections_db_path: args.inflections_db_path,
+                render_cache_path: args.render_cache_path,
+                version: args.version.unwrap_or_else(|| time_stamp.get(..10).unwrap_or(time_stamp)),
                 concise: args.concise,
+                package: args.package,
+                collision_report: args.collision_report,
+                prefer_headwords: args.prefer_headwords,
+                deconstruct_path: args.deconstruct_path,
+                sutta_link_pattern: args.sutta_link_pattern,
+                frequency_path: args.frequency_path,
+                min_frequency: args.min_frequency,
+                mdd: args.mdd,
+                site_url: args.site_url,
             }
         }
     }
 }
 
+///
+/// NOTE: Honours https://reproducible-builds.org/specs/source-date-epoch/ so that
+/// identical inputs produce byte for byte identical dictionaries.
+///
+fn create_time_stamp(time_stamp: Option<&str>) -> Result<String, String> {
+    let ts = match time_stamp
+        .map(|ts| ts.to_string())
+        .or_else(|| env::var("SOURCE_DATE_EPOCH").ok())
+    {
+        Some(ts) => args::parse_time_stamp(&ts)?,
+        None => Utc::now(),
+    };
+
+    Ok(ts.to_rfc3339_opts(SecondsFormat::Secs, true))
+}
+
 fn print_banner() {
     println!(
         "{} - {}",
@@ -96,6 +187,40 @@ fn print_banner() {
     println!();
 }
 
+fn print_output_formats() {
+    println!("Output formats:");
+    for output_format in OutputFormat::all() {
+        println!(
+            "... {}: {}",
+            output_format.name(),
+            output_format.description()
+        );
+        println!(
+            "      Input formats: {}",
+            output_format
+                .input_formats()
+                .iter()
+                .map(|f| f.to_string())
+                .collect::<Vec<String>>()
+                .join(", ")
+        );
+        println!(
+            "      Options: {}",
+            if output_format.options().is_empty() {
+                "<none>".to_string()
+            } else {
+                output_format
+                    .options()
+                    .iter()
+                    .map(|o| format!("--{}", o))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            }
+        );
+    }
+    println!();
+}
+
 fn print_dictionary_info(di: &DictionaryInfo) {
     println!("Generating dictionary with following parameters:");
     println!("... Name: {}", di.name);
@@ -106,6 +231,8 @@ fn print_dictionary_info(di: &DictionaryInfo) {
     println!("... Input format: {}", di.input_format);
     println!("... Output format: {}", di.output_format);
     println!("... Output folder: {}", di.output_folder);
+    println!("... Time stamp: {}", di.time_stamp);
+    println!("... Version: {}", di.version);
     println!("... Links color: {}", di.links_color);
     println!("... Headings color: {}", di.headings_color);
     println!(
@@ -122,7 +249,42 @@ fn print_dictionary_info(di: &DictionaryInfo) {
         if let Some(idb) = di.inflections_db_path {
             idb
         } else {
-            "<will not generate>"
+            match di.input_format {
+                InputFormat::Dpd => "<built-in Stem & Pattern tables>",
+                InputFormat::Dps => "<will not generate>",
+            }
+        }
+    );
+    println!(
+        "... Render cache: {}",
+        di.render_cache_path.unwrap_or("<will not use>")
+    );
+    println!("... Collision report: {}", di.collision_report);
+    println!("... Mdd: {}", di.mdd);
+    println!(
+        "... Site url: {}",
+        di.site_url.unwrap_or("<default for local preview>")
+    );
+    println!("... Prefer headwords: {}", di.prefer_headwords);
+    println!(
+        "... Deconstruct: {}",
+        di.deconstruct_path.unwrap_or("<will not deconstruct>")
+    );
+    println!(
+        "... Sutta links: {}",
+        di.sutta_link_pattern.unwrap_or("<will not link>")
+    );
+    println!(
+        "... Frequency: {} (min {})",
+        di.frequency_path.unwrap_or("<will not show>"),
+        di.min_frequency
+    );
+    println!(
+        "... Package: {}",
+        if di.package {
+            "zip, tar.gz"
+        } else {
+            "<will not package>"
         }
     );
     println!();
@@ -1,49 +1,42 @@
+use crate::frequency::{load_frequency_table, with_frequencies};
 use crate::input::dpd::DpdPaliWord;
 use crate::input::input_format::InputFormat;
-use crate::{DictionaryBuilder, DictionaryFile, DictionaryInfo};
+use crate::output::output_generator::OutputGenerator;
+use crate::{DictionaryFiles, DictionaryInfo};
 use pls_core_extras::inflection_generator::InflectionGenerator;
 use pls_core_extras::logger::PlsLogger;
 use std::path::Path;
 
-mod input_parsers;
+pub(crate) mod input_parsers;
 mod output_generators;
 
-pub struct AjDict<'a> {
-    dict_info: &'a DictionaryInfo<'a>,
-    input_data_path: &'a Path,
-    logger: &'a dyn PlsLogger,
-}
+pub struct AjDictGenerator;
+
+impl OutputGenerator for AjDictGenerator {
+    fn name(&self) -> &'static str {
+        "ajdict"
+    }
+
+    fn description(&self) -> &'static str {
+        "Ven. Anandajyoti Dictionary format."
+    }
+
+    fn input_formats(&self) -> &'static [InputFormat] {
+        &[InputFormat::Dpd]
+    }
 
-impl<'a> DictionaryBuilder<'a> for AjDict<'a> {
-    fn new(
-        dict_info: &'a DictionaryInfo,
-        input_data_path: &'a Path,
-        _igen: &'a dyn InflectionGenerator,
-        logger: &'a dyn PlsLogger,
-    ) -> Self {
-        AjDict {
-            dict_info,
-            input_data_path,
-            logger,
-        }
+    fn options(&self) -> &'static [&'static str] {
+        &["concise", "frequency", "min-frequency"]
     }
 
-    fn build_files(&self) -> Result<Vec<DictionaryFile>, String> {
-        match self.dict_info.input_format {
-            InputFormat::Dpd => run_for_ods_type::<DpdPaliWord>(
-                self.input_data_path,
-                self.logger,
-                self.dict_info.concise,
-            ),
-            InputFormat::Dps => {
-                let msg = format!(
-                    "Dictionary format '{}' has not yet been implemented for '{}'.",
-                    self.dict_info.output_format, self.dict_info.short_name
-                );
-                self.logger.error(&msg);
-                Err(msg)
-            }
-        }
+    fn build_files(
+        &self,
+        dict_info: &DictionaryInfo,
+        input_data_path: &Path,
+        igen: &dyn InflectionGenerator,
+        logger: &dyn PlsLogger,
+    ) -> Result<DictionaryFiles, String> {
+        run_for_ods_type::<DpdPaliWord>(dict_info, input_data_path, igen, logger)
     }
 }
 
@@ -55,12 +48,20 @@ pub trait AjDictPaliWord {
 }
 
 pub fn run_for_ods_type<'a, T: 'a + serde::de::DeserializeOwned + AjDictPaliWord>(
+    dict_info: &DictionaryInfo,
     input_data_path: &Path,
+    igen: &dyn InflectionGenerator,
     logger: &dyn PlsLogger,
-    concise: bool,
-) -> Result<Vec<DictionaryFile>, String> {
-    let words = input_parsers::load_words::<T>(input_data_path, logger)?;
-    let sd_files = output_generators::create_dictionary(words, logger, concise)?;
+) -> Result<DictionaryFiles, String> {
+    let frequencies = load_frequency_table(dict_info.frequency_path, logger)?;
+    let words = with_frequencies(
+        input_parsers::load_words::<T>(input_data_path, logger)?,
+        |w| w.id(),
+        frequencies.as_ref(),
+        igen,
+        dict_info.min_frequency,
+    );
+    let sd_files = output_generators::create_dictionary(words, logger, dict_info.concise)?;
 
     Ok(sd_files)
 }
@@ -1,18 +1,26 @@
 use crate::ajdict::AjDictPaliWord;
-use crate::DictionaryFile;
+use crate::frequency::Frequency;
+use crate::{DictionaryData, DictionaryFile, DictionaryFiles};
 use pls_core_extras::logger::PlsLogger;
 
 fn create_dict_entries(
-    words: impl Iterator<Item = impl AjDictPaliWord>,
+    words: impl Iterator<Item = (impl AjDictPaliWord, Option<Frequency>)>,
     logger: &dyn PlsLogger,
-) -> Result<(Vec<String>, Vec<String>), String> {
+    concise: bool,
+) -> Result<Vec<String>, String> {
     logger.info(&"Creating dict entries.".to_string());
 
-    let mut dpd_entries: Vec<String> = Vec::new();
-    let mut cdpd_entries: Vec<String> = Vec::new();
-    for (n, word) in words.into_iter().enumerate() {
-        dpd_entries.push(word.word_data_entry()?);
-        cdpd_entries.push(word.concise_word_data_entry()?);
+    let mut dict_entries: Vec<String> = Vec::new();
+    for (n, (word, frequency)) in words.into_iter().enumerate() {
+        let freq = frequency
+            .map(|f| format!("; freq: {}", f.total))
+            .unwrap_or_default();
+        let entry = if concise {
+            word.concise_word_data_entry()?
+        } else {
+            word.word_data_entry()?
+        };
+        dict_entries.push(entry + &freq);
 
         if n % 1_000 == 0 && n != 0 {
             logger.info(&format!(
@@ -25,10 +33,10 @@ fn create_dict_entries(
 
     logger.info(&format!(
         "... done creating {} dict entries.",
-        dpd_entries.len()
+        dict_entries.len()
     ));
 
-    Ok((dpd_entries, cdpd_entries))
+    Ok(dict_entries)
 }
 
 fn create_txt_data(dict_entries: Vec<String>, logger: &dyn PlsLogger) -> Vec<u8> {
@@ -53,29 +61,23 @@ fn create_txt_data(dict_entries: Vec<String>, logger: &dyn PlsLogger) -> Vec<u8>
 }
 
 pub fn create_dictionary(
-    words: impl Iterator<Item = impl AjDictPaliWord>,
+    words: impl Iterator<Item = (impl AjDictPaliWord, Option<Frequency>)>,
     logger: &dyn PlsLogger,
     concise: bool,
-) -> Result<Vec<DictionaryFile>, String> {
-    let (dpd_entries, cdpd_entries) = create_dict_entries(words, logger)?;
-    let dpd_txt = create_txt_data(dpd_entries, logger);
-    let cdpd_txt = create_txt_data(cdpd_entries, logger);
+) -> Result<DictionaryFiles, String> {
+    let dict_entries = create_dict_entries(words, logger, concise)?;
+    let word_count = dict_entries.len();
+    let txt = create_txt_data(dict_entries, logger);
 
-    if concise {
-        Ok(vec![DictionaryFile {
+    Ok(DictionaryFiles {
+        word_count,
+        files: vec![DictionaryFile {
             extension: "ajd.txt".to_string(),
             bom: vec![0xEF, 0xBB, 0xBF],
-            data: cdpd_txt,
+            data: DictionaryData::InMemory(txt),
             can_be_empty: false,
-        }])
-    } else {
-        Ok(vec![DictionaryFile {
-            extension: "ajd.txt".to_string(),
-            bom: vec![0xEF, 0xBB, 0xBF],
-            data: dpd_txt,
-            can_be_empty: false,
-        }])
-    }
+        }],
+    })
 }
 
 #[cfg(test)]
@@ -0,0 +1,479 @@
+use crate::stardict::{create_stylesheet, ArticleReader, IdxEntry};
+use crate::{escape_xml, DictionaryData, DictionaryFile, DictionaryFiles, DictionaryInfo};
+use itertools::Itertools;
+use pls_core_extras::logger::PlsLogger;
+use regex::{Captures, Regex};
+use std::collections::{HashMap, HashSet};
+use tera::{Context, Tera};
+
+lazy_static! {
+    static ref TEMPLATES: Tera = {
+        let mut tera = Tera::default();
+        tera.add_raw_templates(vec![
+            ("info_plist", include_str!("templates/info.plist")),
+            ("front_matter", include_str!("templates/front_matter.xml")),
+            ("makefile", include_str!("templates/Makefile")),
+        ])
+        .expect("Unexpected failure adding template");
+        tera
+    };
+    static ref ARTICLE_BODY: Regex =
+        Regex::new(r"(?s)<body>\n?(.*)</body>").expect("is valid regex");
+    static ref BWORD_LINK: Regex = Regex::new(r#"href="bword://([^"]*)""#).expect("is valid regex");
+    static ref ELEMENT_ID: Regex =
+        Regex::new(r#"( id|href)="(#?)([^"]*)""#).expect("is valid regex");
+    static ref ENTITY: Regex = Regex::new(r"&(#?[0-9A-Za-z]+;)?").expect("is valid regex");
+    static ref VOID_ELEMENT: Regex =
+        Regex::new(r"<(br|hr|img|col|wbr|meta|link|input)\b([^>]*?)/?>").expect("is valid regex");
+}
+
+/// The source files go into the <short_name>.apple folder, named as in the Dictionary Development Kit.
+const APPLE_FOLDER: &str = "apple";
+
+const DICTIONARY_NAMESPACES: &str = "xmlns=\"http://www.w3.org/1999/xhtml\" xmlns:d=\"http://www.apple.com/DTDs/DictionaryService-1.0.rng\"";
+
+const FRONT_MATTER_ID: &str = "front_back_matter";
+
+const BUNDLE_ID_PREFIX: &str = "org.digitalpalitools.dictionary";
+
+struct AppleEntry {
+    id: String,
+    word: String,
+    index_words: Vec<String>,
+    data_offset: i32,
+    data_size: i32,
+}
+
+#[derive(Serialize)]
+struct InfoViewModel<'a> {
+    development_region: &'a str,
+    bundle_id: &'a str,
+    short_name: &'a str,
+    name: &'a str,
+    description: &'a str,
+    author: &'a str,
+    version: &'a str,
+    time_stamp: &'a str,
+    feedback_form_url: &'a str,
+    front_matter_id: &'a str,
+}
+
+///
+/// Entry ids are attribute values of type ID, i.e. they cannot have spaces, √ and such.
+///
+fn make_entry_id(short_name: &str, word: &str) -> String {
+    let word: String = word
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+        .collect();
+
+    format!("{}_{}", short_name, word)
+}
+
+fn create_entries(dict_info: &DictionaryInfo, idx_entries: Vec<IdxEntry>) -> Vec<AppleEntry> {
+    let mut ids = HashSet::new();
+    idx_entries
+        .into_iter()
+        .map(|e| {
+            let base_id = make_entry_id(dict_info.short_name, &e.word);
+            let id = (1..)
+                .map(|n| match n {
+                    1 => base_id.clone(),
+                    n => format!("{}_{}", base_id, n),
+                })
+                .find(|id| ids.insert(id.clone()))
+                .expect("Unexpected");
+
+            let index_words = std::iter::once(e.word.clone())
+                .chain(e.synonym_words)
+                .unique()
+                .collect();
+
+            AppleEntry {
+                id,
+                word: e.word,
+                index_words,
+                data_offset: e.data_offset,
+                data_size: e.data_size,
+            }
+        })
+        .collect()
+}
+
+///
+/// The articles are html, while entries have to be well formed XHTML. Named entities other than
+/// those of XML become character references, stray ampersands are escaped and void elements are
+/// closed.
+///
+fn make_xhtml(html: &str) -> String {
+    let html = ENTITY.replace_all(html, |caps: &Captures| {
+        match caps.get(1).map(|m| m.as_str()) {
+            Some("nbsp;") => "&#160;".to_string(),
+            Some(e)
+                if e.starts_with('#') || ["amp;", "lt;", "gt;", "quot;", "apos;"].contains(&e) =>
+            {
+                format!("&{}", e)
+            }
+            Some(e) => format!("&amp;{}", e),
+            None => "&amp;".to_string(),
+        }
+    });
+
+    VOID_ELEMENT.replace_all(&html, "<$1$2/>").to_string()
+}
+
+///
+/// Links to other entries become x-dictionary: urls and the ids within the article get the entry
+/// id as prefix, as the ids have to be unique across the whole dictionary.
+///
+fn rewrite_links(html: &str, entry_id: &str, ids: &HashMap<&str, &str>) -> String {
+    let html = BWORD_LINK.replace_all(html, |caps: &Captures| match ids.get(&caps[1]) {
+        Some(id) => format!("href=\"x-dictionary:r:{}\"", id),
+        None => format!("href=\"x-dictionary:d:{}\"", &caps[1]),
+    });
+
+    ELEMENT_ID
+        .replace_all(&html, |caps: &Captures| match (&caps[1], &caps[2]) {
+            (" id", _) => format!(" id=\"{}-{}\"", entry_id, &caps[3]),
+            (_, "#") => format!("href=\"#{}-{}\"", entry_id, &caps[3]),
+            _ => caps[0].to_string(),
+        })
+        .to_string()
+}
+
+fn create_dictionary_xml(
+    dict_info: &DictionaryInfo,
+    dict: &DictionaryData,
+    entries: &[AppleEntry],
+    logger: &dyn PlsLogger,
+) -> Result<Vec<u8>, String> {
+    logger.info(&format!("Creating {} apple entries.", entries.len()));
+
+    let mut ids: HashMap<&str, &str> = HashMap::new();
+    for entry in entries {
+        ids.entry(&entry.word).or_insert(&entry.id);
+    }
+    let mut reader = ArticleReader::new(dict)?;
+    let mut xml = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<d:dictionary {}>\n",
+        DICTIONARY_NAMESPACES
+    );
+    for (n, entry) in entries.iter().enumerate() {
+        let article = reader.read(entry.data_offset, entry.data_size)?;
+        let body = ARTICLE_BODY
+            .captures(&article)
+            .map(|caps| caps[1].to_string())
+            .unwrap_or(article);
+
+        xml.push_str(&format!(
+            "<d:entry id=\"{}\" d:title=\"{}\">\n",
+            entry.id,
+            escape_xml(&entry.word)
+        ));
+        for word in &entry.index_words {
+            xml.push_str(&format!(
+                "<d:index d:value=\"{}\" d:title=\"{}\"/>\n",
+                escape_xml(word),
+                escape_xml(&entry.word)
+            ));
+        }
+        xml.push_str(&format!(
+            "<h1>{}</h1>\n{}\n</d:entry>\n",
+            escape_xml(&entry.word),
+            rewrite_links(&make_xhtml(body.trim()), &entry.id, &ids)
+        ));
+
+        if n % 1_000 == 0 && n != 0 {
+            logger.info(&format!(
+                "... created {:05} apple entries, ending with '{}'.",
+                n, entry.word
+            ));
+        }
+    }
+
+    xml.push_str(&render_template(dict_info, "front_matter")?);
+    xml.push_str("</d:dictionary>\n");
+
+    logger.info(&format!(
+        "... done creating {} apple entries.",
+        entries.len()
+    ));
+    Ok(xml.into_bytes())
+}
+
+fn render_template(dict_info: &DictionaryInfo, template: &str) -> Result<String, String> {
+    let bundle_id = format!("{}.{}", BUNDLE_ID_PREFIX, dict_info.short_name);
+    let vm = InfoViewModel {
+        development_region: "English",
+        bundle_id: &bundle_id,
+        short_name: dict_info.short_name,
+        name: dict_info.name,
+        description: dict_info.description,
+        author: dict_info.author,
+        version: dict_info.version,
+        time_stamp: dict_info.time_stamp,
+        feedback_form_url: dict_info.feedback_form_url,
+        front_matter_id: FRONT_MATTER_ID,
+    };
+
+    let context = Context::from_serialize(&vm).map_err(|e| e.to_string())?;
+    TEMPLATES
+        .render(template, &context)
+        .map_err(|e| e.to_string())
+}
+
+///
+/// The Dictionary Development Kit sources go into the <short_name>.apple folder. The dictionary
+/// itself is built on macOS with its Makefile.
+///
+pub fn create_dictionary(
+    dict_info: &DictionaryInfo,
+    dict: &DictionaryData,
+    idx_entries: Vec<IdxEntry>,
+    logger: &dyn PlsLogger,
+) -> Result<DictionaryFiles, String> {
+    let entries = create_entries(dict_info, idx_entries);
+
+    let files = vec![
+        (
+            "MyDictionary.xml",
+            create_dictionary_xml(dict_info, dict, &entries, logger)?,
+        ),
+        (
+            "MyDictionary.css",
+            create_stylesheet(dict_info)?.into_bytes(),
+        ),
+        (
+            "MyInfo.plist",
+            render_template(dict_info, "info_plist")?.into_bytes(),
+        ),
+        (
+            "Makefile",
+            render_template(dict_info, "makefile")?.into_bytes(),
+        ),
+    ];
+
+    Ok(DictionaryFiles {
+        word_count: entries.len(),
+        files: files
+            .into_iter()
+            .map(|(name, data)| DictionaryFile {
+                extension: format!("{}/{}", APPLE_FOLDER, name),
+                bom: vec![],
+                data: DictionaryData::InMemory(data),
+                can_be_empty: false,
+            })
+            .collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::dpd::DpdPaliWord;
+    use crate::input::dps::DpsPaliWord;
+    use crate::resolve_file_in_manifest_dir;
+    use crate::stardict::create_articles;
+    use crate::tests::{TestInflectionGenerator, TestLogger};
+    use crate::{InputFormat, OutputFormat};
+    use test_case::test_case;
+
+    const D_NAMESPACE: &str = "http://www.apple.com/DTDs/DictionaryService-1.0.rng";
+
+    fn create_dict_info(input_format: &InputFormat) -> DictionaryInfo<'_> {
+        DictionaryInfo {
+            input_format,
+            ..crate::tests::create_dict_info(&OutputFormat(&crate::apple::AppleGenerator))
+        }
+    }
+
+    fn create_test_articles() -> (DictionaryData, Vec<IdxEntry>) {
+        let articles = [
+            (
+                "abala",
+                vec!["abalo", "abalaṃ"],
+                "<html><head><style>*{}</style></head><body>\n<a id=\"top-dpd\"></a><a href=\"#abala_1\">abala 1</a>&nbsp;(adj)<br>\n<a href=\"bword://√bal\">√bal</a> <a href=\"bword://ā √bal (derivation)\">ā √bal</a><h4 id=\"abala_1\">abala 1</h4></body></html>",
+            ),
+            (
+                "√bal",
+                vec!["bal"],
+                "<html><body>\n<a href=\"bword://abala\">abala</a> A & B</body></html>",
+            ),
+            ("√bal", vec![], "<html><body>\n√bal</body></html>"),
+        ];
+        let mut dict = Vec::new();
+        let mut idx_entries = Vec::new();
+        for (word, synonyms, html) in articles.iter() {
+            idx_entries.push(IdxEntry {
+                word: word.to_string(),
+                data_offset: dict.len() as i32,
+                data_size: html.len() as i32,
+                synonym_words: synonyms.iter().map(|s| s.to_string()).collect(),
+            });
+            dict.extend_from_slice(html.as_bytes());
+        }
+
+        (DictionaryData::InMemory(dict), idx_entries)
+    }
+
+    fn dictionary_file(files: &DictionaryFiles, name: &str) -> String {
+        let extension = format!("{}/{}", APPLE_FOLDER, name);
+        let file = files
+            .files
+            .iter()
+            .find(|f| f.extension == extension)
+            .unwrap_or_else(|| panic!("'{}' is missing", name));
+        match &file.data {
+            DictionaryData::InMemory(data) => String::from_utf8(data.clone()).expect("Unexpected"),
+            _ => panic!("Unexpected"),
+        }
+    }
+
+    ///
+    /// What build_dict.sh checks before it builds: well formed XML, unique entry ids, a title for
+    /// each entry and index values, with the front matter referred to from the plist.
+    ///
+    fn check_dictionary(files: &DictionaryFiles) -> usize {
+        let xml = dictionary_file(files, "MyDictionary.xml");
+        let doc = roxmltree::Document::parse(&xml).expect("MyDictionary.xml is not well formed");
+        let root = doc.root_element();
+        assert_eq!(root.tag_name().namespace(), Some(D_NAMESPACE));
+        assert_eq!(root.tag_name().name(), "dictionary");
+
+        let mut ids = HashSet::new();
+        let mut entry_count = 0;
+        for entry in root.children().filter(|n| n.is_element()) {
+            assert_eq!(entry.tag_name().namespace(), Some(D_NAMESPACE));
+            assert_eq!(entry.tag_name().name(), "entry");
+            let id = entry.attribute("id").expect("entry must have an id");
+            assert!(
+                id.chars().all(|c| c.is_alphanumeric() || c == '_'),
+                "'{}' is not a valid id",
+                id
+            );
+            assert!(ids.insert(id.to_string()), "'{}' is not unique", id);
+            assert!(entry.attribute((D_NAMESPACE, "title")).is_some());
+            entry_count += 1;
+
+            for index in entry
+                .descendants()
+                .filter(|n| n.tag_name().namespace() == Some(D_NAMESPACE))
+                .skip(1)
+            {
+                assert_eq!(index.tag_name().name(), "index");
+                assert_eq!(index.parent(), Some(entry));
+                assert!(index.attribute((D_NAMESPACE, "value")).is_some());
+            }
+            for element in entry.descendants().filter(|n| n.is_element()).skip(1) {
+                if let Some(element_id) = element.attribute("id") {
+                    assert!(
+                        ids.insert(element_id.to_string()),
+                        "'{}' is not unique",
+                        element_id
+                    );
+                }
+            }
+        }
+
+        let plist = dictionary_file(files, "MyInfo.plist");
+        let plist = roxmltree::Document::parse(&plist).expect("MyInfo.plist is not well formed");
+        let keys: Vec<&str> = plist
+            .descendants()
+            .filter(|n| n.has_tag_name("key"))
+            .filter_map(|n| n.text())
+            .collect();
+        for key in [
+            "CFBundleIdentifier",
+            "CFBundleName",
+            "DCSDictionaryFrontMatterReferenceID",
+        ]
+        .iter()
+        {
+            assert!(keys.contains(key), "'{}' is missing in MyInfo.plist", key);
+        }
+        assert!(ids.contains(FRONT_MATTER_ID));
+
+        entry_count
+    }
+
+    #[test_case("a&nbsp;b", "a&#160;b")]
+    #[test_case("a & b &amp; c", "a &amp; b &amp; c")]
+    #[test_case("&lt;&#8730;&mdash;", "&lt;&#8730;&amp;mdash;")]
+    #[test_case("a<br>b<br/>c<hr class=\"x\">", "a<br/>b<br/>c<hr class=\"x\"/>")]
+    fn make_xhtml_test(html: &str, expected: &str) {
+        assert_eq!(make_xhtml(html), expected);
+    }
+
+    #[test]
+    fn rewrite_links_test() {
+        let ids: HashMap<&str, &str> = vec![("√bal", "dpd__bal")].into_iter().collect();
+
+        let html = rewrite_links(
+            "<a id=\"top\" href=\"bword://√bal\">x</a><a href=\"#top\">y</a><a href=\"bword://bala\">z</a><a href=\"https://x.org\">w</a>",
+            "dpd_abala",
+            &ids,
+        );
+
+        assert_eq!(html, "<a id=\"dpd_abala-top\" href=\"x-dictionary:r:dpd__bal\">x</a><a href=\"#dpd_abala-top\">y</a><a href=\"x-dictionary:d:bala\">z</a><a href=\"https://x.org\">w</a>");
+    }
+
+    #[test]
+    fn create_dictionary_test() {
+        let (dict, idx_entries) = create_test_articles();
+
+        let files = create_dictionary(
+            &create_dict_info(&InputFormat::Dpd),
+            &dict,
+            idx_entries,
+            &TestLogger::new(),
+        )
+        .expect("Unexpected");
+
+        assert_eq!(files.word_count, 3);
+        assert_eq!(check_dictionary(&files), 4);
+        insta::assert_snapshot!(dictionary_file(&files, "MyDictionary.xml"));
+    }
+
+    #[test]
+    fn info_plist_test() {
+        let (dict, idx_entries) = create_test_articles();
+
+        let files = create_dictionary(
+            &create_dict_info(&InputFormat::Dpd),
+            &dict,
+            idx_entries,
+            &TestLogger::new(),
+        )
+        .expect("Unexpected");
+
+        insta::assert_snapshot!(dictionary_file(&files, "MyInfo.plist"));
+    }
+
+    #[test_case(InputFormat::Dpd, "Pali_English_Dictionary_10_rows-full.csv")]
+    #[test_case(InputFormat::Dps, "dps_sample.csv")]
+    fn dictionary_is_valid_test(input_format: InputFormat, file_name: &str) {
+        let path = resolve_file_in_manifest_dir(file_name).expect("must exist!");
+        let output_folder = tempfile::tempdir().expect("Unexpected");
+        let dict_info = DictionaryInfo {
+            input_data_path: path.to_str().expect("Unexpected"),
+            output_folder: output_folder.path().to_str().expect("Unexpected"),
+            ..create_dict_info(&input_format)
+        };
+        let igen = TestInflectionGenerator::new();
+        let (dict, idx_entries) = match input_format {
+            InputFormat::Dpd => {
+                create_articles::<DpdPaliWord>(&dict_info, &path, &igen, &TestLogger::new())
+            }
+            InputFormat::Dps => {
+                create_articles::<DpsPaliWord>(&dict_info, &path, &igen, &TestLogger::new())
+            }
+        }
+        .expect("Unexpected");
+        let idx_count = idx_entries.len();
+
+        let files = create_dictionary(&dict_info, &dict, idx_entries, &TestLogger::new())
+            .expect("Unexpected");
+
+        assert_eq!(check_dictionary(&files), idx_count + 1);
+    }
+}
@@ -0,0 +1,335 @@
+use crate::input::{create_csv_reader, make_group_id};
+use crate::{DictionaryData, DictionaryFile};
+use pls_core_extras::logger::PlsLogger;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::path::Path;
+use tera::{Context, Tera};
+
+lazy_static! {
+    static ref TEMPLATES: Tera = {
+        let mut tera = Tera::default();
+        tera.add_raw_templates(vec![("changelog", include_str!("templates/changelog.md"))])
+            .expect("Unexpected failure adding template");
+        tera
+    };
+}
+
+/// Rows are matched across versions by this column.
+const ID_FIELD: &str = "Pāli1";
+
+type WordFields = Map<String, Value>;
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum FieldChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+#[derive(Debug, Serialize)]
+struct FieldChange {
+    field: String,
+    kind: FieldChangeKind,
+    old: String,
+    new: String,
+}
+
+#[derive(Debug, Serialize)]
+struct ChangedWord {
+    id: String,
+    fields: Vec<FieldChange>,
+}
+
+#[derive(Debug, Serialize)]
+struct RenumberedWord {
+    old_id: String,
+    new_id: String,
+}
+
+#[derive(Debug, Serialize)]
+struct Changelog {
+    old_word_count: usize,
+    new_word_count: usize,
+    added: Vec<String>,
+    removed: Vec<String>,
+    renumbered: Vec<RenumberedWord>,
+    changed: Vec<ChangedWord>,
+}
+
+fn load_words<T: DeserializeOwned + Serialize>(
+    path: &Path,
+    logger: &dyn PlsLogger,
+) -> Result<Vec<(String, WordFields)>, String> {
+    let rdr = create_csv_reader(path, logger)?;
+
+    let mut words = Vec::new();
+    for (i, r) in rdr.into_deserialize::<T>().enumerate() {
+        match r {
+            Ok(w) => match serde_json::to_value(&w).map_err(|e| e.to_string())? {
+                Value::Object(fields) => {
+                    let id = fields
+                        .get(ID_FIELD)
+                        .and_then(Value::as_str)
+                        .unwrap_or_default()
+                        .to_string();
+                    words.push((id, fields));
+                }
+                _ => return Err(format!("Record #{} is not a structure.", i)),
+            },
+            Err(e) => logger.error(&format!(
+                "Unable to deserialize record #{}. Error: {}.",
+                i, e
+            )),
+        }
+    }
+
+    logger.info(&format!("... done loading words from {:?}.", &path));
+    Ok(words)
+}
+
+fn index_words<'a>(
+    words: &'a [(String, WordFields)],
+    logger: &dyn PlsLogger,
+) -> BTreeMap<&'a str, &'a WordFields> {
+    let mut index = BTreeMap::new();
+    for (id, fields) in words {
+        if index.contains_key(id.as_str()) {
+            logger.warning(&format!(
+                "Duplicate '{}' found, ignoring all but the first occurrence.",
+                id
+            ));
+        } else {
+            index.insert(id.as_str(), fields);
+        }
+    }
+
+    index
+}
+
+fn field_value(fields: &WordFields, field: &str) -> String {
+    fields
+        .get(field)
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_string()
+}
+
+fn diff_fields(old: &WordFields, new: &WordFields) -> Vec<FieldChange> {
+    old.keys()
+        .chain(new.keys().filter(|k| !old.contains_key(*k)))
+        .filter(|f| f.as_str() != ID_FIELD)
+        .filter_map(|f| {
+            let o = field_value(old, f);
+            let n = field_value(new, f);
+            let kind = match (o.is_empty(), n.is_empty()) {
+                _ if o == n => return None,
+                (true, false) => FieldChangeKind::Added,
+                (false, true) => FieldChangeKind::Removed,
+                _ => FieldChangeKind::Changed,
+            };
+
+            Some(FieldChange {
+                field: f.to_string(),
+                kind,
+                old: o,
+                new: n,
+            })
+        })
+        .collect()
+}
+
+///
+/// Takes the pairs of old and new ids that match, each id pairing up at most once.
+///
+fn take_pairs<'a>(
+    old_ids: &mut Vec<&'a str>,
+    new_ids: &mut Vec<&'a str>,
+    matches: impl Fn(&str, &str) -> bool,
+) -> Vec<(&'a str, &'a str)> {
+    let mut pairs = Vec::new();
+    new_ids.retain(
+        |new_id| match old_ids.iter().position(|old_id| matches(old_id, new_id)) {
+            Some(pos) => {
+                pairs.push((old_ids.remove(pos), *new_id));
+                false
+            }
+            None => true,
+        },
+    );
+
+    pairs
+}
+
+///
+/// Within a group of homonyms rows are matched by content before they are matched by id, so
+/// that a new homonym shifting the numbers of the others shows up as renumbered homonyms.
+///
+fn create_changelog(
+    old_words: &[(String, WordFields)],
+    new_words: &[(String, WordFields)],
+    logger: &dyn PlsLogger,
+) -> Changelog {
+    let old_index = index_words(old_words, logger);
+    let new_index = index_words(new_words, logger);
+
+    let mut groups: BTreeMap<String, (Vec<&str>, Vec<&str>)> = BTreeMap::new();
+    for id in old_index.keys() {
+        groups.entry(make_group_id(id)).or_default().0.push(id);
+    }
+    for id in new_index.keys() {
+        groups.entry(make_group_id(id)).or_default().1.push(id);
+    }
+
+    let same_content =
+        |old_id: &str, new_id: &str| diff_fields(old_index[old_id], new_index[new_id]).is_empty();
+
+    let mut added = Vec::new();
+    let mut removed = Vec::new();
+    let mut renumbered = Vec::new();
+    let mut changed = Vec::new();
+    for (_, (mut old_ids, mut new_ids)) in groups {
+        take_pairs(&mut old_ids, &mut new_ids, |o, n| {
+            o == n && same_content(o, n)
+        });
+        for (old_id, new_id) in take_pairs(&mut old_ids, &mut new_ids, same_content) {
+            renumbered.push(RenumberedWord {
+                old_id: old_id.to_string(),
+                new_id: new_id.to_string(),
+            });
+        }
+        for (old_id, new_id) in take_pairs(&mut old_ids, &mut new_ids, |o, n| o == n) {
+            changed.push(ChangedWord {
+                id: new_id.to_string(),
+                fields: diff_fields(old_index[old_id], new_index[new_id]),
+            });
+        }
+        added.extend(new_ids.into_iter().map(|id| id.to_string()));
+        removed.extend(old_ids.into_iter().map(|id| id.to_string()));
+    }
+
+    added.sort();
+    removed.sort();
+    renumbered.sort_by(|r1, r2| r1.new_id.cmp(&r2.new_id));
+    changed.sort_by(|c1, c2| c1.id.cmp(&c2.id));
+
+    Changelog {
+        old_word_count: old_words.len(),
+        new_word_count: new_words.len(),
+        added,
+        removed,
+        renumbered,
+        changed,
+    }
+}
+
+fn create_markdown(changelog: &Changelog) -> Result<Vec<u8>, String> {
+    let context = Context::from_serialize(changelog).map_err(|e| e.to_string())?;
+    let md = TEMPLATES
+        .render("changelog", &context)
+        .map_err(|e| e.to_string())?;
+
+    Ok(md.into_bytes())
+}
+
+pub fn create_changelog_files<T: DeserializeOwned + Serialize>(
+    old_data_path: &Path,
+    new_data_path: &Path,
+    logger: &dyn PlsLogger,
+) -> Result<Vec<DictionaryFile>, String> {
+    let old_words = load_words::<T>(old_data_path, logger)?;
+    let new_words = load_words::<T>(new_data_path, logger)?;
+
+    logger.info("Comparing words.");
+    let changelog = create_changelog(&old_words, &new_words, logger);
+    logger.info(&format!(
+        "... found {} added, {} removed, {} renumbered and {} changed words.",
+        changelog.added.len(),
+        changelog.removed.len(),
+        changelog.renumbered.len(),
+        changelog.changed.len()
+    ));
+
+    let md = create_markdown(&changelog)?;
+    let json = serde_json::to_vec_pretty(&changelog).map_err(|e| e.to_string())?;
+
+    Ok(vec![
+        DictionaryFile {
+            extension: "md".to_string(),
+            bom: vec![],
+            data: DictionaryData::InMemory(md),
+            can_be_empty: false,
+        },
+        DictionaryFile {
+            extension: "json".to_string(),
+            bom: vec![],
+            data: DictionaryData::InMemory(json),
+            can_be_empty: false,
+        },
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::dpd::DpdPaliWord;
+    use crate::resolve_file_in_manifest_dir;
+    use crate::tests::TestLogger;
+
+    fn create_test_changelog() -> Changelog {
+        let l = TestLogger::new();
+        let old_path = resolve_file_in_manifest_dir("Pali_English_Dictionary_10_rows-full.csv")
+            .expect("must exist!");
+        let new_path = resolve_file_in_manifest_dir("src/changelog/test_data/dpd_new.csv")
+            .expect("must exist!");
+        let old_words = load_words::<DpdPaliWord>(&old_path, &l).expect("unexpected");
+        let new_words = load_words::<DpdPaliWord>(&new_path, &l).expect("unexpected");
+
+        create_changelog(&old_words, &new_words, &l)
+    }
+
+    #[test]
+    fn create_changelog_test() {
+        let changelog = create_test_changelog();
+
+        assert_eq!(changelog.old_word_count, 13);
+        assert_eq!(changelog.new_word_count, 14);
+        assert_eq!(changelog.added, vec!["adhikāra 1", "ābādhin"]);
+        assert_eq!(changelog.removed, vec!["abahulīkata"]);
+        let renumbered: Vec<(&str, &str)> = changelog
+            .renumbered
+            .iter()
+            .map(|r| (r.old_id.as_str(), r.new_id.as_str()))
+            .collect();
+        assert_eq!(
+            renumbered,
+            vec![("adhikāra 1", "adhikāra 2"), ("adhikāra 10", "adhikāra 3")]
+        );
+        let changed: Vec<(&str, &str, &FieldChangeKind)> = changelog
+            .changed
+            .iter()
+            .flat_map(|c| {
+                c.fields
+                    .iter()
+                    .map(move |f| (c.id.as_str(), f.field.as_str(), &f.kind))
+            })
+            .collect();
+        assert_eq!(
+            changed,
+            vec![
+                ("abalabala", "Pāli Root", &FieldChangeKind::Added),
+                ("ābādha", "Meaning IN CONTEXT", &FieldChangeKind::Changed),
+            ]
+        );
+    }
+
+    #[test]
+    fn create_markdown_test() {
+        let md = create_markdown(&create_test_changelog()).expect("unexpected");
+
+        insta::assert_snapshot!(String::from_utf8(md).expect("unexpected"));
+    }
+}
@@ -0,0 +1,325 @@
+use crate::input::create_csv_reader;
+use crate::input::dpd::DpdPaliWord;
+use itertools::Itertools;
+use pls_core_extras::logger::PlsLogger;
+use regex::Regex;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+lazy_static! {
+    static ref HTML_TAG: Regex = Regex::new(r"<[^>]*>").expect("Malformed regex string");
+}
+
+/// Splits with more members than this are not worth showing.
+const MAX_MEMBERS: usize = 4;
+/// Shorter members (mostly prefixes and particles) match almost anywhere.
+const MIN_MEMBER_CHARS: usize = 2;
+/// Number of splits listed for a form.
+pub const MAX_SPLITS: usize = 3;
+
+///
+/// (joined, end of first member, start of second member), i.e. the first member + the
+/// second member read as joined at their junction. Plain concatenation is the first rule.
+///
+const SANDHI_RULES: &[(&str, &str, &str)] = &[
+    ("", "", ""),
+    // Vowel sandhi.
+    ("ā", "a", "a"),
+    ("ā", "a", "ā"),
+    ("ā", "ā", "a"),
+    ("ā", "ā", "ā"),
+    ("e", "a", "i"),
+    ("e", "a", "ī"),
+    ("o", "a", "u"),
+    ("o", "a", "ū"),
+    ("ī", "i", "i"),
+    ("ū", "u", "u"),
+    // Vowel elision.
+    ("", "a", ""),
+    ("", "ā", ""),
+    ("", "i", ""),
+    ("", "", "a"),
+    // Niggahīta before vowels and its assimilation to the following consonant.
+    ("m", "ṃ", ""),
+    ("ṅ", "ṃ", ""),
+    ("ñ", "ṃ", ""),
+    ("ṇ", "ṃ", ""),
+    ("n", "ṃ", ""),
+];
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Member {
+    /// The member as it reads before sandhi.
+    pub form: String,
+    /// The word to look up for the member, if it has an article.
+    pub target: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Split {
+    pub members: Vec<Member>,
+}
+
+///
+/// Splits forms into known members, i.e. headwords, their inflections and compound members,
+/// undoing sandhi at each junction.
+///
+#[derive(Default)]
+pub struct Deconstructor {
+    known: HashMap<String, Option<String>>,
+}
+
+impl Deconstructor {
+    pub fn new() -> Self {
+        Deconstructor {
+            known: HashMap::new(),
+        }
+    }
+
+    ///
+    /// NOTE: The first target added for a form wins, so add headwords before inflections.
+    ///
+    pub fn add_known(&mut self, form: &str, target: &str) {
+        self.insert(form, Some(target));
+    }
+
+    ///
+    /// A member without an article of its own, e.g. a compound member that is not a headword.
+    ///
+    pub fn add_member(&mut self, form: &str) {
+        self.insert(form, None);
+    }
+
+    fn insert(&mut self, form: &str, target: Option<&str>) {
+        if form.chars().count() >= MIN_MEMBER_CHARS {
+            self.known
+                .entry(form.to_string())
+                .or_insert_with(|| target.map(|t| t.to_string()));
+        }
+    }
+
+    pub fn is_known(&self, form: &str) -> bool {
+        self.known.contains_key(form)
+    }
+
+    pub fn deconstruct(&self, form: &str) -> Vec<Split> {
+        if self.is_known(form) {
+            return vec![];
+        }
+
+        self.split(form, MAX_MEMBERS)
+            .into_iter()
+            .filter(|members| members.len() > 1)
+            .unique()
+            .sorted_by(|m1, m2| {
+                m1.len()
+                    .cmp(&m2.len())
+                    .then(balance(m2).cmp(&balance(m1)))
+                    .then(m1.cmp(m2))
+            })
+            .take(MAX_SPLITS)
+            .map(|members| Split {
+                members: members
+                    .into_iter()
+                    .map(|m| Member {
+                        target: self.known[&m].clone(),
+                        form: m,
+                    })
+                    .collect(),
+            })
+            .collect()
+    }
+
+    fn split(&self, text: &str, max_members: usize) -> Vec<Vec<String>> {
+        let mut splits = Vec::new();
+        if self.is_known(text) {
+            splits.push(vec![text.to_string()]);
+        }
+        if max_members < 2 {
+            return splits;
+        }
+
+        for (i, _) in text.char_indices().skip(1) {
+            let (head, rest) = text.split_at(i);
+            for (joined, first_end, second_start) in SANDHI_RULES {
+                if !rest.starts_with(joined) {
+                    continue;
+                }
+
+                let first = format!("{}{}", head, first_end);
+                let second = format!("{}{}", second_start, &rest[joined.len()..]);
+                if second.is_empty() || !self.is_known(&first) {
+                    continue;
+                }
+
+                for mut tail in self.split(&second, max_members - 1) {
+                    tail.insert(0, first.clone());
+                    splits.push(tail);
+                }
+            }
+        }
+
+        splits
+    }
+}
+
+/// Prefers splits into long members over those with many short ones.
+fn balance(members: &[String]) -> usize {
+    members.iter().map(|m| m.chars().count().pow(2)).sum()
+}
+
+///
+/// The members of the Compound Construction column, e.g. 'na > a + bala' gives 'a' and 'bala'.
+///
+pub fn load_compound_members(
+    input_data_path: &Path,
+    logger: &dyn PlsLogger,
+) -> Result<Vec<String>, String> {
+    let rdr = create_csv_reader(input_data_path, logger)?;
+
+    let members = rdr
+        .into_deserialize::<DpdPaliWord>()
+        .filter_map(|r| r.ok())
+        .flat_map(|w| {
+            HTML_TAG
+                .replace_all(&w.compound_construction, " ")
+                .split('+')
+                .filter_map(|m| m.rsplit('>').next())
+                .flat_map(|m| m.split_whitespace())
+                .map(|m| m.to_string())
+                .collect::<Vec<String>>()
+        })
+        .sorted()
+        .dedup()
+        .collect();
+
+    Ok(members)
+}
+
+///
+/// Unique lower cased words of a text or word list.
+///
+pub fn load_forms(path: &Path, logger: &dyn PlsLogger) -> Result<Vec<String>, String> {
+    logger.info(&format!("Loading forms to deconstruct from {:?}.", path));
+    let text = fs::read_to_string(path).map_err(|e| e.to_string())?;
+
+    let forms: Vec<String> = text
+        .split(|c: char| !c.is_alphabetic())
+        .filter(|w| !w.is_empty())
+        .map(|w| w.to_lowercase())
+        .sorted()
+        .dedup()
+        .collect();
+
+    logger.info(&format!("... done loading {} forms.", forms.len()));
+    Ok(forms)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resolve_file_in_manifest_dir;
+    use crate::tests::TestLogger;
+    use test_case::test_case;
+
+    fn create_deconstructor() -> Deconstructor {
+        let mut d = Deconstructor::new();
+        for (form, target) in &[
+            ("tatra", "tatra"),
+            ("ayaṃ", "ayaṃ"),
+            ("evaṃ", "evaṃ"),
+            ("eva", "eva"),
+            ("ca", "ca"),
+            ("taṃ", "so"),
+            ("dhammo", "dhamma"),
+            ("dhamma", "dhamma"),
+            ("vinayo", "vinaya"),
+        ] {
+            d.add_known(form, target);
+        }
+
+        d
+    }
+
+    fn members(splits: &[Split]) -> Vec<Vec<&str>> {
+        splits
+            .iter()
+            .map(|s| s.members.iter().map(|m| m.form.as_str()).collect())
+            .collect()
+    }
+
+    #[test_case("tatrāyaṃ", &["tatra", "ayaṃ"]; "vowel sandhi")]
+    #[test_case("ceva", &["ca", "eva"]; "vowel elision")]
+    #[test_case("evameva", &["evaṃ", "eva"]; "niggahita before vowel")]
+    #[test_case("tañca", &["taṃ", "ca"]; "niggahita assimilation")]
+    #[test_case("dhammavinayo", &["dhamma", "vinayo"]; "compound")]
+    #[test_case("buddhavacana", &[]; "unknown members")]
+    fn deconstruct_test(form: &str, expected: &[&str]) {
+        let splits = create_deconstructor().deconstruct(form);
+
+        let expected: Vec<Vec<&str>> = if expected.is_empty() {
+            vec![]
+        } else {
+            vec![expected.to_vec()]
+        };
+        assert_eq!(
+            members(&splits).into_iter().take(1).collect::<Vec<_>>(),
+            expected
+        );
+    }
+
+    #[test]
+    fn deconstruct_links_to_targets_test() {
+        let splits = create_deconstructor().deconstruct("tañca");
+
+        assert_eq!(
+            splits[0].members[0],
+            Member {
+                form: "taṃ".to_string(),
+                target: Some("so".to_string())
+            }
+        );
+    }
+
+    #[test]
+    fn deconstruct_does_not_link_members_test() {
+        let mut d = create_deconstructor();
+        d.add_member("vacana");
+        d.add_member("dhamma");
+
+        let splits = d.deconstruct("dhammavacana");
+
+        assert_eq!(
+            splits[0].members,
+            vec![
+                Member {
+                    form: "dhamma".to_string(),
+                    target: Some("dhamma".to_string())
+                },
+                Member {
+                    form: "vacana".to_string(),
+                    target: None
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn known_forms_are_not_deconstructed_test() {
+        assert!(create_deconstructor().deconstruct("dhammo").is_empty());
+    }
+
+    #[test]
+    fn load_compound_members_test() {
+        let path = resolve_file_in_manifest_dir("Pali_English_Dictionary_10_rows-full.csv")
+            .expect("must exist!");
+
+        let members = load_compound_members(&path, &TestLogger::new()).expect("Unexpected");
+
+        assert_eq!(
+            members,
+            vec!["abala", "bahulīkata", "bala", "byāpajjha", "ca", "na"]
+        );
+    }
+}
@@ -0,0 +1,243 @@
+use crate::input::dpd::DpdPaliWord;
+use crate::input::{create_csv_reader, make_group_id, make_sort_key};
+use itertools::Itertools;
+use pls_core_extras::logger::PlsLogger;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+#[derive(Debug, Serialize)]
+pub struct DerivationNode {
+    pub pali1: String,
+    pub group_id: String,
+    pub pos: String,
+    pub meaning: String,
+    pub derivative: String,
+    pub suffix: String,
+    pub children: Vec<DerivationNode>,
+}
+
+///
+/// The words of a family, e.g. 'ā √bādh', nested under the words they are derived from.
+///
+#[derive(Debug, Serialize)]
+pub struct DerivationTree {
+    pub family: String,
+    pub nodes: Vec<DerivationNode>,
+}
+
+pub fn create_derivation_trees(
+    words: impl Iterator<Item = DpdPaliWord>,
+    logger: &dyn PlsLogger,
+) -> Vec<DerivationTree> {
+    let words: Vec<DpdPaliWord> = words
+        .filter(|w| !w.family.trim().is_empty())
+        .sorted_by_key(|w| make_sort_key(&w.pali1))
+        .collect();
+
+    let mut ids: HashMap<String, usize> = HashMap::new();
+    for (i, w) in words.iter().enumerate() {
+        ids.entry(w.pali1.clone()).or_insert(i);
+        ids.entry(make_group_id(&w.pali1)).or_insert(i);
+    }
+
+    let mut parents: Vec<Option<usize>> = words
+        .iter()
+        .enumerate()
+        .map(|(i, w)| find_parent(i, w, &words, &ids, logger))
+        .collect();
+    break_cycles(&mut parents, &words, logger);
+
+    let mut families: BTreeMap<&str, Vec<usize>> = BTreeMap::new();
+    let mut children: HashMap<usize, Vec<usize>> = HashMap::new();
+    for (i, p) in parents.iter().enumerate() {
+        match p {
+            Some(p) => children.entry(*p).or_default().push(i),
+            None => families.entry(words[i].family.trim()).or_default().push(i),
+        }
+    }
+
+    families
+        .into_iter()
+        .map(|(family, top)| DerivationTree {
+            family: family.to_string(),
+            nodes: top
+                .into_iter()
+                .map(|i| create_node(i, &words, &children))
+                .collect(),
+        })
+        .collect()
+}
+
+///
+/// The parent is the word named in Derived from or, failing that, the one the Base ends in.
+/// Roots (e.g. 'ā √bādh') and words from other families leave the word at the top of its tree.
+///
+fn find_parent(
+    i: usize,
+    word: &DpdPaliWord,
+    words: &[DpdPaliWord],
+    ids: &HashMap<String, usize>,
+    logger: &dyn PlsLogger,
+) -> Option<usize> {
+    let parent = match word.derived_from.trim() {
+        "" => word.base.rsplit('>').next().unwrap_or_default().trim(),
+        df => df,
+    };
+    if parent.is_empty() || parent.contains('√') || parent == word.family.trim() {
+        return None;
+    }
+
+    match ids.get(parent) {
+        Some(&p) if p != i && words[p].family.trim() == word.family.trim() => Some(p),
+        Some(_) => None,
+        None => {
+            logger.warning(&format!(
+                "Missing parent '{}' of '{}' in family '{}'.",
+                parent, word.pali1, word.family
+            ));
+            None
+        }
+    }
+}
+
+///
+/// Follows each parent chain once, cutting the cycle it runs into at the word the cycle
+/// closes on. Words leading into a cycle keep their parents.
+///
+fn break_cycles(parents: &mut [Option<usize>], words: &[DpdPaliWord], logger: &dyn PlsLogger) {
+    let mut on_path = vec![false; parents.len()];
+    let mut done = vec![false; parents.len()];
+    for i in 0..parents.len() {
+        let mut path = Vec::new();
+        let mut current = Some(i);
+        while let Some(c) = current {
+            if done[c] {
+                break;
+            }
+            if on_path[c] {
+                logger.warning(&format!(
+                    "Cycle in derivations of '{}' in family '{}'.",
+                    words[c].pali1, words[c].family
+                ));
+                parents[c] = None;
+                break;
+            }
+            on_path[c] = true;
+            path.push(c);
+            current = parents[c];
+        }
+
+        for c in path {
+            on_path[c] = false;
+            done[c] = true;
+        }
+    }
+}
+
+fn create_node(
+    i: usize,
+    words: &[DpdPaliWord],
+    children: &HashMap<usize, Vec<usize>>,
+) -> DerivationNode {
+    let w = &words[i];
+    DerivationNode {
+        pali1: w.pali1.clone(),
+        group_id: make_group_id(&w.pali1),
+        pos: w.pos.clone(),
+        meaning: if w.in_english.is_empty() {
+            w.buddhadatta.clone()
+        } else {
+            w.in_english.clone()
+        },
+        derivative: w.derivative.clone(),
+        suffix: w.suffix.clone(),
+        children: children
+            .get(&i)
+            .map(|cs| {
+                cs.iter()
+                    .map(|&c| create_node(c, words, children))
+                    .collect()
+            })
+            .unwrap_or_default(),
+    }
+}
+
+pub fn load_derivation_trees(
+    input_data_path: &Path,
+    logger: &dyn PlsLogger,
+) -> Result<Vec<DerivationTree>, String> {
+    let rdr = create_csv_reader(input_data_path, logger)?;
+
+    // NOTE: Records that fail to deserialize are reported when the words are loaded.
+    let trees = create_derivation_trees(
+        rdr.into_deserialize::<DpdPaliWord>().filter_map(|r| r.ok()),
+        logger,
+    );
+
+    logger.info(&format!(
+        "... done loading {} derivation trees.",
+        trees.len()
+    ));
+    Ok(trees)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resolve_file_in_manifest_dir;
+    use crate::tests::TestLogger;
+
+    fn create_words(specs: &[(&str, &str)]) -> Vec<DpdPaliWord> {
+        let path = resolve_file_in_manifest_dir("Pali_English_Dictionary_10_rows-full.csv")
+            .expect("must exist!");
+        let rdr = create_csv_reader(&path, &TestLogger::new()).expect("Unexpected");
+
+        rdr.into_deserialize::<DpdPaliWord>()
+            .zip(specs)
+            .map(|(w, (pali1, derived_from))| DpdPaliWord {
+                pali1: pali1.to_string(),
+                family: "f".to_string(),
+                derived_from: derived_from.to_string(),
+                base: "".to_string(),
+                ..w.expect("Unexpected")
+            })
+            .collect()
+    }
+
+    fn tree_words(nodes: &[DerivationNode]) -> Vec<String> {
+        nodes
+            .iter()
+            .map(|n| format!("{}[{}]", n.pali1, tree_words(&n.children).join(" ")))
+            .collect()
+    }
+
+    #[test]
+    fn load_derivation_trees_test() {
+        let path = resolve_file_in_manifest_dir("Pali_English_Dictionary_10_rows-full.csv")
+            .expect("must exist!");
+
+        let trees = load_derivation_trees(&path, &TestLogger::new()).expect("Unexpected");
+
+        insta::assert_yaml_snapshot!(trees);
+    }
+
+    #[test]
+    fn cycles_and_missing_parents_are_cut_test() {
+        let words = create_words(&[("a", "b"), ("b", "a"), ("c", "b"), ("d", "x")]);
+
+        let trees = create_derivation_trees(words.into_iter(), &TestLogger::new());
+
+        assert_eq!(trees.len(), 1);
+        assert_eq!(tree_words(&trees[0].nodes), vec!["a[b[c[]]]", "d[]"]);
+    }
+
+    #[test]
+    fn words_leading_into_cycles_keep_their_parents_test() {
+        let words = create_words(&[("a", "b"), ("b", "c"), ("c", "b")]);
+
+        let trees = create_derivation_trees(words.into_iter(), &TestLogger::new());
+
+        assert_eq!(trees.len(), 1);
+        assert_eq!(tree_words(&trees[0].nodes), vec!["b[a[] c[]]"]);
+    }
+}
@@ -0,0 +1,99 @@
+use crate::ajdict::input_parsers::load_words;
+use crate::ajdict::AjDictPaliWord;
+use crate::input::dpd::DpdPaliWord;
+use crate::input::input_format::InputFormat;
+use crate::output::output_generator::OutputGenerator;
+use crate::{DictionaryFiles, DictionaryInfo};
+use pls_core_extras::inflection_generator::InflectionGenerator;
+use pls_core_extras::logger::PlsLogger;
+use std::path::Path;
+
+mod output_generators;
+pub mod server;
+
+pub struct DictdGenerator;
+
+impl OutputGenerator for DictdGenerator {
+    fn name(&self) -> &'static str {
+        "dictd"
+    }
+
+    fn description(&self) -> &'static str {
+        "DICT protocol (dictd) database format."
+    }
+
+    fn input_formats(&self) -> &'static [InputFormat] {
+        &[InputFormat::Dpd]
+    }
+
+    fn options(&self) -> &'static [&'static str] {
+        &["concise"]
+    }
+
+    fn build_files(
+        &self,
+        dict_info: &DictionaryInfo,
+        input_data_path: &Path,
+        igen: &dyn InflectionGenerator,
+        logger: &dyn PlsLogger,
+    ) -> Result<DictionaryFiles, String> {
+        run_for_ods_type::<DpdPaliWord>(dict_info, input_data_path, igen, logger)
+    }
+}
+
+///
+/// The entries are the text of the AjDict output.
+///
+pub fn run_for_ods_type<'a, T: 'a + serde::de::DeserializeOwned + AjDictPaliWord>(
+    dict_info: &DictionaryInfo,
+    input_data_path: &Path,
+    igen: &dyn InflectionGenerator,
+    logger: &dyn PlsLogger,
+) -> Result<DictionaryFiles, String> {
+    let words = load_words::<T>(input_data_path, logger)?;
+    let dictd_files = output_generators::create_dictionary(dict_info, words, igen, logger)?;
+
+    Ok(dictd_files)
+}
+
+const B64_ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+///
+/// Offsets and lengths in the .index are numbers written in base64 digits, e.g. 0 is 'A'.
+///
+pub(crate) fn encode_b64(n: u64) -> String {
+    let mut digits = vec![B64_ALPHABET[(n % 64) as usize]];
+    let mut n = n / 64;
+    while n > 0 {
+        digits.push(B64_ALPHABET[(n % 64) as usize]);
+        n /= 64;
+    }
+    digits.reverse();
+
+    String::from_utf8(digits).expect("The alphabet is ascii")
+}
+
+pub(crate) fn decode_b64(s: &str) -> Result<u64, String> {
+    s.bytes().try_fold(0u64, |acc, b| {
+        B64_ALPHABET
+            .iter()
+            .position(|&a| a == b)
+            .map(|d| acc * 64 + d as u64)
+            .ok_or_else(|| format!("Invalid base64 number '{}'", s))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case(0, "A")]
+    #[test_case(63, "/")]
+    #[test_case(64, "BA")]
+    #[test_case(58315, "OPL")]
+    fn b64_test(n: u64, s: &str) {
+        assert_eq!(encode_b64(n), s);
+        assert_eq!(decode_b64(s), Ok(n));
+    }
+}
@@ -0,0 +1,423 @@
+use crate::ajdict::AjDictPaliWord;
+use crate::dictd::encode_b64;
+use crate::input::make_group_id;
+use crate::{DictionaryData, DictionaryFile, DictionaryFiles, DictionaryInfo};
+use chrono::DateTime;
+use flate2::{Compress, Compression, Crc, FlushCompress, Status};
+use itertools::Itertools;
+use pls_core_extras::inflection_generator::InflectionGenerator;
+use pls_core_extras::logger::PlsLogger;
+
+/// The uncompressed size of the chunks of a .dict.dz, as chosen by dictzip.
+const DICTZIP_CHUNK_SIZE: usize = 58315;
+
+const DEFINITION_INDENT: &str = "   ";
+
+#[derive(Debug)]
+struct IndexEntry {
+    headword: String,
+    offset: usize,
+    length: usize,
+}
+
+///
+/// Appends an entry, i.e. the headword followed by the indented definition lines, to the
+/// .dict data and indexes it under the headword and its synonyms.
+///
+fn append_entry(
+    dict: &mut String,
+    index: &mut Vec<IndexEntry>,
+    headword: &str,
+    lines: &[String],
+    synonyms: impl Iterator<Item = String>,
+) {
+    let offset = dict.len();
+    dict.push_str(headword);
+    dict.push('\n');
+    for line in lines {
+        if !line.is_empty() {
+            dict.push_str(DEFINITION_INDENT);
+        }
+        dict.push_str(line);
+        dict.push('\n');
+    }
+    let length = dict.len() - offset;
+
+    index.push(IndexEntry {
+        headword: headword.to_string(),
+        offset,
+        length,
+    });
+    for synonym in synonyms {
+        index.push(IndexEntry {
+            headword: synonym,
+            offset,
+            length,
+        });
+    }
+}
+
+///
+/// The entries dictd reads the database name and information from.
+///
+fn create_header_entries(dict_info: &DictionaryInfo) -> Vec<(&'static str, Vec<String>)> {
+    vec![
+        (
+            "00-database-info",
+            vec![
+                dict_info.description.to_string(),
+                "".to_string(),
+                format!("Author: {}", dict_info.author),
+                format!("Version: {}", dict_info.version),
+            ],
+        ),
+        ("00-database-short", vec![dict_info.name.to_string()]),
+        ("00-database-utf8", vec![]),
+    ]
+}
+
+fn create_dict_data(
+    dict_info: &DictionaryInfo,
+    words: impl Iterator<Item = impl AjDictPaliWord>,
+    igen: &dyn InflectionGenerator,
+    logger: &dyn PlsLogger,
+) -> Result<(String, Vec<IndexEntry>, usize), String> {
+    logger.info("Creating dictd entries.");
+
+    let mut dict = String::new();
+    let mut index: Vec<IndexEntry> = Vec::new();
+    for (headword, lines) in create_header_entries(dict_info) {
+        append_entry(&mut dict, &mut index, headword, &lines, std::iter::empty());
+    }
+
+    let word_groups = words
+        .sorted_by(|w1, w2| Ord::cmp(&w1.sort_key(), &w2.sort_key()))
+        .group_by(|w| make_group_id(w.id()));
+
+    let mut word_count = 0;
+    for (group_id, words) in &word_groups {
+        let words: Vec<_> = words.collect();
+        let lines = words
+            .iter()
+            .map(|w| {
+                if dict_info.concise {
+                    w.concise_word_data_entry()
+                } else {
+                    w.word_data_entry()
+                }
+            })
+            .collect::<Result<Vec<String>, String>>()?;
+        let synonyms = words
+            .iter()
+            .flat_map(|w| igen.generate_all_inflections(w.id()))
+            .filter(|i| i != &group_id)
+            .unique();
+
+        append_entry(&mut dict, &mut index, &group_id, &lines, synonyms);
+        word_count += 1;
+
+        if word_count % 1_000 == 0 {
+            logger.info(&format!(
+                "... created {:05} dictd entries, ending with '{}'.",
+                word_count, group_id
+            ));
+        }
+    }
+
+    logger.info(&format!("... done creating {} dictd entries.", word_count));
+    Ok((dict, index, word_count))
+}
+
+///
+/// dictd ignores case and, unless the database is 00-database-allchars, everything other
+/// than letters, digits and spaces when searching the index.
+///
+pub(crate) fn make_dictd_sort_key(headword: &str) -> String {
+    headword
+        .to_lowercase()
+        .chars()
+        .filter(|c| c.is_alphanumeric() || c.is_whitespace())
+        .collect()
+}
+
+fn create_index(mut index: Vec<IndexEntry>, logger: &dyn PlsLogger) -> Vec<u8> {
+    logger.info(&format!("Creating {} index entries.", index.len()));
+
+    index.sort_by_cached_key(|e| {
+        (
+            make_dictd_sort_key(&e.headword),
+            e.headword.clone(),
+            e.offset,
+        )
+    });
+    index.dedup_by(|e1, e2| e1.headword == e2.headword && e1.offset == e2.offset);
+
+    index
+        .iter()
+        .map(|e| {
+            format!(
+                "{}\t{}\t{}\n",
+                e.headword,
+                encode_b64(e.offset as u64),
+                encode_b64(e.length as u64)
+            )
+        })
+        .collect::<String>()
+        .into_bytes()
+}
+
+fn compress_chunk(
+    compress: &mut Compress,
+    chunk: &[u8],
+    flush: FlushCompress,
+) -> Result<Vec<u8>, String> {
+    let mut compressed = Vec::with_capacity(chunk.len() + chunk.len() / 10 + 64);
+    let start = compress.total_in();
+    loop {
+        let consumed = (compress.total_in() - start) as usize;
+        let status = compress
+            .compress_vec(&chunk[consumed..], &mut compressed, flush)
+            .map_err(|e| e.to_string())?;
+
+        let done = match flush {
+            FlushCompress::Finish => status == Status::StreamEnd,
+            _ => {
+                (compress.total_in() - start) as usize == chunk.len()
+                    && compressed.len() < compressed.capacity()
+            }
+        };
+        if done {
+            return Ok(compressed);
+        }
+        compressed.reserve(compressed.capacity().max(64));
+    }
+}
+
+///
+/// A gzip file whose chunks are flushed so that each can be decompressed on its own, with
+/// the chunk sizes in an 'RA' extra field for random access.
+///
+/// See https://linux.die.net/man/1/dictzip
+///
+fn create_dictzip(data: &[u8], mtime: u32) -> Result<Vec<u8>, String> {
+    let mut chunks: Vec<&[u8]> = data.chunks(DICTZIP_CHUNK_SIZE).collect();
+    if chunks.is_empty() {
+        chunks.push(&[]);
+    }
+
+    let mut compress = Compress::new(Compression::best(), false);
+    let mut compressed_chunks: Vec<Vec<u8>> = Vec::with_capacity(chunks.len());
+    for (i, chunk) in chunks.iter().enumerate() {
+        let flush = if i + 1 == chunks.len() {
+            FlushCompress::Finish
+        } else {
+            FlushCompress::Full
+        };
+        compressed_chunks.push(compress_chunk(&mut compress, chunk, flush)?);
+    }
+
+    let ra_length = 6 + 2 * compressed_chunks.len();
+    if ra_length + 4 > u16::MAX as usize {
+        return Err("Dictionary is too large for dictzip".to_string());
+    }
+
+    let mut dz: Vec<u8> = vec![0x1f, 0x8b, 0x08, 0x04];
+    dz.extend_from_slice(&mtime.to_le_bytes());
+    dz.extend_from_slice(&[0x02, 0x03]);
+    dz.extend_from_slice(&((ra_length + 4) as u16).to_le_bytes());
+    dz.extend_from_slice(b"RA");
+    dz.extend_from_slice(&(ra_length as u16).to_le_bytes());
+    dz.extend_from_slice(&1u16.to_le_bytes());
+    dz.extend_from_slice(&(DICTZIP_CHUNK_SIZE as u16).to_le_bytes());
+    dz.extend_from_slice(&(compressed_chunks.len() as u16).to_le_bytes());
+    for c in &compressed_chunks {
+        dz.extend_from_slice(&(c.len() as u16).to_le_bytes());
+    }
+    for c in &compressed_chunks {
+        dz.extend_from_slice(c);
+    }
+
+    let mut crc = Crc::new();
+    crc.update(data);
+    dz.extend_from_slice(&crc.sum().to_le_bytes());
+    dz.extend_from_slice(&(data.len() as u32).to_le_bytes());
+
+    Ok(dz)
+}
+
+///
+/// See https://linux.die.net/man/8/dictd and the dictfmt and dictzip tools.
+///
+pub fn create_dictionary(
+    dict_info: &DictionaryInfo,
+    words: impl Iterator<Item = impl AjDictPaliWord>,
+    igen: &dyn InflectionGenerator,
+    logger: &dyn PlsLogger,
+) -> Result<DictionaryFiles, String> {
+    let (dict, index, word_count) = create_dict_data(dict_info, words, igen, logger)?;
+    let index = create_index(index, logger);
+    let mtime = DateTime::parse_from_rfc3339(dict_info.time_stamp)
+        .map_err(|e| e.to_string())?
+        .timestamp();
+    let dict = dict.into_bytes();
+    let dict_dz = create_dictzip(&dict, mtime as u32)?;
+
+    Ok(DictionaryFiles {
+        word_count,
+        files: vec![
+            DictionaryFile {
+                extension: "index".to_string(),
+                bom: vec![],
+                data: DictionaryData::InMemory(index),
+                can_be_empty: false,
+            },
+            DictionaryFile {
+                extension: "dict".to_string(),
+                bom: vec![],
+                data: DictionaryData::InMemory(dict),
+                can_be_empty: false,
+            },
+            DictionaryFile {
+                extension: "dict.dz".to_string(),
+                bom: vec![],
+                data: DictionaryData::InMemory(dict_dz),
+                can_be_empty: false,
+            },
+        ],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::input_format::InputFormat;
+    use crate::tests::{TestInflectionGenerator, TestLogger};
+    use crate::OutputFormat;
+    use flate2::read::GzDecoder;
+    use flate2::{Decompress, FlushDecompress};
+    use std::io::Read;
+
+    struct TestPaliWord {
+        id: String,
+        text: String,
+    }
+
+    impl AjDictPaliWord for TestPaliWord {
+        fn id(&self) -> &str {
+            &self.id
+        }
+
+        fn sort_key(&self) -> String {
+            self.id.clone()
+        }
+
+        fn concise_word_data_entry(&self) -> Result<String, String> {
+            Ok(format!("{} (concise)", self.text))
+        }
+
+        fn word_data_entry(&self) -> Result<String, String> {
+            Ok(self.text.clone())
+        }
+    }
+
+    fn create_dict_info(input_format: &InputFormat) -> DictionaryInfo<'_> {
+        DictionaryInfo {
+            input_format,
+            concise: true,
+            ..crate::tests::create_dict_info(&OutputFormat(&crate::dictd::DictdGenerator))
+        }
+    }
+
+    #[test]
+    fn create_index_test() {
+        let mut dict = String::new();
+        let mut index = Vec::new();
+        append_entry(
+            &mut dict,
+            &mut index,
+            "Ābādha",
+            &["a".to_string()],
+            std::iter::empty(),
+        );
+        append_entry(
+            &mut dict,
+            &mut index,
+            "abala",
+            &["b".to_string(), "c".to_string()],
+            vec!["abalo".to_string(), "a-bala".to_string()].into_iter(),
+        );
+
+        let index = create_index(index, &TestLogger::new());
+
+        assert_eq!(dict, "Ābādha\n   a\nabala\n   b\n   c\n");
+        assert_eq!(
+            String::from_utf8(index).expect("Unexpected"),
+            "a-bala\tO\tQ\nabala\tO\tQ\nabalo\tO\tQ\nĀbādha\tA\tO\n"
+        );
+    }
+
+    #[test]
+    fn create_dict_data_test() {
+        let words = vec![
+            TestPaliWord {
+                id: "abala 2".to_string(),
+                text: "abala 2; masc; weakness".to_string(),
+            },
+            TestPaliWord {
+                id: "abala 1".to_string(),
+                text: "abala 1; adj; weak".to_string(),
+            },
+        ];
+        let input_format = InputFormat::Dpd;
+        let dict_info = create_dict_info(&input_format);
+
+        let (dict, index, word_count) = create_dict_data(
+            &dict_info,
+            words.into_iter(),
+            &TestInflectionGenerator::new(),
+            &TestLogger::new(),
+        )
+        .expect("Unexpected");
+
+        assert_eq!(word_count, 1);
+        let index = create_index(index, &TestLogger::new());
+        insta::assert_snapshot!(format!(
+            "{}---\n{}",
+            dict,
+            String::from_utf8(index).expect("Unexpected")
+        ));
+    }
+
+    #[test]
+    fn create_dictzip_test() {
+        let data: Vec<u8> = (0..3 * DICTZIP_CHUNK_SIZE as u32)
+            .map(|i| (i.wrapping_mul(2_654_435_761) >> 24) as u8 % 16 + b'a')
+            .collect();
+
+        let dz = create_dictzip(&data, 1_609_459_200).expect("Unexpected");
+
+        let mut inflated = Vec::new();
+        GzDecoder::new(&dz[..])
+            .read_to_end(&mut inflated)
+            .expect("Unexpected");
+        assert_eq!(inflated, data);
+
+        let chunk_count = u16::from_le_bytes([dz[20], dz[21]]) as usize;
+        let chunk_sizes: Vec<usize> = (0..chunk_count)
+            .map(|i| u16::from_le_bytes([dz[22 + 2 * i], dz[23 + 2 * i]]) as usize)
+            .collect();
+        assert_eq!(&dz[12..14], b"RA");
+        assert_eq!(chunk_count, 3);
+
+        let start = 22 + 2 * chunk_count + chunk_sizes[0];
+        let mut chunk = Vec::with_capacity(DICTZIP_CHUNK_SIZE);
+        Decompress::new(false)
+            .decompress_vec(
+                &dz[start..start + chunk_sizes[1]],
+                &mut chunk,
+                FlushDecompress::Sync,
+            )
+            .expect("Unexpected");
+        assert_eq!(chunk, &data[DICTZIP_CHUNK_SIZE..2 * DICTZIP_CHUNK_SIZE]);
+    }
+}
@@ -0,0 +1,423 @@
+use crate::dictd::decode_b64;
+use crate::dictd::output_generators::make_dictd_sort_key;
+use flate2::read::GzDecoder;
+use pls_core_extras::logger::PlsLogger;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::path::Path;
+
+const STRATEGIES: &[(&str, &str)] = &[
+    ("exact", "Match headwords exactly"),
+    ("prefix", "Match prefixes"),
+];
+
+struct DictIndexEntry {
+    headword: String,
+    key: String,
+    offset: usize,
+    length: usize,
+}
+
+///
+/// A database built by the dictd output format, i.e. a .index and its .dict or .dict.dz.
+///
+pub struct DictDatabase {
+    name: String,
+    description: String,
+    index: Vec<DictIndexEntry>,
+    dict: Vec<u8>,
+}
+
+impl DictDatabase {
+    pub fn load(index_path: &Path, dict_path: &Path) -> Result<Self, String> {
+        let index = std::fs::read_to_string(index_path).map_err(|e| e.to_string())?;
+        let dict = std::fs::read(dict_path).map_err(|e| e.to_string())?;
+        let dict = if dict_path.extension().map(|e| e == "dz").unwrap_or(false) {
+            let mut inflated = Vec::new();
+            GzDecoder::new(&dict[..])
+                .read_to_end(&mut inflated)
+                .map_err(|e| e.to_string())?;
+            inflated
+        } else {
+            dict
+        };
+        let name = index_path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .ok_or_else(|| format!("Invalid index file name {:?}", index_path))?;
+
+        Self::new(name, &index, dict)
+    }
+
+    fn new(name: &str, index: &str, dict: Vec<u8>) -> Result<Self, String> {
+        let index = index
+            .lines()
+            .map(|l| {
+                let fields: Vec<&str> = l.split('\t').collect();
+                if fields.len() != 3 {
+                    return Err(format!("Malformed index line '{}'", l));
+                }
+                let offset = decode_b64(fields[1])? as usize;
+                let length = decode_b64(fields[2])? as usize;
+                if offset + length > dict.len() {
+                    return Err(format!("Index line '{}' is beyond the dict", l));
+                }
+                Ok(DictIndexEntry {
+                    headword: fields[0].to_string(),
+                    key: make_dictd_sort_key(fields[0]),
+                    offset,
+                    length,
+                })
+            })
+            .collect::<Result<Vec<DictIndexEntry>, String>>()?;
+
+        let mut db = DictDatabase {
+            name: name.to_string(),
+            description: name.to_string(),
+            index,
+            dict,
+        };
+        if let Some(short) = db.lookup("00-database-short").first() {
+            db.description = strip_headword(short).trim().to_string();
+        }
+
+        Ok(db)
+    }
+
+    fn definition(&self, e: &DictIndexEntry) -> String {
+        String::from_utf8_lossy(&self.dict[e.offset..e.offset + e.length]).to_string()
+    }
+
+    ///
+    /// The definitions of the entries the word is indexed under, each once.
+    ///
+    fn lookup(&self, word: &str) -> Vec<String> {
+        let key = make_dictd_sort_key(word);
+        let mut offsets: Vec<usize> = Vec::new();
+        let mut definitions = Vec::new();
+        for e in self.index.iter().filter(|e| e.key == key) {
+            if !offsets.contains(&e.offset) {
+                offsets.push(e.offset);
+                definitions.push(self.definition(e));
+            }
+        }
+
+        definitions
+    }
+
+    fn find_matches(&self, strategy: &str, word: &str) -> Option<Vec<&str>> {
+        let key = make_dictd_sort_key(word);
+        let is_match: Box<dyn Fn(&DictIndexEntry) -> bool> = match strategy {
+            "exact" | "." => Box::new(|e| e.key == key),
+            "prefix" => Box::new(|e| e.key.starts_with(&key)),
+            _ => return None,
+        };
+
+        let mut matches: Vec<&str> = Vec::new();
+        for e in self
+            .index
+            .iter()
+            .filter(|e| !e.headword.starts_with("00-database-"))
+        {
+            if is_match(e) && !matches.contains(&e.headword.as_str()) {
+                matches.push(&e.headword);
+            }
+        }
+
+        Some(matches)
+    }
+}
+
+///
+/// Splits a command line into words, where double or single quoted words may contain spaces.
+///
+fn tokenize(line: &str) -> Result<Vec<String>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = line.trim().chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+
+        let mut token = String::new();
+        if c == '"' || c == '\'' {
+            chars.next();
+            loop {
+                match chars.next() {
+                    Some('\\') => token.extend(chars.next()),
+                    Some(q) if q == c => break,
+                    Some(x) => token.push(x),
+                    None => return Err("Unterminated quoted string".to_string()),
+                }
+            }
+        } else {
+            while let Some(&x) = chars.peek() {
+                if x.is_whitespace() {
+                    break;
+                }
+                token.push(x);
+                chars.next();
+            }
+        }
+        tokens.push(token);
+    }
+
+    Ok(tokens)
+}
+
+fn write_text(out: &mut impl Write, text: &str) -> std::io::Result<()> {
+    for line in text.lines() {
+        if line.starts_with('.') {
+            write!(out, ".")?;
+        }
+        write!(out, "{}\r\n", line)?;
+    }
+    write!(out, ".\r\n")
+}
+
+///
+/// The text of a definition without the headword line dictfmt starts it with.
+///
+fn strip_headword(definition: &str) -> &str {
+    definition.split_once('\n').map(|(_, t)| t).unwrap_or("")
+}
+
+fn is_database(db: &DictDatabase, database: &str) -> bool {
+    database == "*" || database == "!" || database == db.name
+}
+
+fn handle_command(
+    db: &DictDatabase,
+    words: &[String],
+    out: &mut impl Write,
+) -> std::io::Result<bool> {
+    let command = words[0].to_uppercase();
+    let args: Vec<&str> = words[1..].iter().map(|w| w.as_str()).collect();
+    match (command.as_str(), args.as_slice()) {
+        ("DEFINE", [database, word]) => {
+            if !is_database(db, database) {
+                write!(
+                    out,
+                    "550 Invalid database, use \"SHOW DB\" for list of databases\r\n"
+                )?;
+                return Ok(true);
+            }
+            let definitions = db.lookup(word);
+            if definitions.is_empty() {
+                write!(out, "552 No match\r\n")?;
+                return Ok(true);
+            }
+            write!(out, "150 {} definitions retrieved\r\n", definitions.len())?;
+            for d in &definitions {
+                write!(
+                    out,
+                    "151 \"{}\" {} \"{}\"\r\n",
+                    word, db.name, db.description
+                )?;
+                write_text(out, d)?;
+            }
+            write!(out, "250 ok\r\n")?;
+        }
+        ("MATCH", [database, strategy, word]) => {
+            if !is_database(db, database) {
+                write!(
+                    out,
+                    "550 Invalid database, use \"SHOW DB\" for list of databases\r\n"
+                )?;
+                return Ok(true);
+            }
+            match db.find_matches(strategy, word) {
+                None => write!(
+                    out,
+                    "551 Invalid strategy, use \"SHOW STRAT\" for a list of strategies\r\n"
+                )?,
+                Some(matches) if matches.is_empty() => write!(out, "552 No match\r\n")?,
+                Some(matches) => {
+                    write!(out, "152 {} matches found\r\n", matches.len())?;
+                    let text = matches
+                        .iter()
+                        .map(|m| format!("{} \"{}\"\n", db.name, m))
+                        .collect::<String>();
+                    write_text(out, &text)?;
+                    write!(out, "250 ok\r\n")?;
+                }
+            }
+        }
+        ("SHOW", [what]) => match what.to_uppercase().as_str() {
+            "DB" | "DATABASES" => {
+                write!(out, "110 1 databases present\r\n")?;
+                write_text(out, &format!("{} \"{}\"\n", db.name, db.description))?;
+                write!(out, "250 ok\r\n")?;
+            }
+            "STRAT" | "STRATEGIES" => {
+                write!(out, "111 {} strategies present\r\n", STRATEGIES.len())?;
+                let text = STRATEGIES
+                    .iter()
+                    .map(|(s, d)| format!("{} \"{}\"\n", s, d))
+                    .collect::<String>();
+                write_text(out, &text)?;
+                write!(out, "250 ok\r\n")?;
+            }
+            _ => write!(out, "501 Syntax error, illegal parameters\r\n")?,
+        },
+        ("SHOW", [what, database]) if what.to_uppercase() == "INFO" => {
+            if !is_database(db, database) {
+                write!(
+                    out,
+                    "550 Invalid database, use \"SHOW DB\" for list of databases\r\n"
+                )?;
+                return Ok(true);
+            }
+            write!(out, "112 database information follows\r\n")?;
+            let info = db.lookup("00-database-info");
+            write_text(
+                out,
+                &info.iter().map(|i| strip_headword(i)).collect::<String>(),
+            )?;
+            write!(out, "250 ok\r\n")?;
+        }
+        ("CLIENT", _) => write!(out, "250 ok\r\n")?,
+        ("HELP", []) => {
+            write!(out, "113 help text follows\r\n")?;
+            write_text(
+                out,
+                "DEFINE database word\nMATCH database strategy word\nSHOW DB\nSHOW STRAT\nSHOW INFO database\nCLIENT info\nHELP\nQUIT\n",
+            )?;
+            write!(out, "250 ok\r\n")?;
+        }
+        ("QUIT", []) => {
+            write!(out, "221 bye\r\n")?;
+            return Ok(false);
+        }
+        ("DEFINE", _) | ("MATCH", _) | ("SHOW", _) | ("HELP", _) | ("QUIT", _) => {
+            write!(out, "501 Syntax error, illegal parameters\r\n")?
+        }
+        _ => write!(out, "500 Syntax error, command not recognized\r\n")?,
+    }
+
+    Ok(true)
+}
+
+///
+/// Serves a connection as per RFC 2229 until the client quits or disconnects.
+///
+pub fn handle_session(
+    db: &DictDatabase,
+    input: impl BufRead,
+    mut out: impl Write,
+) -> Result<(), String> {
+    write!(
+        out,
+        "220 localhost edpdgen {} <edpdgen@localhost>\r\n",
+        env!("CARGO_PKG_VERSION")
+    )
+    .map_err(|e| e.to_string())?;
+
+    for line in input.lines() {
+        let line = line.map_err(|e| e.to_string())?;
+        let keep_going = match tokenize(&line) {
+            Ok(words) if words.is_empty() => true,
+            Ok(words) => handle_command(db, &words, &mut out).map_err(|e| e.to_string())?,
+            Err(_) => {
+                write!(out, "501 Syntax error, illegal parameters\r\n")
+                    .map_err(|e| e.to_string())?;
+                true
+            }
+        };
+        out.flush().map_err(|e| e.to_string())?;
+        if !keep_going {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+///
+/// Serves the database to one client at a time.
+///
+pub fn serve(db: &DictDatabase, address: &str, logger: &dyn PlsLogger) -> Result<(), String> {
+    let listener = TcpListener::bind(address).map_err(|e| e.to_string())?;
+    logger.info(&format!(
+        "Serving '{}' ({} index entries) on dict://{}.",
+        db.name,
+        db.index.len(),
+        address
+    ));
+
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(s) => s,
+            Err(e) => {
+                logger.warning(&format!("Unable to accept connection: {}.", e));
+                continue;
+            }
+        };
+        let peer = stream
+            .peer_addr()
+            .map(|a| a.to_string())
+            .unwrap_or_else(|_| "unknown".to_string());
+        logger.info(&format!("Connection from {}.", peer));
+        let reader = match stream.try_clone() {
+            Ok(s) => BufReader::new(s),
+            Err(e) => {
+                logger.warning(&format!("Unable to read from {}: {}.", peer, e));
+                continue;
+            }
+        };
+        if let Err(e) = handle_session(db, reader, stream) {
+            logger.warning(&format!("Connection from {} failed: {}.", peer, e));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    const DICT: &str = "00-database-info\n   Test dictionary.\n00-database-short\n   Test\nabala\n   weak\n.ka\n   dotted\n";
+    const INDEX: &str =
+        "00-database-info\tA\tl\n00-database-short\tl\ta\n.ka\tBN\tO\nabala\t/\tO\nabalo\t/\tO\n";
+
+    fn create_db() -> DictDatabase {
+        DictDatabase::new("dpd", INDEX, DICT.as_bytes().to_vec()).expect("Unexpected")
+    }
+
+    #[test_case(r#"DEFINE dpd "a b""#, &["DEFINE", "dpd", "a b"])]
+    #[test_case("match * prefix 'x \\' y'", &["match", "*", "prefix", "x ' y"])]
+    fn tokenize_test(line: &str, expected: &[&str]) {
+        assert_eq!(
+            tokenize(line),
+            Ok(expected.iter().map(|s| s.to_string()).collect())
+        );
+    }
+
+    #[test]
+    fn show_info_test() {
+        let mut out = Vec::new();
+
+        handle_session(&create_db(), "SHOW INFO dpd\r\n".as_bytes(), &mut out).expect("Unexpected");
+
+        let out = String::from_utf8(out).expect("Unexpected");
+        assert!(out.contains(
+            "112 database information follows\r\n   Test dictionary.\r\n.\r\n250 ok\r\n"
+        ));
+    }
+
+    #[test]
+    fn session_test() {
+        let input = "SHOW DB\r\nSHOW STRAT\r\nSHOW INFO dpd\r\nDEFINE dpd Abalo\r\nDEFINE * ka\r\nDEFINE other abala\r\nDEFINE dpd xyz\r\nMATCH dpd prefix aba\r\nMATCH dpd . abalo\r\nMATCH dpd soundex aba\r\nCLIENT test\r\nFOO\r\nDEFINE\r\nQUIT\r\nSHOW DB\r\n";
+        let mut out = Vec::new();
+
+        handle_session(&create_db(), input.as_bytes(), &mut out).expect("Unexpected");
+
+        let out = String::from_utf8(out)
+            .expect("Unexpected")
+            .replace(env!("CARGO_PKG_VERSION"), "x.y.z");
+        insta::assert_snapshot!(out);
+    }
+}
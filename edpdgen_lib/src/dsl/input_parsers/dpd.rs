@@ -0,0 +1,75 @@
+use crate::dsl::input_parsers::dsl_filter;
+use crate::dsl::DslPaliWord;
+use crate::input::dpd::DpdPaliWord;
+use crate::input::{make_group_id, make_sort_key};
+use tera::{Context, Tera};
+
+lazy_static! {
+    static ref TEMPLATES: Tera = {
+        let mut tera = Tera::default();
+        tera.add_raw_templates(vec![("dpd_card", include_str!("templates/dpd_card.dsl"))])
+            .expect("Unexpected failure adding template");
+        tera.register_filter("dsl", dsl_filter);
+        tera
+    };
+}
+
+#[derive(Serialize)]
+struct CardViewModel<'a> {
+    word: &'a DpdPaliWord,
+    concise: bool,
+}
+
+impl DslPaliWord for DpdPaliWord {
+    fn id(&self) -> &str {
+        &self.pali1
+    }
+
+    fn sort_key(&self) -> String {
+        make_sort_key(self.id())
+    }
+
+    fn group_id(&self) -> String {
+        make_group_id(self.id())
+    }
+
+    fn card_body(&self, concise: bool) -> Result<String, String> {
+        let vm = CardViewModel {
+            word: self,
+            concise,
+        };
+
+        let context = Context::from_serialize(&vm).map_err(|e| e.to_string())?;
+        TEMPLATES
+            .render("dpd_card", &context)
+            .map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dsl::input_parsers::load_words;
+    use crate::resolve_file_in_manifest_dir;
+    use crate::tests::TestLogger;
+    use test_case::test_case;
+
+    #[test_case(0, false)]
+    #[test_case(4, false)]
+    #[test_case(9, false)]
+    #[test_case(0, true)]
+    #[test_case(9, true)]
+    fn card_body_tests(rec_number: usize, concise: bool) {
+        let path = resolve_file_in_manifest_dir("Pali_English_Dictionary_10_rows-full.csv")
+            .expect("must exist!");
+        let l = TestLogger::new();
+        let mut recs = load_words::<DpdPaliWord>(&path, &l).expect("unexpected");
+
+        let card_body = recs
+            .nth(rec_number)
+            .map(|r| r.card_body(concise).expect("unexpected"))
+            .expect("unexpected");
+
+        insta::assert_snapshot!(card_body);
+    }
+}
@@ -0,0 +1,91 @@
+use crate::dsl::DslPaliWord;
+use crate::input::create_csv_reader;
+use pls_core_extras::logger::PlsLogger;
+use regex::Regex;
+use std::collections::HashMap;
+use std::path::Path;
+use tera::Value;
+
+pub mod dpd;
+pub mod dps;
+
+lazy_static! {
+    static ref HTML_TAG: Regex = Regex::new(r"<[^>]*>").expect("Malformed regex string");
+}
+
+pub fn load_words<'a, T: 'a + serde::de::DeserializeOwned + DslPaliWord>(
+    path: &Path,
+    logger: &'a dyn PlsLogger,
+) -> Result<impl Iterator<Item = impl DslPaliWord> + 'a, String> {
+    let rdr = create_csv_reader(path, logger)?;
+
+    let words = rdr
+        .into_deserialize::<T>()
+        .enumerate()
+        .filter_map(move |(i, r)| match r {
+            Ok(w) => Some(w),
+            Err(e) => {
+                logger.error(&format!(
+                    "Unable to deserialize record #{}. Error: {}.",
+                    i, e
+                ));
+                None
+            }
+        });
+
+    logger.info(&format!("... done loading words from {:?}.", &path));
+    Ok(words)
+}
+
+///
+/// Escapes the characters that are DSL markup in card bodies.
+///
+pub fn escape_dsl(s: &str) -> String {
+    s.chars()
+        .fold(String::with_capacity(s.len()), |mut acc, c| {
+            if "\\[]{}~@^".contains(c) {
+                acc.push('\\');
+            }
+            acc.push(c);
+            acc
+        })
+}
+
+///
+/// Converts the bits of html in the input data to DSL markup and keeps each field on one line.
+///
+pub fn html_to_dsl(s: &str) -> String {
+    let s = escape_dsl(s)
+        .replace("\r", "")
+        .replace("\n", " | ")
+        .replace("<br/>", " | ")
+        .replace("<br />", " | ")
+        .replace("<br>", " | ")
+        .replace("<b>", "[b]")
+        .replace("</b>", "[/b]")
+        .replace("<i>", "[i]")
+        .replace("</i>", "[/i]");
+
+    HTML_TAG.replace_all(&s, "").into_owned()
+}
+
+pub fn dsl_filter(value: &Value, _args: &HashMap<String, Value>) -> tera::Result<Value> {
+    value
+        .as_str()
+        .map(|s| Value::String(html_to_dsl(s)))
+        .ok_or_else(|| tera::Error::msg("Filter 'dsl' expects a string"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("abala", "abala")]
+    #[test_case("[g] {x} ~a @b ^c \\d", "\\[g\\] \\{x\\} \\~a \\@b \\^c \\\\d"; "markup characters")]
+    #[test_case("<b>dhammo</b><br/>dhammaṃ", "[b]dhammo[/b] | dhammaṃ"; "bold and breaks")]
+    #[test_case("<i>x</i> <span>y</span>", "[i]x[/i] y"; "other tags are removed")]
+    fn html_to_dsl_test(html: &str, expected: &str) {
+        assert_eq!(html_to_dsl(html), expected);
+    }
+}
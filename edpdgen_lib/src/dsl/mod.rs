@@ -0,0 +1,67 @@
+use crate::input::dpd::DpdPaliWord;
+use crate::input::dps::DpsPaliWord;
+use crate::input::input_format::InputFormat;
+use crate::output::output_generator::OutputGenerator;
+use crate::{DictionaryFiles, DictionaryInfo};
+use pls_core_extras::inflection_generator::InflectionGenerator;
+use pls_core_extras::logger::PlsLogger;
+use std::path::Path;
+
+mod input_parsers;
+mod output_generators;
+
+pub struct DslGenerator;
+
+impl OutputGenerator for DslGenerator {
+    fn name(&self) -> &'static str {
+        "dsl"
+    }
+
+    fn description(&self) -> &'static str {
+        "ABBYY Lingvo and GoldenDict DSL format."
+    }
+
+    fn input_formats(&self) -> &'static [InputFormat] {
+        &[InputFormat::Dpd, InputFormat::Dps]
+    }
+
+    fn options(&self) -> &'static [&'static str] {
+        &["concise"]
+    }
+
+    fn build_files(
+        &self,
+        dict_info: &DictionaryInfo,
+        input_data_path: &Path,
+        igen: &dyn InflectionGenerator,
+        logger: &dyn PlsLogger,
+    ) -> Result<DictionaryFiles, String> {
+        match dict_info.input_format {
+            InputFormat::Dpd => {
+                run_for_ods_type::<DpdPaliWord>(dict_info, input_data_path, igen, logger)
+            }
+            InputFormat::Dps => {
+                run_for_ods_type::<DpsPaliWord>(dict_info, input_data_path, igen, logger)
+            }
+        }
+    }
+}
+
+pub trait DslPaliWord {
+    fn id(&self) -> &str;
+    fn sort_key(&self) -> String;
+    fn group_id(&self) -> String;
+    fn card_body(&self, concise: bool) -> Result<String, String>;
+}
+
+pub fn run_for_ods_type<'a, T: 'a + serde::de::DeserializeOwned + DslPaliWord>(
+    dict_info: &DictionaryInfo,
+    input_data_path: &Path,
+    igen: &dyn InflectionGenerator,
+    logger: &dyn PlsLogger,
+) -> Result<DictionaryFiles, String> {
+    let words = input_parsers::load_words::<T>(input_data_path, logger)?;
+    let dsl_files = output_generators::create_dictionary(dict_info, words, igen, logger)?;
+
+    Ok(dsl_files)
+}
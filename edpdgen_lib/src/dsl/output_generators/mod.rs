@@ -0,0 +1,198 @@
+use crate::dsl::input_parsers::escape_dsl;
+use crate::dsl::DslPaliWord;
+use crate::input::input_format::InputFormat;
+use crate::{DictionaryData, DictionaryFile, DictionaryFiles, DictionaryInfo};
+use itertools::Itertools;
+use pls_core_extras::inflection_generator::InflectionGenerator;
+use pls_core_extras::logger::PlsLogger;
+
+const UTF16LE_BOM: [u8; 2] = [0xFF, 0xFE];
+
+///
+/// Headwords also escape the parentheses that mark optional parts of a heading.
+///
+fn escape_headword(s: &str) -> String {
+    escape_dsl(s).replace("(", "\\(").replace(")", "\\)")
+}
+
+fn create_cards(
+    words: impl Iterator<Item = impl DslPaliWord>,
+    igen: &dyn InflectionGenerator,
+    logger: &dyn PlsLogger,
+    concise: bool,
+) -> Result<Vec<String>, String> {
+    logger.info("Creating dsl cards.");
+
+    let word_groups = words
+        .sorted_by(|w1, w2| Ord::cmp(&w1.sort_key(), &w2.sort_key()))
+        .group_by(|pw| pw.group_id());
+
+    let mut cards: Vec<String> = Vec::new();
+    for (n, (group_id, words)) in word_groups.into_iter().enumerate() {
+        let words: Vec<_> = words.collect();
+
+        let mut card = escape_headword(&group_id) + "\n";
+        let alternates = words
+            .iter()
+            .flat_map(|w| igen.generate_all_inflections(w.id()))
+            .filter(|i| i != &group_id)
+            .unique();
+        for alternate in alternates {
+            card.push_str(&escape_headword(&alternate));
+            card.push('\n');
+        }
+        for w in &words {
+            card.push_str(&w.card_body(concise)?);
+        }
+        cards.push(card);
+
+        if n % 1_000 == 0 && n != 0 {
+            logger.info(&format!(
+                "... created {:05} dsl cards, ending with '{}'.",
+                n, group_id
+            ));
+        }
+    }
+
+    logger.info(&format!("... done creating {} dsl cards.", cards.len()));
+
+    Ok(cards)
+}
+
+fn create_dsl_data(dict_info: &DictionaryInfo, cards: &[String]) -> Vec<u8> {
+    let contents_language = match dict_info.input_format {
+        InputFormat::Dpd => "English",
+        InputFormat::Dps => "Russian",
+    };
+
+    let header = format!(
+        "#NAME \"{}\"\n#INDEX_LANGUAGE \"Pali\"\n#CONTENTS_LANGUAGE \"{}\"\n\n",
+        dict_info.name, contents_language
+    );
+
+    to_utf16le(&(header + &cards.join("\n")))
+}
+
+fn create_ann_data(dict_info: &DictionaryInfo) -> Vec<u8> {
+    let ann = format!(
+        "{}\n{}\nAuthor: {}\nVersion: {}\n",
+        dict_info.name, dict_info.description, dict_info.author, dict_info.version
+    );
+
+    to_utf16le(&ann)
+}
+
+///
+/// DSL files are UTF-16LE with CRLF line endings, as expected by ABBYY Lingvo.
+///
+fn to_utf16le(s: &str) -> Vec<u8> {
+    s.replace("\r", "")
+        .replace("\n", "\r\n")
+        .encode_utf16()
+        .flat_map(|c| c.to_le_bytes().to_vec())
+        .collect()
+}
+
+pub fn create_dictionary(
+    dict_info: &DictionaryInfo,
+    words: impl Iterator<Item = impl DslPaliWord>,
+    igen: &dyn InflectionGenerator,
+    logger: &dyn PlsLogger,
+) -> Result<DictionaryFiles, String> {
+    let cards = create_cards(words, igen, logger, dict_info.concise)?;
+    let word_count = cards.len();
+    let dsl = create_dsl_data(dict_info, &cards);
+    let ann = create_ann_data(dict_info);
+
+    let mut png = Vec::new();
+    png.extend_from_slice(&dict_info.icon);
+
+    Ok(DictionaryFiles {
+        word_count,
+        files: vec![
+            DictionaryFile {
+                extension: "dsl".to_string(),
+                bom: UTF16LE_BOM.to_vec(),
+                data: DictionaryData::InMemory(dsl),
+                can_be_empty: false,
+            },
+            DictionaryFile {
+                extension: "ann".to_string(),
+                bom: UTF16LE_BOM.to_vec(),
+                data: DictionaryData::InMemory(ann),
+                can_be_empty: false,
+            },
+            DictionaryFile {
+                extension: "png".to_string(),
+                bom: vec![],
+                data: DictionaryData::InMemory(png),
+                can_be_empty: false,
+            },
+        ],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{TestInflectionGenerator, TestLogger};
+
+    struct TestPaliWord {
+        id: String,
+        body: String,
+    }
+
+    impl DslPaliWord for TestPaliWord {
+        fn id(&self) -> &str {
+            &self.id
+        }
+
+        fn sort_key(&self) -> String {
+            self.id.clone()
+        }
+
+        fn group_id(&self) -> String {
+            self.id.split(' ').next().unwrap_or_default().to_string()
+        }
+
+        fn card_body(&self, _concise: bool) -> Result<String, String> {
+            Ok(self.body.clone())
+        }
+    }
+
+    #[test]
+    fn create_cards_test() {
+        let words = vec![
+            TestPaliWord {
+                id: "abala 2".to_string(),
+                body: "\t[m1]two[/m]\n".to_string(),
+            },
+            TestPaliWord {
+                id: "abala 1".to_string(),
+                body: "\t[m1]one[/m]\n".to_string(),
+            },
+            TestPaliWord {
+                id: "a(b)".to_string(),
+                body: "\t[m1]three[/m]\n".to_string(),
+            },
+        ];
+
+        let cards = create_cards(
+            words.into_iter(),
+            &TestInflectionGenerator::new(),
+            &TestLogger::new(),
+            false,
+        )
+        .expect("Unexpected");
+
+        insta::assert_debug_snapshot!(cards);
+    }
+
+    #[test]
+    fn to_utf16le_test() {
+        assert_eq!(
+            to_utf16le("a\nā"),
+            vec![0x61, 0, 0x0D, 0, 0x0A, 0, 0x01, 0x01]
+        );
+    }
+}
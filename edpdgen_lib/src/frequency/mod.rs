@@ -0,0 +1,184 @@
+use crate::input::make_group_id;
+use itertools::Itertools;
+use pls_core_extras::inflection_generator::InflectionGenerator;
+use pls_core_extras::logger::PlsLogger;
+use std::collections::HashMap;
+use std::ops::AddAssign;
+use std::path::Path;
+
+#[derive(Debug, Deserialize)]
+struct FrequencyRecord {
+    form: String,
+    vinaya: u64,
+    sutta: u64,
+    abhidhamma: u64,
+}
+
+///
+/// Occurrences in each piṭaka of the Tipiṭaka.
+///
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize)]
+pub struct Frequency {
+    pub vinaya: u64,
+    pub sutta: u64,
+    pub abhidhamma: u64,
+    pub total: u64,
+}
+
+impl AddAssign for Frequency {
+    fn add_assign(&mut self, other: Self) {
+        self.vinaya += other.vinaya;
+        self.sutta += other.sutta;
+        self.abhidhamma += other.abhidhamma;
+        self.total += other.total;
+    }
+}
+
+///
+/// Corpus counts of forms, read from a tsv with columns form, vinaya, sutta and abhidhamma.
+///
+pub struct FrequencyTable {
+    forms: HashMap<String, Frequency>,
+}
+
+impl FrequencyTable {
+    pub fn load(path: &Path, logger: &dyn PlsLogger) -> Result<Self, String> {
+        logger.info(&format!("Loading frequencies from {:?}.", path));
+        let rdr = csv::ReaderBuilder::new()
+            .delimiter(b'\t')
+            .comment(Some(b'#'))
+            .from_path(path)
+            .map_err(|e| e.to_string())?;
+
+        let mut forms: HashMap<String, Frequency> = HashMap::new();
+        for r in rdr.into_deserialize::<FrequencyRecord>() {
+            let r = r.map_err(|e| e.to_string())?;
+            *forms.entry(r.form.trim().to_lowercase()).or_default() += Frequency {
+                vinaya: r.vinaya,
+                sutta: r.sutta,
+                abhidhamma: r.abhidhamma,
+                total: r.vinaya + r.sutta + r.abhidhamma,
+            };
+        }
+
+        logger.info(&format!(
+            "... done loading frequencies of {} forms.",
+            forms.len()
+        ));
+        Ok(FrequencyTable { forms })
+    }
+
+    ///
+    /// The frequency of a word summed over its headword and all of its inflections.
+    ///
+    pub fn word_frequency(&self, pali1: &str, igen: &dyn InflectionGenerator) -> Frequency {
+        std::iter::once(make_group_id(pali1))
+            .chain(igen.generate_all_inflections(pali1))
+            .unique()
+            .filter_map(|f| self.forms.get(&f))
+            .fold(Frequency::default(), |mut acc, f| {
+                acc += *f;
+                acc
+            })
+    }
+}
+
+///
+/// Pairs the words with their frequency when there is a frequency table, leaving out the words
+/// below `min_frequency`. The frequency takes all inflections of a word, so it is computed once
+/// and carried along with the word.
+///
+pub fn with_frequencies<'a, W: 'a>(
+    words: impl Iterator<Item = W> + 'a,
+    id: fn(&W) -> &str,
+    frequencies: Option<&'a FrequencyTable>,
+    igen: &'a dyn InflectionGenerator,
+    min_frequency: u64,
+) -> impl Iterator<Item = (W, Option<Frequency>)> + 'a {
+    words.filter_map(
+        move |w| match frequencies.map(|f| f.word_frequency(id(&w), igen)) {
+            Some(f) if f.total < min_frequency => None,
+            frequency => Some((w, frequency)),
+        },
+    )
+}
+
+pub fn load_frequency_table(
+    frequency_path: Option<&str>,
+    logger: &dyn PlsLogger,
+) -> Result<Option<FrequencyTable>, String> {
+    frequency_path
+        .map(|p| FrequencyTable::load(Path::new(p), logger))
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{TestInflectionGenerator, TestLogger};
+    use std::fs;
+    use std::path::PathBuf;
+    use tempfile::TempDir;
+
+    fn create_frequency_file(contents: &str) -> (TempDir, PathBuf) {
+        let dir = tempfile::tempdir().expect("Unexpected");
+        let path = dir.path().join("frequency.tsv");
+        fs::write(&path, contents).expect("Unexpected");
+
+        (dir, path)
+    }
+
+    #[test]
+    fn word_frequency_test() {
+        let (_dir, path) = create_frequency_file(
+            "form\tvinaya\tsutta\tabhidhamma\nabala\t1\t2\t3\nAbala\t1\t0\t0\nabala 1_1\t0\t10\t0\nbala\t100\t100\t100\n",
+        );
+        let table = FrequencyTable::load(&path, &TestLogger::new()).expect("Unexpected");
+
+        let frequency = table.word_frequency("abala 1", &TestInflectionGenerator::new());
+
+        assert_eq!(
+            frequency,
+            Frequency {
+                vinaya: 2,
+                sutta: 12,
+                abhidhamma: 3,
+                total: 17
+            }
+        );
+    }
+
+    #[test]
+    fn with_frequencies_test() {
+        let (_dir, path) =
+            create_frequency_file("form\tvinaya\tsutta\tabhidhamma\nabala\t1\t2\t3\n");
+        let table = FrequencyTable::load(&path, &TestLogger::new()).expect("Unexpected");
+        let igen = TestInflectionGenerator::new();
+        let frequent_words = |min_frequency| {
+            with_frequencies(
+                vec!["abala 1", "ābādha"].into_iter(),
+                |w| w,
+                Some(&table),
+                &igen,
+                min_frequency,
+            )
+            .map(|(w, f)| (w, f.map(|f| f.total)))
+            .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            frequent_words(0),
+            vec![("abala 1", Some(6)), ("ābādha", Some(0))]
+        );
+        assert_eq!(frequent_words(6), vec![("abala 1", Some(6))]);
+        assert_eq!(frequent_words(7), vec![]);
+    }
+
+    #[test]
+    fn with_frequencies_without_table_test() {
+        let igen = TestInflectionGenerator::new();
+        let words = with_frequencies(vec!["abala 1"].into_iter(), |w| w, None, &igen, 7);
+
+        assert_eq!(words.collect::<Vec<_>>(), vec![("abala 1", None)]);
+    }
+}
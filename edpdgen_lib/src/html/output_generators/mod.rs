@@ -0,0 +1,740 @@
+use crate::input::make_sort_key;
+use crate::stardict::{
+    create_style_context, create_stylesheet, ArticleReader, IdxEntry, DECONSTRUCTION_SUFFIX,
+    DERIVATION_SUFFIX,
+};
+use crate::{escape_xml, DictionaryData, DictionaryFile, DictionaryFiles, DictionaryInfo};
+use chrono::DateTime;
+use itertools::Itertools;
+use pls_core_extras::logger::PlsLogger;
+use regex::{Captures, Regex};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use tera::{Context, Tera};
+
+lazy_static! {
+    static ref TEMPLATES: Tera = {
+        let mut tera = Tera::default();
+        tera.add_raw_templates(vec![
+            ("page", include_str!("templates/page.html")),
+            ("listing", include_str!("templates/listing.html")),
+            ("site_style", include_str!("templates/site.css")),
+        ])
+        .expect("Unexpected failure adding template");
+        tera
+    };
+    static ref ARTICLE_BODY: Regex =
+        Regex::new(r"(?s)<body>\n?(.*)</body>").expect("is valid regex");
+    static ref BWORD_LINK: Regex = Regex::new(r#"href="bword://([^"]*)""#).expect("is valid regex");
+}
+
+/// The site is written to the <short_name>.site folder.
+const SITE_FOLDER: &str = "site";
+
+/// Where e.g. python3 -m http.server serves the site folder.
+const DEFAULT_SITE_URL: &str = "http://localhost:8000/";
+
+/// See https://www.sitemaps.org/protocol.html#index
+const SITEMAP_MAX_URLS: usize = 50_000;
+
+/// The Pāli alphabet in order, with the aspirates as letters of their own.
+const PALI_ALPHABET: &[&str] = &[
+    "a", "ā", "i", "ī", "u", "ū", "e", "o", "k", "kh", "g", "gh", "ṅ", "c", "ch", "j", "jh", "ñ",
+    "ṭ", "ṭh", "ḍ", "ḍh", "ṇ", "t", "th", "d", "dh", "n", "p", "ph", "b", "bh", "m", "y", "r", "l",
+    "v", "s", "h", "ḷ", "ṃ",
+];
+
+/// The index page of the words that do not start with a Pāli letter.
+const OTHER_LETTER: &str = "other";
+
+const ROOTS_INDEX_PATH: &str = "roots/index.html";
+
+struct SitePage {
+    word: String,
+    folder: &'static str,
+    path: String,
+    data_offset: i32,
+    data_size: i32,
+    synonym_words: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct Link {
+    title: String,
+    url: String,
+}
+
+#[derive(Serialize)]
+struct PageViewModel<'a> {
+    dict_short_name: &'a str,
+    name: &'a str,
+    author: &'a str,
+    version: &'a str,
+    title: &'a str,
+    root: &'a str,
+    body: &'a str,
+}
+
+#[derive(Serialize)]
+struct ListingViewModel<'a> {
+    dict_short_name: &'a str,
+    heading: &'a str,
+    text: &'a str,
+    root: &'a str,
+    letters: &'a [Link],
+    links: &'a [Link],
+}
+
+///
+/// The terms of a shard of the search index, each with the pages it is found on.
+///
+#[derive(Serialize)]
+struct SearchShard<'a> {
+    pages: Vec<(&'a str, String)>,
+    terms: Vec<(&'a str, Vec<usize>)>,
+}
+
+fn pali_letter(s: &str) -> Option<(usize, &'static str)> {
+    PALI_ALPHABET
+        .iter()
+        .enumerate()
+        .filter(|(_, l)| s.starts_with(*l))
+        .max_by_key(|(_, l)| l.len())
+        .map(|(n, l)| (n, *l))
+}
+
+///
+/// Orders words as in the Pāli alphabet, i.e. kh after k and ṃ at the very end. Spaces come
+/// ahead of the letters and everything else after them.
+///
+fn make_pali_sort_key(word: &str) -> Vec<u32> {
+    let word = make_sort_key(&word.to_lowercase());
+    let mut key = Vec::with_capacity(word.len());
+    let mut rest = word.as_str();
+    while let Some(c) = rest.chars().next() {
+        let (n, len) = match pali_letter(rest) {
+            Some((n, letter)) => (n as u32 + 1, letter.len()),
+            None if c.is_whitespace() => (0, c.len_utf8()),
+            None => (PALI_ALPHABET.len() as u32 + 1 + c as u32, c.len_utf8()),
+        };
+        key.push(n);
+        rest = &rest[len..];
+    }
+
+    key
+}
+
+fn make_page_name(word: &str) -> String {
+    let name = word
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|p| !p.is_empty())
+        .join("-");
+
+    if name.is_empty() {
+        "-".to_string()
+    } else {
+        name
+    }
+}
+
+///
+/// Percent encodes all but the unreserved characters and the path separator.
+///
+fn make_url(path: &str) -> String {
+    path.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+///
+/// The folder of the page and the part of the word it is named after.
+///
+fn page_folder(word: &str) -> (&'static str, &str) {
+    if let Some(family) = word.strip_suffix(DERIVATION_SUFFIX) {
+        ("derivations", family)
+    } else if let Some(form) = word.strip_suffix(DECONSTRUCTION_SUFFIX) {
+        ("deconstructions", form)
+    } else if word.starts_with('√') {
+        ("roots", word)
+    } else {
+        ("words", word)
+    }
+}
+
+///
+/// A page per article, i.e. per word group, root family, derivation tree and deconstruction.
+/// Names that collide, e.g. as file systems ignore case, get a number.
+///
+fn create_pages(idx_entries: Vec<IdxEntry>) -> Vec<SitePage> {
+    let mut paths: HashSet<String> = HashSet::new();
+    paths.insert(ROOTS_INDEX_PATH.to_string());
+
+    idx_entries
+        .into_iter()
+        .map(|e| {
+            let (folder, name) = page_folder(&e.word);
+            let name = make_page_name(name);
+            let mut path = format!("{}/{}.html", folder, name);
+            let mut n = 1;
+            while !paths.insert(path.clone()) {
+                n += 1;
+                path = format!("{}/{}-{}.html", folder, name, n);
+            }
+
+            SitePage {
+                folder,
+                path,
+                word: e.word,
+                data_offset: e.data_offset,
+                data_size: e.data_size,
+                synonym_words: e.synonym_words,
+            }
+        })
+        .collect()
+}
+
+///
+/// Points the links between the articles to the pages, and links to words that have no page
+/// to a search for them.
+///
+fn rewrite_links(html: &str, urls: &HashMap<&str, String>, root: &str) -> String {
+    BWORD_LINK
+        .replace_all(html, |caps: &Captures| match urls.get(&caps[1]) {
+            Some(url) => format!("href=\"{}{}\"", root, url),
+            None => format!("href=\"{}index.html?q={}\"", root, make_url(&caps[1])),
+        })
+        .into_owned()
+}
+
+fn render_page(
+    dict_info: &DictionaryInfo,
+    title: &str,
+    root: &str,
+    body: &str,
+) -> Result<String, String> {
+    let vm = PageViewModel {
+        dict_short_name: dict_info.short_name,
+        name: dict_info.name,
+        author: dict_info.author,
+        version: dict_info.version,
+        title,
+        root,
+        body,
+    };
+
+    let context = Context::from_serialize(&vm).map_err(|e| e.to_string())?;
+    TEMPLATES
+        .render("page", &context)
+        .map_err(|e| e.to_string())
+}
+
+fn render_listing(
+    dict_info: &DictionaryInfo,
+    heading: &str,
+    text: &str,
+    root: &str,
+    letters: &[Link],
+    links: &[Link],
+) -> Result<String, String> {
+    let vm = ListingViewModel {
+        dict_short_name: dict_info.short_name,
+        heading,
+        text,
+        root,
+        letters,
+        links,
+    };
+
+    let context = Context::from_serialize(&vm).map_err(|e| e.to_string())?;
+    let body = TEMPLATES
+        .render("listing", &context)
+        .map_err(|e| e.to_string())?;
+
+    render_page(dict_info, heading, root, &body)
+}
+
+fn create_article_pages(
+    dict_info: &DictionaryInfo,
+    dict: &DictionaryData,
+    pages: &[SitePage],
+    logger: &dyn PlsLogger,
+) -> Result<Vec<(String, Vec<u8>)>, String> {
+    logger.info(&format!("Creating {} html pages.", pages.len()));
+
+    let urls: HashMap<&str, String> = pages
+        .iter()
+        .map(|p| (p.word.as_str(), make_url(&p.path)))
+        .collect();
+    let mut reader = ArticleReader::new(dict)?;
+    let mut files = Vec::with_capacity(pages.len());
+    for (n, page) in pages.iter().enumerate() {
+        let article = reader.read(page.data_offset, page.data_size)?;
+        let body = ARTICLE_BODY
+            .captures(&article)
+            .map(|caps| caps[1].to_string())
+            .unwrap_or(article);
+        let html = render_page(
+            dict_info,
+            &page.word,
+            "../",
+            &rewrite_links(&body, &urls, "../"),
+        )?;
+        files.push((page.path.clone(), html.into_bytes()));
+
+        if n % 1_000 == 0 && n != 0 {
+            logger.info(&format!(
+                "... created {:05} html pages, ending with '{}'.",
+                n, page.word
+            ));
+        }
+    }
+
+    logger.info(&format!("... done creating {} html pages.", files.len()));
+    Ok(files)
+}
+
+fn sorted_links<'a>(pages: impl Iterator<Item = &'a SitePage>) -> Vec<Link> {
+    pages
+        .sorted_by_cached_key(|p| (make_pali_sort_key(&p.word), p.word.clone()))
+        .map(|p| Link {
+            title: p.word.clone(),
+            url: make_url(&p.path),
+        })
+        .collect()
+}
+
+///
+/// The home page, an index page per initial letter of the words in Pāli order and an index of
+/// the roots.
+///
+fn create_index_pages(
+    dict_info: &DictionaryInfo,
+    pages: &[SitePage],
+    logger: &dyn PlsLogger,
+) -> Result<Vec<(String, Vec<u8>)>, String> {
+    logger.info("Creating html index pages.");
+
+    let mut letter_pages: BTreeMap<usize, Vec<&SitePage>> = BTreeMap::new();
+    for page in pages.iter().filter(|p| p.folder == "words") {
+        let n = pali_letter(&page.word.to_lowercase())
+            .map(|(n, _)| n)
+            .unwrap_or(PALI_ALPHABET.len());
+        letter_pages.entry(n).or_default().push(page);
+    }
+    let letter_paths: Vec<(String, String)> = letter_pages
+        .keys()
+        .map(|n| {
+            let letter = PALI_ALPHABET.get(*n).unwrap_or(&OTHER_LETTER);
+            (letter.to_string(), format!("letters/{}.html", letter))
+        })
+        .collect();
+    let letters: Vec<Link> = letter_paths
+        .iter()
+        .map(|(letter, path)| Link {
+            title: letter.clone(),
+            url: make_url(path),
+        })
+        .collect();
+
+    let mut files = Vec::new();
+    for ((letter, path), letter_pages) in letter_paths.iter().zip(letter_pages.values()) {
+        let links = sorted_links(letter_pages.iter().copied());
+        let html = render_listing(dict_info, letter, "", "../", &letters, &links)?;
+        files.push((path.clone(), html.into_bytes()));
+    }
+
+    let roots = sorted_links(pages.iter().filter(|p| p.folder == "roots"));
+    let mut home_links = Vec::new();
+    if !roots.is_empty() {
+        let html = render_listing(dict_info, "Roots", "", "../", &letters, &roots)?;
+        files.push((ROOTS_INDEX_PATH.to_string(), html.into_bytes()));
+        home_links.push(Link {
+            title: "Roots".to_string(),
+            url: ROOTS_INDEX_PATH.to_string(),
+        });
+    }
+
+    let html = render_listing(
+        dict_info,
+        dict_info.name,
+        dict_info.description,
+        "",
+        &letters,
+        &home_links,
+    )?;
+    files.push(("index.html".to_string(), html.into_bytes()));
+
+    logger.info(&format!(
+        "... done creating {} html index pages.",
+        files.len()
+    ));
+    Ok(files)
+}
+
+///
+/// The headwords and inflections, lower cased, split on their first character into
+/// search/<code point>.json so that a search only loads the terms it can match.
+///
+fn create_search_index(
+    pages: &[SitePage],
+    logger: &dyn PlsLogger,
+) -> Result<Vec<(String, Vec<u8>)>, String> {
+    logger.info("Creating search index.");
+
+    let mut shards: BTreeMap<u32, BTreeMap<String, Vec<usize>>> = BTreeMap::new();
+    for (n, page) in pages.iter().enumerate() {
+        for term in std::iter::once(&page.word).chain(page.synonym_words.iter()) {
+            let term = term.to_lowercase();
+            if let Some(c) = term.chars().next() {
+                shards
+                    .entry(c as u32)
+                    .or_default()
+                    .entry(term)
+                    .or_default()
+                    .push(n);
+            }
+        }
+    }
+
+    let mut files = Vec::with_capacity(shards.len());
+    for (c, terms) in &shards {
+        let page_ids: Vec<usize> = terms.values().flatten().copied().sorted().dedup().collect();
+        let local_ids: HashMap<usize, usize> = page_ids
+            .iter()
+            .enumerate()
+            .map(|(local_id, page_id)| (*page_id, local_id))
+            .collect();
+        let shard = SearchShard {
+            pages: page_ids
+                .iter()
+                .map(|id| (pages[*id].word.as_str(), make_url(&pages[*id].path)))
+                .collect(),
+            terms: terms
+                .iter()
+                .map(|(term, ids)| {
+                    let ids = ids.iter().map(|id| local_ids[id]).dedup().collect();
+                    (term.as_str(), ids)
+                })
+                .collect(),
+        };
+
+        let json = serde_json::to_vec(&shard).map_err(|e| e.to_string())?;
+        files.push((format!("search/{:04x}.json", c), json));
+    }
+
+    logger.info(&format!(
+        "... done creating search index of {} terms.",
+        shards.values().map(|s| s.len()).sum::<usize>()
+    ));
+    Ok(files)
+}
+
+///
+/// A sitemap index with the sitemaps of all pages, see https://www.sitemaps.org/protocol.html
+///
+fn create_sitemaps(
+    dict_info: &DictionaryInfo,
+    paths: &[&str],
+) -> Result<Vec<(String, Vec<u8>)>, String> {
+    let lastmod = DateTime::parse_from_rfc3339(dict_info.time_stamp)
+        .map_err(|e| e.to_string())?
+        .format("%Y-%m-%d")
+        .to_string();
+    let site_url = dict_info.site_url.unwrap_or(DEFAULT_SITE_URL);
+    let site_url = format!("{}/", site_url.trim_end_matches('/'));
+
+    let mut files = Vec::new();
+    let mut sitemap_index = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<sitemapindex xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n");
+    for (n, chunk) in paths.chunks(SITEMAP_MAX_URLS).enumerate() {
+        let mut sitemap = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n");
+        for path in chunk {
+            sitemap.push_str(&format!(
+                "<url><loc>{}</loc><lastmod>{}</lastmod></url>\n",
+                escape_xml(&format!("{}{}", site_url, make_url(path))),
+                lastmod
+            ));
+        }
+        sitemap.push_str("</urlset>\n");
+
+        let sitemap_path = format!("sitemap-{}.xml", n + 1);
+        sitemap_index.push_str(&format!(
+            "<sitemap><loc>{}</loc><lastmod>{}</lastmod></sitemap>\n",
+            escape_xml(&format!("{}{}", site_url, sitemap_path)),
+            lastmod
+        ));
+        files.push((sitemap_path, sitemap.into_bytes()));
+    }
+    sitemap_index.push_str("</sitemapindex>\n");
+    files.push(("sitemap.xml".to_string(), sitemap_index.into_bytes()));
+
+    Ok(files)
+}
+
+fn create_stylesheets(dict_info: &DictionaryInfo) -> Result<Vec<u8>, String> {
+    let site_style = TEMPLATES
+        .render("site_style", &create_style_context(dict_info)?)
+        .map_err(|e| e.to_string())?;
+
+    Ok(format!("{}{}", create_stylesheet(dict_info)?, site_style).into_bytes())
+}
+
+///
+/// The site goes into the <short_name>.site folder, which can be served as is.
+///
+pub fn create_dictionary(
+    dict_info: &DictionaryInfo,
+    dict: &DictionaryData,
+    idx_entries: Vec<IdxEntry>,
+    logger: &dyn PlsLogger,
+) -> Result<DictionaryFiles, String> {
+    let pages = create_pages(idx_entries);
+
+    let mut site_files = create_index_pages(dict_info, &pages, logger)?;
+    site_files.extend(create_article_pages(dict_info, dict, &pages, logger)?);
+    let paths: Vec<&str> = site_files.iter().map(|(p, _)| p.as_str()).collect();
+    let sitemaps = create_sitemaps(dict_info, &paths)?;
+    site_files.extend(sitemaps);
+    site_files.extend(create_search_index(&pages, logger)?);
+    site_files.push(("style.css".to_string(), create_stylesheets(dict_info)?));
+    site_files.push((
+        "search.js".to_string(),
+        include_str!("templates/search.js").as_bytes().to_vec(),
+    ));
+
+    Ok(DictionaryFiles {
+        word_count: pages.len(),
+        files: site_files
+            .into_iter()
+            .map(|(path, data)| DictionaryFile {
+                extension: format!("{}/{}", SITE_FOLDER, path),
+                bom: vec![],
+                data: DictionaryData::InMemory(data),
+                can_be_empty: false,
+            })
+            .collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::TestLogger;
+    use crate::OutputFormat;
+    use test_case::test_case;
+
+    fn create_dict_info<'a>() -> DictionaryInfo<'a> {
+        DictionaryInfo {
+            site_url: Some("https://dict.example.org/dpd"),
+            ..crate::tests::create_dict_info(&OutputFormat(&crate::html::HtmlGenerator))
+        }
+    }
+
+    fn create_site() -> DictionaryFiles {
+        let articles = [
+            (
+                "abala",
+                vec!["abalo", "abalaṃ"],
+                "<html><head><style>*{}</style></head><body>\n<a href=\"bword://√bal\">√bal</a> <a href=\"bword://bal (derivation)\">bal</a></body></html>",
+            ),
+            (
+                "Ābādha",
+                vec!["ābādhā"],
+                "<html><body>\n<a href=\"bword://mn10\">MN 10</a></body></html>",
+            ),
+            (
+                "kharo",
+                vec![],
+                "<html><body>\n<a href=\"bword://abala\">abala</a></body></html>",
+            ),
+            ("ṭhāna", vec![], "<html><body>\nṭhāna</body></html>"),
+            (
+                "√bal",
+                vec!["bal"],
+                "<html><body>\n<a href=\"bword://abala\">abala</a></body></html>",
+            ),
+            (
+                "bal (derivation)",
+                vec!["bal"],
+                "<html><body>\n<a href=\"bword://abala\">abala</a></body></html>",
+            ),
+        ];
+        let mut dict = Vec::new();
+        let mut idx_entries = Vec::new();
+        for (word, synonyms, html) in articles.iter() {
+            idx_entries.push(IdxEntry {
+                word: word.to_string(),
+                data_offset: dict.len() as i32,
+                data_size: html.len() as i32,
+                synonym_words: synonyms.iter().map(|s| s.to_string()).collect(),
+            });
+            dict.extend_from_slice(html.as_bytes());
+        }
+
+        create_dictionary(
+            &create_dict_info(),
+            &DictionaryData::InMemory(dict),
+            idx_entries,
+            &TestLogger::new(),
+        )
+        .expect("Unexpected")
+    }
+
+    fn site_file(site: &DictionaryFiles, path: &str) -> String {
+        let extension = format!("{}/{}", SITE_FOLDER, path);
+        let file = site
+            .files
+            .iter()
+            .find(|f| f.extension == extension)
+            .unwrap_or_else(|| panic!("'{}' is missing", path));
+        match &file.data {
+            DictionaryData::InMemory(data) => String::from_utf8(data.clone()).expect("Unexpected"),
+            DictionaryData::FileBacked { .. } => panic!("Unexpected"),
+        }
+    }
+
+    #[test]
+    fn make_pali_sort_key_test() {
+        let words = vec![
+            "ṃ",
+            "ṭhāna",
+            "kha",
+            "abala 10",
+            "ka",
+            "ñāṇa",
+            "abalabala",
+            "abala 2",
+            "ā",
+            "kaṃ",
+        ];
+
+        let sorted: Vec<&str> = words
+            .into_iter()
+            .sorted_by_key(|w| make_pali_sort_key(w))
+            .collect();
+
+        assert_eq!(
+            sorted,
+            vec![
+                "abala 2",
+                "abala 10",
+                "abalabala",
+                "ā",
+                "ka",
+                "kaṃ",
+                "kha",
+                "ñāṇa",
+                "ṭhāna",
+                "ṃ"
+            ]
+        );
+    }
+
+    #[test_case("abala", "abala")]
+    #[test_case("Ābādha 1", "ābādha-1"; "capitalised with number")]
+    #[test_case("√bal", "bal")]
+    #[test_case("adhi √kar", "adhi-kar")]
+    #[test_case("?", "-")]
+    fn make_page_name_test(word: &str, expected: &str) {
+        assert_eq!(make_page_name(word), expected);
+    }
+
+    #[test_case("words/abala.html", "words/abala.html")]
+    #[test_case("letters/ā.html", "letters/%C4%81.html")]
+    #[test_case("a b&c", "a%20b%26c")]
+    fn make_url_test(path: &str, expected: &str) {
+        assert_eq!(make_url(path), expected);
+    }
+
+    #[test]
+    fn site_files_test() {
+        let site = create_site();
+
+        let paths: Vec<&str> = site.files.iter().map(|f| f.extension.as_str()).collect();
+        insta::assert_debug_snapshot!(paths);
+    }
+
+    #[test]
+    fn word_page_test() {
+        let site = create_site();
+
+        insta::assert_snapshot!(site_file(&site, "words/abala.html"));
+    }
+
+    #[test]
+    fn letter_page_test() {
+        let site = create_site();
+
+        insta::assert_snapshot!(site_file(&site, "letters/ā.html"));
+    }
+
+    #[test]
+    fn search_index_test() {
+        let site = create_site();
+
+        insta::assert_snapshot!(site_file(&site, "search/0062.json"));
+    }
+
+    #[test]
+    fn sitemap_test() {
+        let site = create_site();
+
+        insta::assert_snapshot!(site_file(&site, "sitemap-1.xml"));
+    }
+
+    fn resolve_href(page_path: &str, href: &str) -> String {
+        let mut parts: Vec<&str> = page_path.split('/').collect();
+        parts.pop();
+        for part in href.split('/') {
+            if part == ".." {
+                parts.pop();
+            } else {
+                parts.push(part);
+            }
+        }
+
+        parts.join("/")
+    }
+
+    #[test_case("href=\"bword://abala\"", "href=\"../words/abala.html\"")]
+    #[test_case("href=\"bword://mn10\"", "href=\"../index.html?q=mn10\"")]
+    #[test_case(
+        "href=\"https://suttacentral.net/mn10\"",
+        "href=\"https://suttacentral.net/mn10\""
+    )]
+    fn rewrite_links_test(html: &str, expected: &str) {
+        let urls: HashMap<&str, String> = vec![("abala", "words/abala.html".to_string())]
+            .into_iter()
+            .collect();
+
+        assert_eq!(rewrite_links(html, &urls, "../"), expected);
+    }
+
+    #[test]
+    fn page_links_resolve_test() {
+        let site = create_site();
+
+        let urls: HashSet<String> = site
+            .files
+            .iter()
+            .map(|f| make_url(f.extension.trim_start_matches("site/")))
+            .collect();
+        let href = Regex::new(r##"(?:href|src)="([^"#?]+)"##).expect("Unexpected");
+        for file in site.files.iter().filter(|f| f.extension.ends_with(".html")) {
+            let page_path = file.extension.trim_start_matches("site/");
+            for caps in href.captures_iter(&site_file(&site, page_path)) {
+                let target = resolve_href(&make_url(page_path), &caps[1]);
+                assert!(
+                    urls.contains(&target),
+                    "'{}' in '{}' does not resolve",
+                    &caps[1],
+                    page_path
+                );
+            }
+        }
+    }
+}
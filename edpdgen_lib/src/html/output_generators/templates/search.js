@@ -0,0 +1,74 @@
+(function () {
+  "use strict";
+
+  var MAX_RESULTS = 50;
+  var root = document.currentScript.getAttribute("data-root");
+  var input = document.getElementById("site-search-input");
+  var results = document.getElementById("site-search-results");
+  var shards = {};
+
+  // NOTE: The index is split on the first character of the terms, see search/<code point>.json.
+  function shardName(query) {
+    var cp = query.codePointAt(0).toString(16);
+    return cp.length < 4 ? ("0000" + cp).slice(-4) : cp;
+  }
+
+  function loadShard(name) {
+    if (!shards[name]) {
+      shards[name] = fetch(root + "search/" + name + ".json").then(function (r) {
+        return r.ok ? r.json() : { pages: [], terms: [] };
+      });
+    }
+    return shards[name];
+  }
+
+  function show(query, shard) {
+    var seen = {};
+    var count = 0;
+    results.textContent = "";
+    for (var i = 0; i < shard.terms.length && count < MAX_RESULTS; i++) {
+      var term = shard.terms[i];
+      if (term[0].lastIndexOf(query, 0) !== 0) {
+        continue;
+      }
+      for (var j = 0; j < term[1].length && count < MAX_RESULTS; j++) {
+        var page = shard.pages[term[1][j]];
+        if (seen[page[1]]) {
+          continue;
+        }
+        seen[page[1]] = true;
+        count++;
+
+        var a = document.createElement("a");
+        a.href = root + page[1];
+        a.textContent = page[0];
+        var li = document.createElement("li");
+        li.appendChild(a);
+        if (term[0] !== page[0].toLowerCase()) {
+          li.appendChild(document.createTextNode(" (" + term[0] + ")"));
+        }
+        results.appendChild(li);
+      }
+    }
+  }
+
+  function search() {
+    var query = input.value.trim().toLowerCase();
+    if (!query) {
+      results.textContent = "";
+      return;
+    }
+    loadShard(shardName(query)).then(function (shard) {
+      if (input.value.trim().toLowerCase() === query) {
+        show(query, shard);
+      }
+    });
+  }
+
+  input.addEventListener("input", search);
+  var q = new URLSearchParams(window.location.search).get("q");
+  if (q) {
+    input.value = q;
+    search();
+  }
+})();
@@ -0,0 +1,330 @@
+use crate::inflections::{load_patterns, InflectionRow, PATTERNS};
+use crate::input::create_csv_reader;
+use crate::input::dpd::DpdPaliWord;
+use itertools::Itertools;
+use pls_core_extras::inflection_generator::{InflectionGenerator, PlsInflectionGenerator};
+use pls_core_extras::logger::PlsLogger;
+use rusqlite::{params, Connection, Transaction};
+use std::collections::{BTreeMap, HashMap};
+use std::ffi::OsStr;
+use std::fs;
+use std::path::Path;
+
+///
+/// Builds the inflections db read by PlsInflectionGenerator:
+/// - _stems: pāli1, stem and pattern of every inflected word of the input csv.
+/// - _index: name, inflection_class and an example word ('like') of every pattern.
+/// - one table per pattern: label, number and comma separated endings.
+///
+/// Patterns are read from the given tsv file or folder of tsv files (same format as the
+/// bundled patterns.tsv), defaulting to the bundled one. The db is then checked by opening it
+/// with PlsInflectionGenerator.
+///
+pub fn build_inflections_db(
+    input_data_path: &Path,
+    patterns_path: Option<&Path>,
+    db_path: &Path,
+    logger: &dyn PlsLogger,
+) -> Result<(), String> {
+    let patterns = match patterns_path {
+        Some(path) => read_pattern_sources(path, logger)?,
+        None => PATTERNS.to_string(),
+    };
+    let patterns: BTreeMap<String, Vec<InflectionRow>> =
+        load_patterns(&patterns)?.into_iter().collect();
+
+    let rdr = create_csv_reader(input_data_path, logger)?;
+    let mut stems: BTreeMap<String, (String, String)> = BTreeMap::new();
+    for (i, r) in rdr.into_deserialize::<DpdPaliWord>().enumerate() {
+        match r {
+            Ok(w) if w.stem.is_empty() || w.stem == "-" || w.pattern.is_empty() => {}
+            Ok(w) => {
+                stems.insert(w.pali1, (w.stem, w.pattern));
+            }
+            Err(e) => logger.error(&format!(
+                "Unable to deserialize record #{}. Error: {}.",
+                i, e
+            )),
+        }
+    }
+
+    logger.info(&format!("Writing {:?}.", db_path));
+    if db_path.exists() {
+        fs::remove_file(db_path).map_err(|e| e.to_string())?;
+    }
+    let mut conn = Connection::open(db_path).map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    write_stems(&tx, &stems)?;
+    write_patterns(&tx, &patterns, &stems)?;
+    tx.commit().map_err(|e| e.to_string())?;
+    conn.close().map_err(|(_, e)| e.to_string())?;
+    logger.info(&format!(
+        "... done writing {} stems and {} patterns.",
+        stems.len(),
+        patterns.len()
+    ));
+
+    create_inflection_generator(db_path, logger)?.check_inflection_db(logger)
+}
+
+fn create_inflection_generator(
+    db_path: &Path,
+    logger: &dyn PlsLogger,
+) -> Result<PlsInflectionGenerator, String> {
+    PlsInflectionGenerator::new(
+        "en",
+        env!("CARGO_PKG_VERSION"),
+        env!("CARGO_PKG_NAME"),
+        db_path
+            .to_str()
+            .ok_or_else(|| format!("Invalid path {:?}.", db_path))?,
+        logger,
+    )
+}
+
+fn read_pattern_sources(path: &Path, logger: &dyn PlsLogger) -> Result<String, String> {
+    let files = if path.is_dir() {
+        fs::read_dir(path)
+            .map_err(|e| e.to_string())?
+            .map(|e| e.map(|e| e.path()).map_err(|e| e.to_string()))
+            .collect::<Result<Vec<_>, String>>()?
+            .into_iter()
+            .filter(|p| p.extension() == Some(OsStr::new("tsv")))
+            .sorted()
+            .collect()
+    } else {
+        vec![path.to_path_buf()]
+    };
+
+    // NOTE: Every source has its own header, only the first one is kept.
+    let mut patterns = String::new();
+    for (i, file) in files.iter().enumerate() {
+        logger.info(&format!("Loading patterns from {:?}.", file));
+        let source = fs::read_to_string(file).map_err(|e| e.to_string())?;
+        let mut lines = source.lines().filter(|l| !l.starts_with('#'));
+        if i > 0 {
+            lines.next();
+        }
+        for l in lines {
+            patterns.push_str(l);
+            patterns.push('\n');
+        }
+    }
+
+    Ok(patterns)
+}
+
+fn write_stems(tx: &Transaction, stems: &BTreeMap<String, (String, String)>) -> Result<(), String> {
+    tx.execute_batch(
+        "CREATE TABLE _stems (
+            pāli1 TEXT PRIMARY KEY,
+            stem TEXT NOT NULL,
+            pattern TEXT NOT NULL
+        );",
+    )
+    .map_err(|e| e.to_string())?;
+
+    let mut stmt = tx
+        .prepare("INSERT INTO _stems (pāli1, stem, pattern) VALUES (?1, ?2, ?3)")
+        .map_err(|e| e.to_string())?;
+    for (pali1, (stem, pattern)) in stems {
+        stmt.execute(params![pali1, stem, pattern])
+            .map_err(|e| e.to_string())?;
+    }
+
+    Ok(())
+}
+
+const PERSONS: &[&str] = &["1st", "2nd", "3rd"];
+
+fn inflection_class(rows: &[InflectionRow]) -> &'static str {
+    if rows
+        .iter()
+        .any(|r| r.label.split_whitespace().any(|l| PERSONS.contains(&l)))
+    {
+        "verb"
+    } else {
+        "nominal"
+    }
+}
+
+fn write_patterns(
+    tx: &Transaction,
+    patterns: &BTreeMap<String, Vec<InflectionRow>>,
+    stems: &BTreeMap<String, (String, String)>,
+) -> Result<(), String> {
+    tx.execute_batch(
+        "CREATE TABLE _index (
+            name TEXT PRIMARY KEY,
+            inflection_class TEXT NOT NULL,
+            like TEXT NOT NULL
+        );",
+    )
+    .map_err(|e| e.to_string())?;
+
+    let mut likes: HashMap<&str, &str> = HashMap::new();
+    for (pali1, (_, pattern)) in stems {
+        likes
+            .entry(pattern.as_str())
+            .or_insert_with(|| pali1.as_str());
+    }
+
+    for (pattern, rows) in patterns {
+        tx.execute(
+            "INSERT INTO _index (name, inflection_class, like) VALUES (?1, ?2, ?3)",
+            params![
+                pattern,
+                inflection_class(rows),
+                likes.get(pattern.as_str()).copied().unwrap_or_default()
+            ],
+        )
+        .map_err(|e| e.to_string())?;
+
+        tx.execute_batch(&format!(
+            "CREATE TABLE \"{}\" (label TEXT NOT NULL, number TEXT NOT NULL, inflections TEXT NOT NULL);",
+            pattern
+        ))
+        .map_err(|e| e.to_string())?;
+        let mut stmt = tx
+            .prepare(&format!(
+                "INSERT INTO \"{}\" (label, number, inflections) VALUES (?1, ?2, ?3)",
+                pattern
+            ))
+            .map_err(|e| e.to_string())?;
+        for r in rows {
+            for (number, endings) in &[("sg", &r.sg), ("pl", &r.pl)] {
+                stmt.execute(params![r.label, number, endings.join(",")])
+                    .map_err(|e| e.to_string())?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::inflections::BuiltinInflectionGenerator;
+    use crate::input::make_group_id;
+    use crate::resolve_file_in_manifest_dir;
+    use crate::tests::TestLogger;
+    use test_case::test_case;
+
+    fn build_db(dir: &Path, patterns_path: Option<&Path>) -> Result<Connection, String> {
+        let input_data_path =
+            resolve_file_in_manifest_dir("Pali_English_Dictionary_10_rows-full.csv")
+                .expect("must exist!");
+        let db_path = dir.join("inflections.db");
+        build_inflections_db(
+            &input_data_path,
+            patterns_path,
+            &db_path,
+            &TestLogger::new(),
+        )?;
+
+        Ok(Connection::open(db_path).expect("Unexpected"))
+    }
+
+    #[test]
+    fn build_inflections_db_from_bundled_patterns_test() {
+        let dir = tempfile::tempdir().expect("Unexpected");
+        let conn = build_db(dir.path(), None).expect("Unexpected");
+
+        let (stem, pattern): (String, String) = conn
+            .query_row(
+                "SELECT stem, pattern FROM _stems WHERE pāli1 = 'ābādha'",
+                params![],
+                |r| Ok((r.get(0)?, r.get(1)?)),
+            )
+            .expect("Unexpected");
+        assert_eq!((stem.as_str(), pattern.as_str()), ("ābādh", "a masc"));
+
+        let (class, like): (String, String) = conn
+            .query_row(
+                "SELECT inflection_class, like FROM _index WHERE name = 'a masc'",
+                params![],
+                |r| Ok((r.get(0)?, r.get(1)?)),
+            )
+            .expect("Unexpected");
+        assert_eq!((class.as_str(), like.as_str()), ("nominal", "adhikāra 1"));
+
+        let inflections: String = conn
+            .query_row(
+                "SELECT inflections FROM 'a masc' WHERE label = 'abl' AND number = 'sg'",
+                params![],
+                |r| r.get(0),
+            )
+            .expect("Unexpected");
+        assert_eq!(inflections, "ā,asmā,amhā");
+    }
+
+    #[test]
+    fn build_inflections_db_from_pattern_folder_test() {
+        let dir = tempfile::tempdir().expect("Unexpected");
+        let patterns_dir = dir.path().join("patterns");
+        fs::create_dir_all(&patterns_dir).expect("Unexpected");
+        fs::write(
+            patterns_dir.join("1.tsv"),
+            "pattern\tlabel\tsg\tpl\na masc\tnom\to\tā\n",
+        )
+        .expect("Unexpected");
+        fs::write(
+            patterns_dir.join("2.tsv"),
+            "# verbs\npattern\tlabel\tsg\tpl\nati pr\t3rd\tati\tanti\n",
+        )
+        .expect("Unexpected");
+
+        let conn = build_db(dir.path(), Some(&patterns_dir)).expect("Unexpected");
+
+        let classes: Vec<(String, String)> = conn
+            .prepare("SELECT name, inflection_class FROM _index ORDER BY name")
+            .expect("Unexpected")
+            .query_map(params![], |r| Ok((r.get(0)?, r.get(1)?)))
+            .expect("Unexpected")
+            .collect::<Result<_, _>>()
+            .expect("Unexpected");
+        assert_eq!(
+            classes,
+            vec![
+                ("a masc".to_string(), "nominal".to_string()),
+                ("ati pr".to_string(), "verb".to_string())
+            ]
+        );
+    }
+
+    #[test_case("ābādha")]
+    #[test_case("ābādhikinī")]
+    #[test_case("abahulīkata")]
+    #[test_case("abyāpajjha")]
+    fn pls_inflection_generator_reads_db_test(pali1: &str) {
+        let dir = tempfile::tempdir().expect("Unexpected");
+        build_db(dir.path(), None).expect("Unexpected");
+        let input_data_path =
+            resolve_file_in_manifest_dir("Pali_English_Dictionary_10_rows-full.csv")
+                .expect("must exist!");
+        let logger = TestLogger::new();
+        let pls_igen = create_inflection_generator(&dir.path().join("inflections.db"), &logger)
+            .expect("Unexpected");
+        let builtin_igen =
+            BuiltinInflectionGenerator::from_csv(&input_data_path, &logger).expect("Unexpected");
+        // NOTE: Whether the headword itself is listed is up to the generator.
+        let headword = make_group_id(pali1);
+        let sorted = |inflections: Vec<String>| -> Vec<String> {
+            inflections
+                .into_iter()
+                .filter(|i| i != &headword)
+                .sorted()
+                .dedup()
+                .collect()
+        };
+
+        let inflections = sorted(pls_igen.generate_all_inflections(pali1));
+
+        assert!(!inflections.is_empty());
+        assert_eq!(
+            inflections,
+            sorted(builtin_igen.generate_all_inflections(pali1))
+        );
+    }
+}
@@ -0,0 +1,247 @@
+use crate::input::dpd::DpdPaliWord;
+use crate::input::{create_csv_reader, make_group_id};
+use itertools::Itertools;
+use pls_core_extras::inflection_generator::InflectionGenerator;
+use pls_core_extras::logger::PlsLogger;
+use std::collections::HashMap;
+use std::path::Path;
+use tera::{Context, Tera};
+
+pub mod db;
+
+lazy_static! {
+    static ref TEMPLATES: Tera = {
+        let mut tera = Tera::default();
+        tera.add_raw_templates(vec![(
+            "inflection_table",
+            include_str!("templates/inflection_table.html"),
+        )])
+        .expect("Unexpected failure adding template");
+        tera
+    };
+}
+
+const PATTERNS: &str = include_str!("patterns.tsv");
+
+///
+/// The bundled data that affects generated inflections, so cached renders are invalidated
+/// when any of it changes.
+///
+pub const INFLECTION_DATA: &[&str] = &[PATTERNS, include_str!("templates/inflection_table.html")];
+
+#[derive(Debug, Deserialize)]
+struct PatternRecord {
+    pattern: String,
+    label: String,
+    sg: String,
+    pl: String,
+}
+
+#[derive(Debug, Serialize)]
+struct InflectionRow {
+    label: String,
+    sg: Vec<String>,
+    pl: Vec<String>,
+}
+
+struct WordStem {
+    stem: String,
+    pattern: String,
+}
+
+///
+/// Generates inflections from the Stem and Pattern columns of the input using the
+/// declension and conjugation tables bundled in patterns.tsv, i.e. needs no inflections db.
+///
+pub struct BuiltinInflectionGenerator {
+    patterns: HashMap<String, Vec<InflectionRow>>,
+    words: HashMap<String, WordStem>,
+}
+
+impl BuiltinInflectionGenerator {
+    pub fn new(words: impl Iterator<Item = DpdPaliWord>) -> Result<Self, String> {
+        let words = words
+            .filter(|w| !w.stem.is_empty() && w.stem != "-" && !w.pattern.is_empty())
+            .map(|w| {
+                (
+                    w.pali1,
+                    WordStem {
+                        stem: w.stem,
+                        pattern: w.pattern,
+                    },
+                )
+            })
+            .collect();
+
+        Ok(BuiltinInflectionGenerator {
+            patterns: load_patterns(PATTERNS)?,
+            words,
+        })
+    }
+
+    pub fn from_csv(input_data_path: &Path, logger: &dyn PlsLogger) -> Result<Self, String> {
+        let rdr = create_csv_reader(input_data_path, logger)?;
+
+        // NOTE: Records that fail to deserialize are reported when the words are loaded.
+        BuiltinInflectionGenerator::new(
+            rdr.into_deserialize::<DpdPaliWord>().filter_map(|r| r.ok()),
+        )
+    }
+
+    fn inflection_rows(&self, pali1: &str) -> Option<Vec<InflectionRow>> {
+        let word = self.words.get(pali1)?;
+        let rows = self.patterns.get(&word.pattern)?;
+
+        let apply = |endings: &[String]| {
+            endings
+                .iter()
+                .map(|e| format!("{}{}", word.stem, e))
+                .collect()
+        };
+
+        Some(
+            rows.iter()
+                .map(|r| InflectionRow {
+                    label: r.label.clone(),
+                    sg: apply(&r.sg),
+                    pl: apply(&r.pl),
+                })
+                .collect(),
+        )
+    }
+}
+
+fn load_patterns(patterns: &str) -> Result<HashMap<String, Vec<InflectionRow>>, String> {
+    let rdr = csv::ReaderBuilder::new()
+        .delimiter(b'\t')
+        .comment(Some(b'#'))
+        .from_reader(patterns.as_bytes());
+
+    let mut tables: HashMap<String, Vec<InflectionRow>> = HashMap::new();
+    for r in rdr.into_deserialize::<PatternRecord>() {
+        let r = r.map_err(|e| e.to_string())?;
+        let split = |endings: &str| endings.split_whitespace().map(String::from).collect();
+        tables.entry(r.pattern).or_default().push(InflectionRow {
+            label: r.label,
+            sg: split(&r.sg),
+            pl: split(&r.pl),
+        });
+    }
+
+    Ok(tables)
+}
+
+impl InflectionGenerator for BuiltinInflectionGenerator {
+    fn check_inflection_db(&self, logger: &dyn PlsLogger) -> Result<(), String> {
+        let unknown_patterns: Vec<&str> = self
+            .words
+            .values()
+            .map(|w| w.pattern.as_str())
+            .filter(|p| !self.patterns.contains_key(*p))
+            .sorted()
+            .dedup()
+            .collect();
+
+        logger.info(&format!(
+            "Using built-in inflection patterns for {} words.",
+            self.words.len()
+        ));
+        if !unknown_patterns.is_empty() {
+            logger.warning(&format!(
+                "No built-in inflection pattern for '{}', words with these will not be inflected.",
+                unknown_patterns.join("', '")
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn generate_inflection_table_html(&self, pali1: &str) -> String {
+        let rows = match self.inflection_rows(pali1) {
+            Some(rows) => rows,
+            None => return "".to_string(),
+        };
+
+        let mut context = Context::new();
+        context.insert("pattern", &self.words[pali1].pattern);
+        context.insert("rows", &rows);
+
+        TEMPLATES
+            .render("inflection_table", &context)
+            .unwrap_or_default()
+    }
+
+    fn generate_all_inflections(&self, pali1: &str) -> Vec<String> {
+        let word = make_group_id(pali1);
+
+        self.inflection_rows(pali1)
+            .unwrap_or_default()
+            .into_iter()
+            .flat_map(|r| r.sg.into_iter().chain(r.pl))
+            .filter(|i| i != &word)
+            .unique()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resolve_file_in_manifest_dir;
+    use crate::tests::TestLogger;
+    use test_case::test_case;
+
+    fn create_generator() -> BuiltinInflectionGenerator {
+        let path = resolve_file_in_manifest_dir("Pali_English_Dictionary_10_rows-full.csv")
+            .expect("must exist!");
+
+        BuiltinInflectionGenerator::from_csv(&path, &TestLogger::new()).expect("Unexpected")
+    }
+
+    #[test]
+    fn all_patterns_are_well_formed_test() {
+        let patterns = load_patterns(PATTERNS).expect("Unexpected");
+
+        assert!(patterns.contains_key("a masc"));
+        for (pattern, rows) in patterns {
+            assert!(!rows.is_empty(), "{}", pattern);
+            for r in rows {
+                assert!(
+                    !r.sg.is_empty() && !r.pl.is_empty(),
+                    "{} {}",
+                    pattern,
+                    r.label
+                );
+            }
+        }
+    }
+
+    #[test_case("ābādha", &["ābādho", "ābādhā", "ābādhaṃ", "ābādhe"]; "a masc")]
+    #[test_case("ābādhikinī", &["ābādhikiniṃ", "ābādhikiniyo", "ābādhikinīsu"]; "ii fem")]
+    #[test_case("abala 1", &["abalo", "abalāni", "abalāyo"]; "a adj")]
+    fn generate_all_inflections_test(pali1: &str, expected: &[&str]) {
+        let inflections = create_generator().generate_all_inflections(pali1);
+
+        for e in expected {
+            assert!(inflections.contains(&e.to_string()), "{}", e);
+        }
+        assert!(!inflections.contains(&make_group_id(pali1)));
+        assert_eq!(inflections.len(), inflections.iter().unique().count());
+    }
+
+    #[test_case("ābādhato"; "indeclinable")]
+    #[test_case("unknown"; "not in csv")]
+    fn words_without_pattern_are_not_inflected_test(pali1: &str) {
+        let igen = create_generator();
+
+        assert!(igen.generate_all_inflections(pali1).is_empty());
+        assert_eq!(igen.generate_inflection_table_html(pali1), "");
+    }
+
+    #[test]
+    fn generate_inflection_table_html_test() {
+        let html = create_generator().generate_inflection_table_html("ābādha");
+
+        insta::assert_snapshot!(html);
+    }
+}
@@ -34,6 +34,12 @@ pub fn make_sort_key(id: &str) -> String {
     sk.into_owned()
 }
 
+pub fn make_group_id(id: &str) -> String {
+    let gid = PALI1_CRACKER.replace(id, |caps: &Captures| caps[1].to_string());
+
+    gid.into_owned()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,243 @@
+use crate::input::dpd::DpdPaliWord;
+use crate::input::{make_group_id, make_sort_key};
+use crate::jsonl::JsonlPaliWord;
+use schemars::{schema_for, JsonSchema};
+
+///
+/// A word with the field names of DpdPaliWord, which are stable unlike the column names.
+///
+#[derive(Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct DpdRecord<'a> {
+    /// The word without its homograph number, e.g. 'abala' for 'abala 1'.
+    group_id: String,
+    /// Orders words alphabetically as per the Pāli alphabet.
+    sort_key: String,
+    /// All inflected forms of the word.
+    inflections: &'a [String],
+    /// The 'Pāli1' column.
+    pali1: &'a str,
+    /// The 'Pāli2' column.
+    pali2: &'a str,
+    /// The 'Fin' column.
+    fin: &'a str,
+    /// The 'POS' column.
+    pos: &'a str,
+    /// The 'Grammar' column.
+    grammar: &'a str,
+    /// The 'Derived from' column.
+    derived_from: &'a str,
+    /// The 'Neg' column.
+    neg: &'a str,
+    /// The 'Verb' column.
+    verb: &'a str,
+    /// The 'Trans' column.
+    trans: &'a str,
+    /// The 'Case' column.
+    case: &'a str,
+    /// The 'Meaning IN CONTEXT' column.
+    in_english: &'a str,
+    /// The 'Sanskrit' column.
+    sanskrit: &'a str,
+    /// The 'Sk Root' column.
+    sanskrit_root: &'a str,
+    /// The 'Family' column.
+    family: &'a str,
+    /// The 'Pāli Root' column.
+    pali_root: &'a str,
+    /// The 'V' column.
+    v: &'a str,
+    /// The 'Grp' column.
+    grp: &'a str,
+    /// The 'Sgn' column.
+    sgn: &'a str,
+    /// The 'Root Meaning' column.
+    root_meaning: &'a str,
+    /// The 'Base' column.
+    base: &'a str,
+    /// The 'Construction' column.
+    construction: &'a str,
+    /// The 'Derivative' column.
+    derivative: &'a str,
+    /// The 'Suffix' column.
+    suffix: &'a str,
+    /// The 'Compound' column.
+    compound: &'a str,
+    /// The 'Compound Construction' column.
+    compound_construction: &'a str,
+    /// The 'Source1' column.
+    source1: &'a str,
+    /// The 'Sutta1' column.
+    sutta1: &'a str,
+    /// The 'Example1' column.
+    example1: &'a str,
+    /// The 'Source 2' column.
+    source2: &'a str,
+    /// The 'Sutta2' column.
+    sutta2: &'a str,
+    /// The 'Example 2' column.
+    example2: &'a str,
+    /// The 'Antonyms' column.
+    antonyms: &'a str,
+    /// The 'Synonyms – different word' column.
+    synonyms: &'a str,
+    /// The 'Variant – same constr or diff reading' column.
+    variant: &'a str,
+    /// The 'Commentary' column.
+    commentary: &'a str,
+    /// The 'Literal Meaning' column.
+    literal_meaning: &'a str,
+    /// The 'Root In Comps' column.
+    root_in_compound: &'a str,
+    /// The 'Notes' column.
+    notes: &'a str,
+    /// The 'Stem' column.
+    stem: &'a str,
+    /// The 'Pattern' column.
+    pattern: &'a str,
+    /// The 'Buddhadatta' column.
+    buddhadatta: &'a str,
+    /// The 'Cl' column.
+    cl: &'a str,
+    /// The 'Sk Root Mn' column.
+    sanskrit_root_meaning: &'a str,
+    /// The 'Phonetic Changes' column.
+    phonetic_changes: &'a str,
+    /// The 'Link' column.
+    link: &'a str,
+}
+
+impl<'a> DpdRecord<'a> {
+    fn new(word: &'a DpdPaliWord, inflections: &'a [String]) -> Self {
+        DpdRecord {
+            group_id: word.group_id(),
+            sort_key: word.sort_key(),
+            inflections,
+            pali1: &word.pali1,
+            pali2: &word.pali2,
+            fin: &word.fin,
+            pos: &word.pos,
+            grammar: &word.grammar,
+            derived_from: &word.derived_from,
+            neg: &word.neg,
+            verb: &word.verb,
+            trans: &word.trans,
+            case: &word.case,
+            in_english: &word.in_english,
+            sanskrit: &word.sanskrit,
+            sanskrit_root: &word.sanskrit_root,
+            family: &word.family,
+            pali_root: &word.pali_root,
+            v: &word.v,
+            grp: &word.grp,
+            sgn: &word.sgn,
+            root_meaning: &word.root_meaning,
+            base: &word.base,
+            construction: &word.construction,
+            derivative: &word.derivative,
+            suffix: &word.suffix,
+            compound: &word.compound,
+            compound_construction: &word.compound_construction,
+            source1: &word.source1,
+            sutta1: &word.sutta1,
+            example1: &word.example1,
+            source2: &word.source2,
+            sutta2: &word.sutta2,
+            example2: &word.example2,
+            antonyms: &word.antonyms,
+            synonyms: &word.synonyms,
+            variant: &word.variant,
+            commentary: &word.commentary,
+            literal_meaning: &word.literal_meaning,
+            root_in_compound: &word.root_in_compound,
+            notes: &word.notes,
+            stem: &word.stem,
+            pattern: &word.pattern,
+            buddhadatta: &word.buddhadatta,
+            cl: &word.cl,
+            sanskrit_root_meaning: &word.sanskrit_root_meaning,
+            phonetic_changes: &word.phonetic_changes,
+            link: &word.link,
+        }
+    }
+}
+
+impl JsonlPaliWord for DpdPaliWord {
+    fn id(&self) -> &str {
+        &self.pali1
+    }
+
+    fn sort_key(&self) -> String {
+        make_sort_key(self.id())
+    }
+
+    fn group_id(&self) -> String {
+        make_group_id(self.id())
+    }
+
+    fn record(&self, inflections: &[String]) -> Result<String, String> {
+        serde_json::to_string(&DpdRecord::new(self, inflections)).map_err(|e| e.to_string())
+    }
+
+    fn schema() -> Result<String, String> {
+        serde_json::to_string_pretty(&schema_for!(DpdRecord)).map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::create_csv_reader;
+    use crate::jsonl::input_parsers::load_words;
+    use crate::resolve_file_in_manifest_dir;
+    use crate::tests::TestLogger;
+    use test_case::test_case;
+
+    #[test_case(0)]
+    #[test_case(4)]
+    #[test_case(9)]
+    fn record_tests(rec_number: usize) {
+        let path = resolve_file_in_manifest_dir("Pali_English_Dictionary_10_rows-full.csv")
+            .expect("must exist!");
+        let l = TestLogger::new();
+        let mut recs = load_words::<DpdPaliWord>(&path, &l).expect("unexpected");
+
+        let record = recs
+            .nth(rec_number)
+            .map(|r| r.record(&["x".to_string()]).expect("unexpected"))
+            .expect("unexpected");
+
+        insta::assert_snapshot!(record);
+    }
+
+    #[test]
+    fn record_covers_every_column_test() {
+        let path = resolve_file_in_manifest_dir("Pali_English_Dictionary_10_rows-full.csv")
+            .expect("must exist!");
+        let l = TestLogger::new();
+        let headers = create_csv_reader(&path, &l)
+            .expect("unexpected")
+            .headers()
+            .expect("unexpected")
+            .clone();
+        // NOTE: Each column holds its own header, so a dropped column is missing from the values.
+        let word: DpdPaliWord = headers.deserialize(Some(&headers)).expect("unexpected");
+
+        let record: serde_json::Value =
+            serde_json::from_str(&word.record(&[]).expect("unexpected")).expect("unexpected");
+        let values: Vec<_> = record
+            .as_object()
+            .expect("unexpected")
+            .values()
+            .filter_map(|v| v.as_str())
+            .collect();
+        let columns = serde_json::to_value(&word).expect("unexpected");
+        for column in columns.as_object().expect("unexpected").keys() {
+            assert!(
+                values.contains(&column.as_str()),
+                "'{}' is not in the record.",
+                column
+            );
+        }
+    }
+}
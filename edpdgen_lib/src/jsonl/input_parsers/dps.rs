@@ -0,0 +1,187 @@
+use crate::input::dps::DpsPaliWord;
+use crate::input::{make_group_id, make_sort_key};
+use crate::jsonl::JsonlPaliWord;
+use schemars::{schema_for, JsonSchema};
+
+///
+/// A word with the field names of DpsPaliWord, which are stable unlike the column names.
+///
+#[derive(Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct DpsRecord<'a> {
+    /// The word without its homograph number, e.g. 'abala' for 'abala 1'.
+    group_id: String,
+    /// Orders words alphabetically as per the Pāli alphabet.
+    sort_key: String,
+    /// All inflected forms of the word.
+    inflections: &'a [String],
+    /// The 'Pāli1' column.
+    pali: &'a str,
+    /// The 'Fin' column.
+    fin: &'a str,
+    /// The 'POS' column.
+    pos: &'a str,
+    /// The 'Grammar' column.
+    grammar: &'a str,
+    /// The 'Derived from' column.
+    derived_from: &'a str,
+    /// The 'Neg' column.
+    neg: &'a str,
+    /// The 'Verb' column.
+    verb: &'a str,
+    /// The 'Trans' column.
+    trans: &'a str,
+    /// The 'Case' column.
+    case: &'a str,
+    /// The 'Meaning IN CONTEXT' column.
+    in_english: &'a str,
+    /// The 'Meaning in native language' column.
+    in_russian: &'a str,
+    /// The 'Pāli Root' column.
+    pali_root: &'a str,
+    /// The 'Base' column.
+    base: &'a str,
+    /// The 'Construction' column.
+    construction: &'a str,
+    /// The 'Sanskrit' column.
+    sanskrit: &'a str,
+    /// The 'Sk Root' column.
+    sanskrit_root: &'a str,
+    /// The 'Commentary' column.
+    commentary: &'a str,
+    /// The 'Notes' column.
+    notes: &'a str,
+    /// The 'Source1' column.
+    source1: &'a str,
+    /// The 'Example1' column.
+    example1: &'a str,
+    /// The 'Sutta1' column.
+    sutta1: &'a str,
+    /// The 'Source 2' column.
+    source2: &'a str,
+    /// The 'Example 2' column.
+    example2: &'a str,
+    /// The 'Sutta2' column.
+    sutta2: &'a str,
+    /// The 'Chapter' column.
+    chapter: &'a str,
+    /// The 'Test' column.
+    test: &'a str,
+    /// The 'Variant' column.
+    variant: &'a str,
+}
+
+impl<'a> DpsRecord<'a> {
+    fn new(word: &'a DpsPaliWord, inflections: &'a [String]) -> Self {
+        DpsRecord {
+            group_id: word.group_id(),
+            sort_key: word.sort_key(),
+            inflections,
+            pali: &word.pali,
+            fin: &word.fin,
+            pos: &word.pos,
+            grammar: &word.grammar,
+            derived_from: &word.derived_from,
+            neg: &word.neg,
+            verb: &word.verb,
+            trans: &word.trans,
+            case: &word.case,
+            in_english: &word.in_english,
+            in_russian: &word.in_russian,
+            pali_root: &word.pali_root,
+            base: &word.base,
+            construction: &word.construction,
+            sanskrit: &word.sanskrit,
+            sanskrit_root: &word.sanskrit_root,
+            commentary: &word.commentary,
+            notes: &word.notes,
+            source1: &word.source1,
+            example1: &word.example1,
+            sutta1: &word.sutta1,
+            source2: &word.source2,
+            example2: &word.example2,
+            sutta2: &word.sutta2,
+            chapter: &word.chapter,
+            test: &word.test,
+            variant: &word.variant,
+        }
+    }
+}
+
+impl JsonlPaliWord for DpsPaliWord {
+    fn id(&self) -> &str {
+        &self.pali
+    }
+
+    fn sort_key(&self) -> String {
+        make_sort_key(self.id())
+    }
+
+    fn group_id(&self) -> String {
+        make_group_id(self.id())
+    }
+
+    fn record(&self, inflections: &[String]) -> Result<String, String> {
+        serde_json::to_string(&DpsRecord::new(self, inflections)).map_err(|e| e.to_string())
+    }
+
+    fn schema() -> Result<String, String> {
+        serde_json::to_string_pretty(&schema_for!(DpsRecord)).map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::create_csv_reader;
+    use crate::jsonl::input_parsers::load_words;
+    use crate::resolve_file_in_manifest_dir;
+    use crate::tests::TestLogger;
+    use test_case::test_case;
+
+    #[test_case(0)]
+    #[test_case(2)]
+    #[test_case(4)]
+    fn record_tests(rec_number: usize) {
+        let path = resolve_file_in_manifest_dir("dps_sample.csv").expect("must exist!");
+        let l = TestLogger::new();
+        let mut recs = load_words::<DpsPaliWord>(&path, &l).expect("unexpected");
+
+        let record = recs
+            .nth(rec_number)
+            .map(|r| r.record(&["x".to_string()]).expect("unexpected"))
+            .expect("unexpected");
+
+        insta::assert_snapshot!(record);
+    }
+
+    #[test]
+    fn record_covers_every_column_test() {
+        let path = resolve_file_in_manifest_dir("dps_sample.csv").expect("must exist!");
+        let l = TestLogger::new();
+        let headers = create_csv_reader(&path, &l)
+            .expect("unexpected")
+            .headers()
+            .expect("unexpected")
+            .clone();
+        // NOTE: Each column holds its own header, so a dropped column is missing from the values.
+        let word: DpsPaliWord = headers.deserialize(Some(&headers)).expect("unexpected");
+
+        let record: serde_json::Value =
+            serde_json::from_str(&word.record(&[]).expect("unexpected")).expect("unexpected");
+        let values: Vec<_> = record
+            .as_object()
+            .expect("unexpected")
+            .values()
+            .filter_map(|v| v.as_str())
+            .collect();
+        let columns = serde_json::to_value(&word).expect("unexpected");
+        for column in columns.as_object().expect("unexpected").keys() {
+            assert!(
+                values.contains(&column.as_str()),
+                "'{}' is not in the record.",
+                column
+            );
+        }
+    }
+}
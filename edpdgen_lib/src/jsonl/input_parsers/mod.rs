@@ -0,0 +1,31 @@
+use crate::input::create_csv_reader;
+use crate::jsonl::JsonlPaliWord;
+use pls_core_extras::logger::PlsLogger;
+use std::path::Path;
+
+pub mod dpd;
+pub mod dps;
+
+pub fn load_words<'a, T: 'a + serde::de::DeserializeOwned + JsonlPaliWord>(
+    path: &Path,
+    logger: &'a dyn PlsLogger,
+) -> Result<impl Iterator<Item = impl JsonlPaliWord> + 'a, String> {
+    let rdr = create_csv_reader(path, logger)?;
+
+    let words = rdr
+        .into_deserialize::<T>()
+        .enumerate()
+        .filter_map(move |(i, r)| match r {
+            Ok(w) => Some(w),
+            Err(e) => {
+                logger.error(&format!(
+                    "Unable to deserialize record #{}. Error: {}.",
+                    i, e
+                ));
+                None
+            }
+        });
+
+    logger.info(&format!("... done loading words from {:?}.", &path));
+    Ok(words)
+}
@@ -0,0 +1,72 @@
+use crate::input::dpd::DpdPaliWord;
+use crate::input::dps::DpsPaliWord;
+use crate::input::input_format::InputFormat;
+use crate::output::output_generator::OutputGenerator;
+use crate::{DictionaryFiles, DictionaryInfo};
+use pls_core_extras::inflection_generator::InflectionGenerator;
+use pls_core_extras::logger::PlsLogger;
+use std::path::Path;
+
+mod input_parsers;
+mod output_generators;
+
+pub struct JsonlGenerator;
+
+impl OutputGenerator for JsonlGenerator {
+    fn name(&self) -> &'static str {
+        "jsonl"
+    }
+
+    fn description(&self) -> &'static str {
+        "JSON Lines with a JSON Schema of the records."
+    }
+
+    fn input_formats(&self) -> &'static [InputFormat] {
+        &[InputFormat::Dpd, InputFormat::Dps]
+    }
+
+    fn options(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    fn build_files(
+        &self,
+        dict_info: &DictionaryInfo,
+        input_data_path: &Path,
+        igen: &dyn InflectionGenerator,
+        logger: &dyn PlsLogger,
+    ) -> Result<DictionaryFiles, String> {
+        match dict_info.input_format {
+            InputFormat::Dpd => {
+                run_for_ods_type::<DpdPaliWord>(dict_info, input_data_path, igen, logger)
+            }
+            InputFormat::Dps => {
+                run_for_ods_type::<DpsPaliWord>(dict_info, input_data_path, igen, logger)
+            }
+        }
+    }
+}
+
+pub trait JsonlPaliWord {
+    fn id(&self) -> &str;
+    fn sort_key(&self) -> String;
+    fn group_id(&self) -> String;
+    /// The word as a single line of JSON.
+    fn record(&self, inflections: &[String]) -> Result<String, String>;
+    /// The JSON Schema of the records.
+    fn schema() -> Result<String, String>
+    where
+        Self: Sized;
+}
+
+pub fn run_for_ods_type<'a, T: 'a + serde::de::DeserializeOwned + JsonlPaliWord>(
+    _dict_info: &DictionaryInfo,
+    input_data_path: &Path,
+    igen: &dyn InflectionGenerator,
+    logger: &dyn PlsLogger,
+) -> Result<DictionaryFiles, String> {
+    let words = input_parsers::load_words::<T>(input_data_path, logger)?;
+    let jsonl_files = output_generators::create_dictionary(T::schema()?, words, igen, logger)?;
+
+    Ok(jsonl_files)
+}
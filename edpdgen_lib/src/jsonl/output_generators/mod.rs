@@ -0,0 +1,156 @@
+use crate::jsonl::JsonlPaliWord;
+use crate::{DictionaryData, DictionaryFile, DictionaryFiles};
+use itertools::Itertools;
+use pls_core_extras::inflection_generator::InflectionGenerator;
+use pls_core_extras::logger::PlsLogger;
+
+fn create_records(
+    words: impl Iterator<Item = impl JsonlPaliWord>,
+    igen: &dyn InflectionGenerator,
+    logger: &dyn PlsLogger,
+) -> Result<(Vec<u8>, usize), String> {
+    logger.info("Creating JSON Lines records.");
+
+    let mut jsonl = String::new();
+    let mut record_count = 0;
+    let words = words.sorted_by(|w1, w2| Ord::cmp(&w1.sort_key(), &w2.sort_key()));
+    for word in words {
+        let group_id = word.group_id();
+        let inflections: Vec<String> = igen
+            .generate_all_inflections(word.id())
+            .into_iter()
+            .filter(|i| i != &group_id)
+            .unique()
+            .collect();
+        jsonl.push_str(&word.record(&inflections)?);
+        jsonl.push('\n');
+        record_count += 1;
+
+        if record_count % 1_000 == 0 {
+            logger.info(&format!(
+                "... created {:05} JSON Lines records, ending with '{}'.",
+                record_count,
+                word.id()
+            ));
+        }
+    }
+
+    logger.info(&format!(
+        "... done creating {} JSON Lines records.",
+        record_count
+    ));
+
+    Ok((jsonl.into_bytes(), record_count))
+}
+
+///
+/// One record per line, see https://jsonlines.org, along with the JSON Schema of the records.
+///
+pub fn create_dictionary(
+    schema: String,
+    words: impl Iterator<Item = impl JsonlPaliWord>,
+    igen: &dyn InflectionGenerator,
+    logger: &dyn PlsLogger,
+) -> Result<DictionaryFiles, String> {
+    let (jsonl, word_count) = create_records(words, igen, logger)?;
+
+    Ok(DictionaryFiles {
+        word_count,
+        files: vec![
+            DictionaryFile {
+                extension: "jsonl".to_string(),
+                bom: vec![],
+                data: DictionaryData::InMemory(jsonl),
+                can_be_empty: false,
+            },
+            DictionaryFile {
+                extension: "schema.json".to_string(),
+                bom: vec![],
+                data: DictionaryData::InMemory(schema.into_bytes()),
+                can_be_empty: false,
+            },
+        ],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::dpd::DpdPaliWord;
+    use crate::input::dps::DpsPaliWord;
+    use crate::jsonl::input_parsers::load_words;
+    use crate::resolve_file_in_manifest_dir;
+    use crate::tests::{TestInflectionGenerator, TestLogger};
+    use jsonschema::JSONSchema;
+    use serde_json::Value;
+
+    fn check_records<T: 'static + serde::de::DeserializeOwned + JsonlPaliWord>(
+        file_name: &str,
+    ) -> usize {
+        let path = resolve_file_in_manifest_dir(file_name).expect("must exist!");
+        let l = TestLogger::new();
+        let words = load_words::<T>(&path, &l).expect("Unexpected");
+        let (jsonl, record_count) =
+            create_records(words, &TestInflectionGenerator::new(), &l).expect("Unexpected");
+
+        let schema: Value =
+            serde_json::from_str(&T::schema().expect("Unexpected")).expect("Unexpected");
+        let schema = JSONSchema::compile(&schema).expect("Unexpected");
+        let jsonl = String::from_utf8(jsonl).expect("Unexpected");
+        for line in jsonl.lines() {
+            let record: Value = serde_json::from_str(line).expect("Unexpected");
+            let result = schema
+                .validate(&record)
+                .map_err(|errors| errors.map(|e| e.to_string()).join("; "));
+            if let Err(e) = result {
+                panic!("'{}' is invalid: {}", line, e);
+            }
+        }
+        assert_eq!(jsonl.lines().count(), record_count);
+
+        record_count
+    }
+
+    #[test]
+    fn dpd_jsonl_is_valid_test() {
+        assert_eq!(
+            check_records::<DpdPaliWord>("Pali_English_Dictionary_10_rows-full.csv"),
+            13
+        );
+    }
+
+    #[test]
+    fn dps_jsonl_is_valid_test() {
+        assert_eq!(check_records::<DpsPaliWord>("dps_sample.csv"), 5);
+    }
+
+    #[test]
+    fn schema_rejects_unknown_fields_test() {
+        let schema: Value =
+            serde_json::from_str(&DpdPaliWord::schema().expect("Unexpected")).expect("Unexpected");
+        let schema = JSONSchema::compile(&schema).expect("Unexpected");
+
+        let mut record: Value = serde_json::from_str(
+            &load_words::<DpdPaliWord>(
+                &resolve_file_in_manifest_dir("Pali_English_Dictionary_10_rows-full.csv")
+                    .expect("must exist!"),
+                &TestLogger::new(),
+            )
+            .expect("Unexpected")
+            .next()
+            .expect("Unexpected")
+            .record(&[])
+            .expect("Unexpected"),
+        )
+        .expect("Unexpected");
+        assert!(schema.is_valid(&record));
+
+        record["Pāli1"] = Value::String("ābādha".to_string());
+        assert!(!schema.is_valid(&record));
+    }
+
+    #[test]
+    fn dpd_schema_test() {
+        insta::assert_snapshot!(DpdPaliWord::schema().expect("Unexpected"));
+    }
+}
@@ -0,0 +1,731 @@
+use std::collections::VecDeque;
+
+const HEADER: &[u8; 16] = b"We love Marisa.\0";
+
+const NUM_TRIES: u32 = 1;
+const NORMAL_CACHE: u32 = 0x0_0200;
+const TEXT_TAIL: u32 = 0x0_1000;
+const WEIGHT_ORDER: u32 = 0x2_0000;
+
+const INVALID_EXTRA: u32 = u32::MAX >> 8;
+
+const BITS_PER_UNIT: usize = 64;
+const BITS_PER_RANK: usize = 512;
+const BITS_PER_SELECT: usize = 512;
+
+#[derive(Default)]
+struct BitVector {
+    units: Vec<u64>,
+    size: usize,
+    num_1s: usize,
+}
+
+impl BitVector {
+    fn push(&mut self, bit: bool) {
+        if self.size == self.units.len() * BITS_PER_UNIT {
+            self.units.push(0);
+        }
+        if bit {
+            self.units[self.size / BITS_PER_UNIT] |= 1 << (self.size % BITS_PER_UNIT);
+            self.num_1s += 1;
+        }
+        self.size += 1;
+    }
+
+    fn get(&self, i: usize) -> bool {
+        self.units[i / BITS_PER_UNIT] & (1 << (i % BITS_PER_UNIT)) != 0
+    }
+
+    ///
+    /// The rank index has an absolute count of 1s for every 512 bits and 7 counts relative to it,
+    /// one for each of the following 64 bit units, packed into 7, 8, 8, 9, 9, 9 and 9 bits.
+    ///
+    fn rank_index(&self) -> Vec<(u32, u32, u32)> {
+        let num_ranks = self.size.div_ceil(BITS_PER_RANK) + 1;
+        let mut ranks = vec![(0u32, 0u32, 0u32); num_ranks];
+
+        let mut num_1s = 0;
+        for (i, unit) in self.units.iter().enumerate() {
+            let rank = &mut ranks[i / (BITS_PER_RANK / BITS_PER_UNIT)];
+            match i % (BITS_PER_RANK / BITS_PER_UNIT) {
+                0 => rank.0 = num_1s,
+                n => set_relative_rank(rank, n, num_1s - rank.0),
+            }
+            num_1s += unit.count_ones();
+        }
+
+        // NOTE: The relative counts past the end of the last block still hold the total.
+        if self.size > 0 {
+            let last = (self.size - 1) / BITS_PER_UNIT;
+            let rank = &mut ranks[last / (BITS_PER_RANK / BITS_PER_UNIT)];
+            for n in (last % (BITS_PER_RANK / BITS_PER_UNIT)) + 1..BITS_PER_RANK / BITS_PER_UNIT {
+                set_relative_rank(rank, n, num_1s - rank.0);
+            }
+        }
+
+        if let Some(rank) = ranks.last_mut() {
+            rank.0 = num_1s;
+        }
+
+        ranks
+    }
+
+    ///
+    /// The positions of every 512th bit of the value, ending with the size of the vector.
+    ///
+    fn select_index(&self, value: bool) -> Vec<u32> {
+        let mut positions: Vec<u32> = (0..self.size)
+            .filter(|&i| self.get(i) == value)
+            .step_by(BITS_PER_SELECT)
+            .map(|i| i as u32)
+            .collect();
+        positions.push(self.size as u32);
+
+        positions
+    }
+
+    fn write(&self, out: &mut Vec<u8>, enables_select0: bool, enables_select1: bool) {
+        write_vector(out, &self.units, |out, u| out.extend(&u.to_le_bytes()));
+        out.extend(&(self.size as u32).to_le_bytes());
+        out.extend(&(self.num_1s as u32).to_le_bytes());
+        write_vector(out, &self.rank_index(), |out, r| {
+            out.extend(&r.0.to_le_bytes());
+            out.extend(&r.1.to_le_bytes());
+            out.extend(&r.2.to_le_bytes());
+        });
+        for (value, enabled) in [(false, enables_select0), (true, enables_select1)].iter() {
+            let selects = if *enabled {
+                self.select_index(*value)
+            } else {
+                vec![]
+            };
+            write_vector(out, &selects, |out, s| out.extend(&s.to_le_bytes()));
+        }
+    }
+
+    ///
+    /// A vector that was never built has no rank index at all, e.g. the end flags of a TEXT tail.
+    ///
+    fn write_unbuilt(&self, out: &mut Vec<u8>) {
+        write_vector(out, &self.units, |out, u| out.extend(&u.to_le_bytes()));
+        out.extend(&(self.size as u32).to_le_bytes());
+        out.extend(&(self.num_1s as u32).to_le_bytes());
+        for _ in 0..3 {
+            write_vector(out, &[0u8; 0], |out, b| out.push(*b));
+        }
+    }
+}
+
+fn set_relative_rank(rank: &mut (u32, u32, u32), n: usize, value: u32) {
+    let (field, mask, shift) = match n {
+        1 => (&mut rank.1, 0x7F, 0),
+        2 => (&mut rank.1, 0xFF, 7),
+        3 => (&mut rank.1, 0xFF, 15),
+        4 => (&mut rank.1, 0x1FF, 23),
+        5 => (&mut rank.2, 0x1FF, 0),
+        6 => (&mut rank.2, 0x1FF, 9),
+        _ => (&mut rank.2, 0x1FF, 18),
+    };
+    *field = (*field & !(mask << shift)) | ((value & mask) << shift);
+}
+
+///
+/// Vectors are written as their size in bytes, the items and then padding up to 8 bytes.
+///
+fn write_vector<T>(out: &mut Vec<u8>, items: &[T], write_item: impl Fn(&mut Vec<u8>, &T)) {
+    let start = out.len() + 8;
+    out.extend(&0u64.to_le_bytes());
+    for item in items {
+        write_item(out, item);
+    }
+    let total_size = (out.len() - start) as u64;
+    out[start - 8..start].copy_from_slice(&total_size.to_le_bytes());
+    out.resize(out.len() + ((8 - total_size % 8) % 8) as usize, 0);
+}
+
+///
+/// Packs the values into as many bits as the largest of them needs.
+///
+fn write_flat_vector(out: &mut Vec<u8>, values: &[u32]) {
+    let max_value = values.iter().copied().max().unwrap_or(0);
+    let value_size = (32 - max_value.leading_zeros()) as usize;
+    let mask = if value_size == 0 {
+        0
+    } else {
+        u32::MAX >> (32 - value_size)
+    };
+
+    let num_units = if value_size == 0 {
+        usize::from(!values.is_empty())
+    } else {
+        (value_size * values.len()).div_ceil(BITS_PER_UNIT)
+    };
+    let mut units = vec![0u64; num_units];
+    for (i, value) in values.iter().enumerate() {
+        let pos = i * value_size;
+        let (unit, offset) = (pos / BITS_PER_UNIT, pos % BITS_PER_UNIT);
+        units[unit] |= u64::from(value & mask) << offset;
+        if offset + value_size > BITS_PER_UNIT {
+            units[unit + 1] |= u64::from(value & mask) >> (BITS_PER_UNIT - offset);
+        }
+    }
+
+    write_vector(out, &units, |out, u| out.extend(&u.to_le_bytes()));
+    out.extend(&(value_size as u32).to_le_bytes());
+    out.extend(&mask.to_le_bytes());
+    out.extend(&(values.len() as u64).to_le_bytes());
+}
+
+///
+/// The TEXT tail holds the NUL terminated labels, with labels that end another one sharing it.
+/// Returns the offset of each of the labels.
+///
+fn build_tail(labels: &[Vec<u8>]) -> (Vec<u8>, Vec<u32>) {
+    let reversed: Vec<Vec<u8>> = labels
+        .iter()
+        .map(|l| l.iter().rev().copied().collect())
+        .collect();
+    let mut order: Vec<usize> = (0..labels.len()).collect();
+    order.sort_by(|&a, &b| reversed[a].cmp(&reversed[b]));
+
+    let mut buf = Vec::new();
+    let mut offsets = vec![0u32; labels.len()];
+    let mut last: Option<usize> = None;
+    for &current in order.iter().rev() {
+        offsets[current] = match last {
+            Some(last) if reversed[last].starts_with(&reversed[current]) => {
+                offsets[last] + (labels[last].len() - labels[current].len()) as u32
+            }
+            _ => {
+                let offset = buf.len() as u32;
+                buf.extend(&labels[current]);
+                buf.push(0);
+                offset
+            }
+        };
+        last = Some(current);
+    }
+
+    (buf, offsets)
+}
+
+struct Cache {
+    parent: u32,
+    child: u32,
+    weight: f32,
+}
+
+fn cache_id(node_id: usize, label: u8, cache_mask: usize) -> usize {
+    (node_id ^ (node_id << 5) ^ label as usize) & cache_mask
+}
+
+///
+/// Writes the keys in the file format of marisa-trie 0.2 (https://github.com/s-yata/marisa-trie),
+/// which Kobo reads the words index with. Only what Kobo needs is supported: a single LOUDS trie
+/// whose multi byte labels go into a TEXT tail, with the default cache and weight node order.
+/// The keys are expected to be unique and not empty.
+///
+pub fn create_trie(keys: &[&str]) -> Vec<u8> {
+    let mut keys: Vec<&[u8]> = keys.iter().map(|k| k.as_bytes()).collect();
+    keys.sort_unstable();
+
+    let mut cache_size = 256;
+    while cache_size < keys.len() / NORMAL_CACHE as usize {
+        cache_size *= 2;
+    }
+    let cache_mask = cache_size - 1;
+    let mut cache: Vec<Cache> = (0..cache_size)
+        .map(|_| Cache {
+            parent: 0,
+            child: 0,
+            weight: f32::MIN_POSITIVE,
+        })
+        .collect();
+
+    let mut louds = BitVector::default();
+    let mut terminal_flags = BitVector::default();
+    let mut link_flags = BitVector::default();
+    let mut bases: Vec<u8> = vec![0];
+    let mut link_labels: Vec<Vec<u8>> = Vec::new();
+    let mut terminals: Vec<usize> = Vec::new();
+    let mut num_l1_nodes = 0;
+
+    louds.push(true);
+    louds.push(false);
+    link_flags.push(false);
+
+    // NOTE: Nodes are numbered breadth first, and so are created in the order of the queue.
+    let mut queue = VecDeque::new();
+    queue.push_back((0, keys.len(), 0));
+    while let Some((mut begin, end, key_pos)) = queue.pop_front() {
+        let node_id = link_flags.size - queue.len() - 1;
+
+        while begin < end && keys[begin].len() == key_pos {
+            terminals.push(node_id);
+            begin += 1;
+        }
+
+        if begin == end {
+            louds.push(false);
+            continue;
+        }
+
+        let mut ranges = Vec::new();
+        let mut range_begin = begin;
+        for i in begin + 1..end {
+            if keys[i - 1][key_pos] != keys[i][key_pos] {
+                ranges.push((range_begin, i));
+                range_begin = i;
+            }
+        }
+        ranges.push((range_begin, end));
+        ranges.sort_by_key(|(b, e)| std::cmp::Reverse(e - b));
+
+        if node_id == 0 {
+            num_l1_nodes = ranges.len();
+        }
+
+        for (begin, end) in ranges {
+            let mut next_key_pos = key_pos + 1;
+            while next_key_pos < keys[begin].len()
+                && (begin + 1..end).all(|j| keys[j - 1][next_key_pos] == keys[j][next_key_pos])
+            {
+                next_key_pos += 1;
+            }
+
+            let label = keys[begin][key_pos];
+            let weight = (end - begin) as f32;
+            let entry = &mut cache[cache_id(node_id, label, cache_mask)];
+            if weight > entry.weight {
+                entry.parent = node_id as u32;
+                entry.child = bases.len() as u32;
+                entry.weight = weight;
+            }
+
+            if next_key_pos == key_pos + 1 {
+                bases.push(label);
+                link_flags.push(false);
+            } else {
+                bases.push(0);
+                link_flags.push(true);
+                link_labels.push(keys[begin][key_pos..next_key_pos].to_vec());
+            }
+
+            queue.push_back((begin, end, next_key_pos));
+            louds.push(true);
+        }
+        louds.push(false);
+    }
+    louds.push(false);
+
+    // NOTE: The links point into the tail, with the low byte of the offset kept in the bases.
+    let (tail, offsets) = build_tail(&link_labels);
+    let mut link_ids = vec![0usize; bases.len()];
+    let mut extras = Vec::with_capacity(offsets.len());
+    for (node_id, link_id) in (0..bases.len())
+        .filter(|&n| link_flags.get(n))
+        .zip(0..offsets.len())
+    {
+        bases[node_id] = (offsets[link_id] % 256) as u8;
+        extras.push(offsets[link_id] / 256);
+        link_ids[node_id] = link_id;
+    }
+
+    terminals.sort_unstable();
+    let mut terminals = terminals.into_iter().peekable();
+    for node_id in 0..=bases.len() {
+        terminal_flags.push(terminals.next_if_eq(&node_id).is_some());
+    }
+
+    let mut out = HEADER.to_vec();
+    louds.write(&mut out, true, true);
+    terminal_flags.write(&mut out, false, true);
+    link_flags.write(&mut out, false, false);
+    write_vector(&mut out, &bases, |out, b| out.push(*b));
+    write_flat_vector(&mut out, &extras);
+    write_vector(&mut out, &tail, |out, b| out.push(*b));
+    BitVector::default().write_unbuilt(&mut out);
+    write_vector(&mut out, &cache, |out, c| {
+        let (parent, child, link) = if c.child == 0 {
+            (u32::MAX, u32::MAX, c.weight.to_bits())
+        } else {
+            let child = c.child as usize;
+            let extra = if link_flags.get(child) {
+                extras[link_ids[child]]
+            } else {
+                INVALID_EXTRA
+            };
+            (c.parent, c.child, u32::from(bases[child]) | (extra << 8))
+        };
+        out.extend(&parent.to_le_bytes());
+        out.extend(&child.to_le_bytes());
+        out.extend(&link.to_le_bytes());
+    });
+    out.extend(&(num_l1_nodes as u32).to_le_bytes());
+    out.extend(&(NUM_TRIES | TEXT_TAIL | WEIGHT_ORDER | NORMAL_CACHE).to_le_bytes());
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resolve_file_in_manifest_dir;
+    use itertools::Itertools;
+    use std::convert::TryInto;
+    use test_case::test_case;
+
+    /// The keys of the golden trie, one per line.
+    const GOLDEN_KEYS: &str = "src/kobo/output_generators/test_data/words.txt";
+
+    /// The golden trie, built with marisa-trie 0.2 by `marisa-build -n 1 -t -w -o words < words.txt`.
+    const GOLDEN_TRIE: &str = "src/kobo/output_generators/test_data/words";
+
+    struct Reader<'a> {
+        data: &'a [u8],
+        pos: usize,
+    }
+
+    impl<'a> Reader<'a> {
+        fn u32(&mut self) -> u32 {
+            self.pos += 4;
+            u32::from_le_bytes(
+                self.data[self.pos - 4..self.pos]
+                    .try_into()
+                    .expect("Unexpected"),
+            )
+        }
+
+        fn u64(&mut self) -> u64 {
+            self.pos += 8;
+            u64::from_le_bytes(
+                self.data[self.pos - 8..self.pos]
+                    .try_into()
+                    .expect("Unexpected"),
+            )
+        }
+
+        fn vector(&mut self) -> &'a [u8] {
+            let size = self.u64() as usize;
+            let items = &self.data[self.pos..self.pos + size];
+            self.pos += size + (8 - size % 8) % 8;
+            items
+        }
+
+        fn u32s(&mut self) -> Vec<u32> {
+            self.vector()
+                .chunks(4)
+                .map(|c| u32::from_le_bytes(c.try_into().expect("Unexpected")))
+                .collect()
+        }
+
+        fn bit_vector(&mut self) -> ReadBitVector {
+            let units = self
+                .vector()
+                .chunks(8)
+                .map(|c| u64::from_le_bytes(c.try_into().expect("Unexpected")))
+                .collect();
+            let size = self.u32() as usize;
+            let num_1s = self.u32() as usize;
+            let ranks = self.u32s();
+            let select0s = self.u32s();
+            let select1s = self.u32s();
+            ReadBitVector {
+                units,
+                size,
+                num_1s,
+                ranks,
+                select0s,
+                select1s,
+            }
+        }
+    }
+
+    struct ReadBitVector {
+        units: Vec<u64>,
+        size: usize,
+        num_1s: usize,
+        ranks: Vec<u32>,
+        select0s: Vec<u32>,
+        select1s: Vec<u32>,
+    }
+
+    impl ReadBitVector {
+        fn get(&self, i: usize) -> bool {
+            assert!(i < self.size);
+            self.units[i / 64] & (1 << (i % 64)) != 0
+        }
+
+        fn rank1(&self, i: usize) -> usize {
+            let (abs, lo, hi) = (
+                self.ranks[i / 512 * 3],
+                self.ranks[i / 512 * 3 + 1],
+                self.ranks[i / 512 * 3 + 2],
+            );
+            let rel = match (i / 64) % 8 {
+                0 => 0,
+                1 => lo & 0x7F,
+                2 => (lo >> 7) & 0xFF,
+                3 => (lo >> 15) & 0xFF,
+                4 => (lo >> 23) & 0x1FF,
+                5 => hi & 0x1FF,
+                6 => (hi >> 9) & 0x1FF,
+                _ => (hi >> 18) & 0x1FF,
+            };
+            let unit = self.units.get(i / 64).copied().unwrap_or(0) & ((1 << (i % 64)) - 1);
+            (abs + rel + unit.count_ones()) as usize
+        }
+
+        fn select0(&self, i: usize) -> usize {
+            let mut pos = self.select0s[i / 512] as usize;
+            let mut remaining = i % 512;
+            loop {
+                if !self.get(pos) {
+                    if remaining == 0 {
+                        return pos;
+                    }
+                    remaining -= 1;
+                }
+                pos += 1;
+            }
+        }
+    }
+
+    struct Trie {
+        louds: ReadBitVector,
+        terminal_flags: ReadBitVector,
+        link_flags: ReadBitVector,
+        bases: Vec<u8>,
+        extras: Vec<u32>,
+        tail: Vec<u8>,
+        cache: Vec<u32>,
+        num_l1_nodes: u32,
+        flags: u32,
+    }
+
+    fn read_trie(data: &[u8]) -> Trie {
+        assert_eq!(&data[..16], HEADER);
+        let mut r = Reader { data, pos: 16 };
+        let louds = r.bit_vector();
+        let terminal_flags = r.bit_vector();
+        let link_flags = r.bit_vector();
+        let bases = r.vector().to_vec();
+        let units: Vec<u64> = r
+            .vector()
+            .chunks(8)
+            .map(|c| u64::from_le_bytes(c.try_into().expect("Unexpected")))
+            .collect();
+        let value_size = r.u32() as usize;
+        let mask = u64::from(r.u32());
+        let size = r.u64() as usize;
+        let extras = (0..size)
+            .map(|i| {
+                let (unit, offset) = (i * value_size / 64, i * value_size % 64);
+                let mut value = units[unit] >> offset;
+                if offset + value_size > 64 {
+                    value |= units[unit + 1] << (64 - offset);
+                }
+                (value & mask) as u32
+            })
+            .collect();
+        let tail = r.vector().to_vec();
+        let end_flags = r.bit_vector();
+        assert_eq!(end_flags.size, 0);
+        let cache = r.u32s();
+        let num_l1_nodes = r.u32();
+        let flags = r.u32();
+        assert_eq!(r.pos, data.len());
+
+        Trie {
+            louds,
+            terminal_flags,
+            link_flags,
+            bases,
+            extras,
+            tail,
+            cache,
+            num_l1_nodes,
+            flags,
+        }
+    }
+
+    impl Trie {
+        fn label(&self, node_id: usize) -> Vec<u8> {
+            if self.link_flags.get(node_id) {
+                let link = usize::from(self.bases[node_id])
+                    | (self.extras[self.link_flags.rank1(node_id)] as usize) << 8;
+                self.tail[link..]
+                    .iter()
+                    .copied()
+                    .take_while(|&b| b != 0)
+                    .collect()
+            } else {
+                vec![self.bases[node_id]]
+            }
+        }
+
+        fn children(&self, node_id: usize) -> Vec<usize> {
+            let mut louds_pos = self.louds.select0(node_id) + 1;
+            let mut children = vec![];
+            while self.louds.get(louds_pos) {
+                children.push(louds_pos - node_id - 1);
+                louds_pos += 1;
+            }
+            children
+        }
+
+        fn lookup(&self, key: &str) -> bool {
+            let mut key = key.as_bytes();
+            let mut node_id = 0;
+            while !key.is_empty() {
+                match self
+                    .children(node_id)
+                    .into_iter()
+                    .find(|&c| key.starts_with(&self.label(c)))
+                {
+                    Some(child) => {
+                        key = &key[self.label(child).len()..];
+                        node_id = child;
+                    }
+                    None => return false,
+                }
+            }
+            self.terminal_flags.get(node_id)
+        }
+
+        fn keys(&self, node_id: usize, prefix: &[u8], keys: &mut Vec<String>) {
+            if self.terminal_flags.get(node_id) {
+                keys.push(String::from_utf8(prefix.to_vec()).expect("Unexpected"));
+            }
+            for child in self.children(node_id) {
+                let prefix = [prefix, &self.label(child)].concat();
+                self.keys(child, &prefix, keys);
+            }
+        }
+    }
+
+    fn check_trie(keys: &[&str]) -> Trie {
+        let trie = read_trie(&create_trie(keys));
+
+        let mut expected: Vec<String> = keys.iter().map(|k| k.to_string()).collect();
+        expected.sort();
+        let mut actual = vec![];
+        trie.keys(0, &[], &mut actual);
+        actual.sort();
+        assert_eq!(actual, expected);
+
+        for key in keys {
+            assert!(trie.lookup(key), "'{}' is missing", key);
+            assert!(!trie.lookup(&format!("{}\u{1}", key)));
+        }
+        assert_eq!(trie.louds.num_1s, trie.bases.len());
+        assert_eq!(trie.terminal_flags.num_1s, keys.len());
+        assert_eq!(trie.link_flags.num_1s, trie.extras.len());
+        assert_eq!(trie.louds.select1s.last(), Some(&(trie.louds.size as u32)));
+        assert_eq!(trie.terminal_flags.select0s.len(), 0);
+
+        trie
+    }
+
+    #[test]
+    fn create_trie_test() {
+        let trie = check_trie(&["a", "abala", "abalo", "abalaṃ", "bala", "balo", "kharo"]);
+
+        assert!(!trie.lookup("aba"));
+        assert!(!trie.lookup("abalā"));
+        assert!(!trie.lookup(""));
+        assert_eq!(trie.num_l1_nodes, 3);
+        assert_eq!(trie.flags, 0x2_1201);
+        assert_eq!(trie.cache.len(), 256 * 3);
+        assert_eq!(trie.tail, "ṃ\0kharo\0bal\0".as_bytes());
+    }
+
+    #[test]
+    fn create_trie_file_test() {
+        let trie = create_trie(&["ab", "abc", "b"]);
+
+        // NOTE: The cache is mostly empty, so only its size and the trailer are checked.
+        let cache_size = 256 * 12;
+        let (trie, rest) = trie.split_at(trie.len() - 8 - cache_size - 8);
+        assert_eq!(rest[..8], (cache_size as u64).to_le_bytes());
+        assert_eq!(rest[rest.len() - 8..], [2, 0, 0, 0, 0x01, 0x12, 0x02, 0]);
+        insta::assert_snapshot!(trie
+            .chunks(16)
+            .map(|c| c.iter().map(|b| format!("{:02x}", b)).join(" "))
+            .join("\n"));
+    }
+
+    #[test_case(0)]
+    #[test_case(1)]
+    #[test_case(511)]
+    #[test_case(512)]
+    #[test_case(513)]
+    #[test_case(5_000)]
+    fn rank_and_select_test(size: usize) {
+        let mut bv = BitVector::default();
+        for i in 0..size {
+            bv.push(i % 3 == 0 || i % 7 == 0);
+        }
+        let mut out = vec![];
+        bv.write(&mut out, true, true);
+        let read = Reader { data: &out, pos: 0 }.bit_vector();
+
+        let mut num_0s = 0;
+        for i in 0..size {
+            assert_eq!(read.rank1(i), i - num_0s);
+            if !read.get(i) {
+                assert_eq!(read.select0(num_0s), i);
+                num_0s += 1;
+            }
+        }
+        assert_eq!(read.rank1(size), bv.num_1s);
+    }
+
+    #[test]
+    fn build_tail_test() {
+        let labels: Vec<Vec<u8>> = ["bala", "la", "kharo", "aro", "la"]
+            .iter()
+            .map(|l| l.as_bytes().to_vec())
+            .collect();
+
+        let (tail, offsets) = build_tail(&labels);
+
+        assert_eq!(tail, b"kharo\0bala\0".to_vec());
+        assert_eq!(offsets, vec![6, 8, 0, 2, 8]);
+    }
+
+    #[test]
+    fn create_trie_large_test() {
+        let syllables = ["a", "bā", "ca", "dhi", "ṃ", "kho", "la"];
+        let make_key = |mut n: usize| {
+            let mut key = String::new();
+            while n > 0 {
+                key.push_str(syllables[n % syllables.len()]);
+                n /= syllables.len();
+            }
+            key
+        };
+        let keys: Vec<String> = (1..20_000usize)
+            .map(|n| format!("{}{}", make_key(n), make_key(n * 7_919)))
+            .unique()
+            .collect();
+        let keys: Vec<&str> = keys.iter().map(|k| k.as_str()).collect();
+
+        let trie = check_trie(&keys);
+
+        assert!(trie.louds.size > 4 * 512);
+        assert!(trie.extras.iter().any(|&e| e > 0));
+    }
+
+    #[test]
+    fn create_trie_matches_marisa_test() {
+        let keys =
+            std::fs::read_to_string(resolve_file_in_manifest_dir(GOLDEN_KEYS).expect("Unexpected"))
+                .expect("Unexpected");
+        let golden = std::fs::read(resolve_file_in_manifest_dir(GOLDEN_TRIE).expect("Unexpected"))
+            .expect("The golden trie is missing, see GOLDEN_TRIE for how to build it");
+
+        let keys: Vec<&str> = keys.lines().collect();
+        assert_eq!(create_trie(&keys), golden);
+    }
+}
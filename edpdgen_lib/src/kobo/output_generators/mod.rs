@@ -0,0 +1,338 @@
+use crate::stardict::{ArticleReader, IdxEntry};
+use crate::{escape_xml, DictionaryData, DictionaryFile, DictionaryFiles, DictionaryInfo};
+use chrono::{DateTime, Datelike, Timelike};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use itertools::Itertools;
+use pls_core_extras::logger::PlsLogger;
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::io::{Cursor, Write};
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+mod marisa;
+
+lazy_static! {
+    static ref ARTICLE_BODY: Regex =
+        Regex::new(r"(?s)<body>\n?(.*)</body>").expect("is valid regex");
+    static ref BWORD_LINK: Regex =
+        Regex::new(r#"(?s)<a [^>]*href="bword://[^"]*"[^>]*>(.*?)</a>"#).expect("is valid regex");
+}
+
+/// Kobo picks the dictionary up by its name, this one being Pāli to English.
+const DICTIONARY_FILE_NAME: &str = "kobo/dicthtml-pi-en.zip";
+
+/// The index of all the words, in marisa-trie format.
+const WORDS_FILE_NAME: &str = "words";
+
+/// The shard of the words that do not start with two letters.
+const NON_LETTER_PREFIX: &str = "11";
+
+struct KoboEntry {
+    word: String,
+    variants: Vec<String>,
+    html: String,
+}
+
+fn normalize_word(word: &str) -> String {
+    word.trim().to_lowercase()
+}
+
+///
+/// The shard a word is looked up in, i.e. its first 2 letters. Words of a single letter are
+/// padded with 'a', as Kobo does.
+///
+fn make_word_prefix(word: &str) -> String {
+    let word = normalize_word(word);
+    let mut prefix: Vec<char> = word.chars().take(2).collect();
+    if prefix.len() == 1 {
+        prefix.push('a');
+    }
+
+    if prefix.is_empty() || prefix.iter().any(|c| !c.is_alphabetic()) {
+        NON_LETTER_PREFIX.to_string()
+    } else {
+        prefix.into_iter().collect()
+    }
+}
+
+fn create_entries(
+    dict: &DictionaryData,
+    idx_entries: Vec<IdxEntry>,
+    logger: &dyn PlsLogger,
+) -> Result<Vec<KoboEntry>, String> {
+    logger.info(&format!("Creating {} kobo entries.", idx_entries.len()));
+
+    let mut reader = ArticleReader::new(dict)?;
+    let mut entries = Vec::with_capacity(idx_entries.len());
+    for idx_entry in idx_entries {
+        let article = reader.read(idx_entry.data_offset, idx_entry.data_size)?;
+        let body = ARTICLE_BODY
+            .captures(&article)
+            .map(|caps| caps[1].to_string())
+            .unwrap_or(article);
+
+        // NOTE: Kobo cannot follow links between entries, so only their text is kept.
+        let html = BWORD_LINK.replace_all(&body, "$1").trim().to_string();
+
+        let word = normalize_word(&idx_entry.word);
+        let variants = idx_entry
+            .synonym_words
+            .iter()
+            .map(|s| normalize_word(s))
+            .filter(|s| !s.is_empty() && s != &word)
+            .unique()
+            .collect();
+
+        entries.push(KoboEntry {
+            word,
+            variants,
+            html,
+        });
+    }
+
+    Ok(entries)
+}
+
+fn create_entry_html(entry: &KoboEntry) -> String {
+    let variants = if entry.variants.is_empty() {
+        String::new()
+    } else {
+        format!(
+            "<var>{}</var>",
+            entry
+                .variants
+                .iter()
+                .map(|v| format!("<variant name=\"{}\"/>", escape_xml(v)))
+                .join("")
+        )
+    };
+
+    format!(
+        "<w><a name=\"{}\" />{}{}</w>",
+        escape_xml(&entry.word),
+        variants,
+        entry.html
+    )
+}
+
+///
+/// Kobo looks up a word only in the shard of its own prefix, so entries whose variants have other
+/// prefixes go into those shards as well.
+///
+fn create_shards(entries: &[KoboEntry]) -> BTreeMap<String, String> {
+    let mut shards: BTreeMap<String, String> = BTreeMap::new();
+    for entry in entries {
+        let html = create_entry_html(entry);
+        let prefixes = std::iter::once(&entry.word)
+            .chain(entry.variants.iter())
+            .map(|w| make_word_prefix(w))
+            .unique();
+        for prefix in prefixes {
+            shards
+                .entry(prefix)
+                .or_insert_with(|| "<html>".to_string())
+                .push_str(&html);
+        }
+    }
+
+    for shard in shards.values_mut() {
+        shard.push_str("</html>");
+    }
+
+    shards
+}
+
+fn create_words(entries: &[KoboEntry]) -> Vec<u8> {
+    let words: Vec<&str> = entries
+        .iter()
+        .flat_map(|e| std::iter::once(&e.word).chain(e.variants.iter()))
+        .filter(|w| !w.is_empty())
+        .map(|w| w.as_str())
+        .unique()
+        .collect();
+
+    marisa::create_trie(&words)
+}
+
+fn gzip(data: &[u8]) -> Result<Vec<u8>, String> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data).map_err(|e| e.to_string())?;
+    encoder.finish().map_err(|e| e.to_string())
+}
+
+fn create_zip(
+    dict_info: &DictionaryInfo,
+    words: &[u8],
+    shards: &BTreeMap<String, String>,
+) -> Result<Vec<u8>, String> {
+    let mtime = DateTime::parse_from_rfc3339(dict_info.time_stamp).map_err(|e| e.to_string())?;
+    // NOTE: Zip cannot represent dates before 1980, those fall back to the zip epoch.
+    let mtime = zip::DateTime::from_date_and_time(
+        mtime.year() as u16,
+        mtime.month() as u8,
+        mtime.day() as u8,
+        mtime.hour() as u8,
+        mtime.minute() as u8,
+        mtime.second() as u8,
+    )
+    .unwrap_or_default();
+    let options = FileOptions::default()
+        .compression_method(CompressionMethod::Deflated)
+        .last_modified_time(mtime)
+        .unix_permissions(0o644);
+
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    zip.start_file(WORDS_FILE_NAME, options)
+        .map_err(|e| e.to_string())?;
+    zip.write_all(words).map_err(|e| e.to_string())?;
+
+    // NOTE: The shards are gzipped already.
+    let options = options.compression_method(CompressionMethod::Stored);
+    for (prefix, html) in shards {
+        zip.start_file(format!("{}.html", prefix), options)
+            .map_err(|e| e.to_string())?;
+        zip.write_all(&gzip(html.as_bytes())?)
+            .map_err(|e| e.to_string())?;
+    }
+
+    zip.finish()
+        .map(|c| c.into_inner())
+        .map_err(|e| e.to_string())
+}
+
+///
+/// The dictionary is the <short_name>.kobo/dicthtml-pi-en.zip to be copied to .kobo/dict on the
+/// device.
+///
+pub fn create_dictionary(
+    dict_info: &DictionaryInfo,
+    dict: &DictionaryData,
+    idx_entries: Vec<IdxEntry>,
+    logger: &dyn PlsLogger,
+) -> Result<DictionaryFiles, String> {
+    let entries = create_entries(dict, idx_entries, logger)?;
+
+    let shards = create_shards(&entries);
+    logger.info(&format!(
+        "Created {} kobo shards for {} entries.",
+        shards.len(),
+        entries.len()
+    ));
+    let words = create_words(&entries);
+
+    Ok(DictionaryFiles {
+        word_count: entries.len(),
+        files: vec![DictionaryFile {
+            extension: DICTIONARY_FILE_NAME.to_string(),
+            bom: vec![],
+            data: DictionaryData::InMemory(create_zip(dict_info, &words, &shards)?),
+            can_be_empty: false,
+        }],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{create_dict_info, TestLogger};
+    use crate::OutputFormat;
+    use flate2::read::GzDecoder;
+    use std::io::Read;
+    use test_case::test_case;
+
+    fn create_test_entries() -> (DictionaryData, Vec<IdxEntry>) {
+        let articles = [
+            (
+                "abala",
+                vec!["abalo", "abalaṃ", "abala"],
+                "<html><head><style>*{}</style></head><body>\n<a href=\"bword://√bal\">√bal</a> <a href=\"#abala_1\">1</a></body></html>",
+            ),
+            (
+                "Ābādha",
+                vec!["ābādhā", "bādhā"],
+                "<html><body>\nābādha &amp; bādha</body></html>",
+            ),
+            ("√bal", vec!["bal"], "<html><body>\n√bal</body></html>"),
+        ];
+        let mut dict = Vec::new();
+        let mut idx_entries = Vec::new();
+        for (word, synonyms, html) in articles.iter() {
+            idx_entries.push(IdxEntry {
+                word: word.to_string(),
+                data_offset: dict.len() as i32,
+                data_size: html.len() as i32,
+                synonym_words: synonyms.iter().map(|s| s.to_string()).collect(),
+            });
+            dict.extend_from_slice(html.as_bytes());
+        }
+
+        (DictionaryData::InMemory(dict), idx_entries)
+    }
+
+    #[test_case("abala", "ab")]
+    #[test_case(" Ābādha", "āb"; "capitalized and padded")]
+    #[test_case("a", "aa")]
+    #[test_case("a b", "11")]
+    #[test_case("√bal", "11")]
+    #[test_case("", "11")]
+    fn make_word_prefix_test(word: &str, expected: &str) {
+        assert_eq!(make_word_prefix(word), expected);
+    }
+
+    #[test]
+    fn create_shards_test() {
+        let (dict, idx_entries) = create_test_entries();
+        let entries = create_entries(&dict, idx_entries, &TestLogger::new()).expect("Unexpected");
+
+        let shards = create_shards(&entries);
+
+        insta::assert_snapshot!(shards
+            .iter()
+            .map(|(prefix, html)| format!("{}.html\n{}", prefix, html))
+            .join("\n\n"));
+    }
+
+    #[test]
+    fn create_dictionary_test() {
+        let (dict, idx_entries) = create_test_entries();
+
+        let files = create_dictionary(
+            &create_dict_info(&OutputFormat(&crate::kobo::KoboGenerator)),
+            &dict,
+            idx_entries,
+            &TestLogger::new(),
+        )
+        .expect("Unexpected");
+
+        assert_eq!(files.word_count, 3);
+        assert_eq!(files.files.len(), 1);
+        assert_eq!(files.files[0].extension, "kobo/dicthtml-pi-en.zip");
+        let data = match &files.files[0].data {
+            DictionaryData::InMemory(data) => data,
+            _ => panic!("Unexpected"),
+        };
+        let mut zip = zip::ZipArchive::new(Cursor::new(data)).expect("Unexpected");
+        let names: Vec<String> = (0..zip.len())
+            .map(|i| zip.by_index(i).expect("Unexpected").name().to_string())
+            .collect();
+        assert_eq!(
+            names,
+            vec!["words", "11.html", "ab.html", "ba.html", "bā.html", "āb.html"]
+        );
+
+        let mut words = vec![];
+        zip.by_name("words")
+            .expect("Unexpected")
+            .read_to_end(&mut words)
+            .expect("Unexpected");
+        assert!(words.starts_with(b"We love Marisa.\0"));
+
+        let mut html = String::new();
+        GzDecoder::new(zip.by_name("bā.html").expect("Unexpected"))
+            .read_to_string(&mut html)
+            .expect("Unexpected");
+        assert!(html.starts_with("<html><w><a name=\"ābādha\" />"));
+    }
+}
@@ -3,22 +3,52 @@ extern crate lazy_static;
 #[macro_use]
 extern crate serde_derive;
 
+use crate::input::dpd::DpdPaliWord;
+use crate::input::dps::DpsPaliWord;
 use crate::input::input_format::InputFormat;
 use crate::output::output_format::OutputFormat;
+use itertools::Itertools;
 use pls_core_extras::inflection_generator::{
     InflectionGenerator, NullInflectionGenerator, PlsInflectionGenerator,
 };
 use pls_core_extras::logger::PlsLogger;
+use regex::Regex;
+use std::collections::HashMap;
 use std::fs;
 use std::fs::File;
+use std::io;
 use std::io::Write;
 use std::path::{Path, PathBuf};
 
 mod ajdict;
+mod apple;
+mod changelog;
+mod deconstruction;
+mod derivations;
+mod dictd;
+mod dsl;
+mod frequency;
 mod glib;
+mod html;
+mod inflections;
 pub mod input;
+mod jsonl;
+mod kobo;
+mod mdict;
 pub mod output;
+mod package;
+mod render_cache;
+mod roots;
+mod slob;
+mod sqlite;
 mod stardict;
+mod suttas;
+mod tei;
+mod xdxf;
+
+lazy_static! {
+    static ref HTML_TAG: Regex = Regex::new(r"<[^>]*>").expect("Malformed regex string");
+}
 
 pub struct DictionaryInfo<'a> {
     pub name: &'a str,
@@ -38,61 +68,151 @@ pub struct DictionaryInfo<'a> {
     pub host_url: &'a str,
     pub host_version: &'a str,
     pub inflections_db_path: Option<&'a str>,
+    pub render_cache_path: Option<&'a str>,
+    pub version: &'a str,
     pub concise: bool,
+    pub package: bool,
+    pub collision_report: bool,
+    pub prefer_headwords: bool,
+    pub deconstruct_path: Option<&'a str>,
+    pub sutta_link_pattern: Option<&'a str>,
+    pub frequency_path: Option<&'a str>,
+    pub min_frequency: u64,
+    pub mdd: bool,
+    pub site_url: Option<&'a str>,
+}
+
+pub enum DictionaryData {
+    /// Data held entirely in memory.
+    InMemory(Vec<u8>),
+    /// Data already streamed to a temporary file, moved into place on write.
+    FileBacked { path: PathBuf, size: u64 },
+}
+
+impl DictionaryData {
+    pub fn len(&self) -> u64 {
+        match self {
+            DictionaryData::InMemory(data) => data.len() as u64,
+            DictionaryData::FileBacked { size, .. } => *size,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
 }
 
 pub struct DictionaryFile {
     pub extension: String,
     pub bom: Vec<u8>,
-    pub data: Vec<u8>,
+    pub data: DictionaryData,
     pub can_be_empty: bool,
 }
 
-pub trait DictionaryBuilder<'a> {
-    fn new(
-        dict_info: &'a DictionaryInfo,
-        input_data_path: &'a Path,
-        igen: &'a dyn InflectionGenerator,
-        logger: &'a dyn PlsLogger,
-    ) -> Self;
-    fn build_files(&self) -> Result<Vec<DictionaryFile>, String>;
+pub struct DictionaryFiles {
+    pub word_count: usize,
+    pub files: Vec<DictionaryFile>,
 }
 
 pub fn run(dict_info: &DictionaryInfo, logger: &dyn PlsLogger) -> Result<(), String> {
+    if !dict_info.output_format.supports(dict_info.input_format) {
+        let msg = format!(
+            "Dictionary format '{}' has not yet been implemented for '{}'.",
+            dict_info.output_format, dict_info.input_format
+        );
+        logger.error(&msg);
+        return Err(msg);
+    }
+
+    let input_data_path = Path::new(dict_info.input_data_path);
     let igen: Box<dyn InflectionGenerator> =
-        if let Some(inflections_db_path) = dict_info.inflections_db_path {
-            Box::new(PlsInflectionGenerator::new(
+        match (dict_info.inflections_db_path, dict_info.input_format) {
+            (Some(inflections_db_path), _) => Box::new(PlsInflectionGenerator::new(
                 "en",
                 env!("CARGO_PKG_VERSION"),
                 env!("CARGO_PKG_NAME"),
                 inflections_db_path,
                 logger,
-            )?)
-        } else {
-            Box::new(NullInflectionGenerator::new())
+            )?),
+            (None, InputFormat::Dpd) => Box::new(
+                inflections::BuiltinInflectionGenerator::from_csv(input_data_path, logger)?,
+            ),
+            (None, InputFormat::Dps) => Box::new(NullInflectionGenerator::new()),
         };
 
     igen.check_inflection_db(logger)?;
+    if dict_info.sutta_link_pattern.is_some() {
+        suttas::check_sutta_refs(input_data_path, logger)?;
+    }
 
-    let input_data_path = Path::new(dict_info.input_data_path);
-    let dict_files = match dict_info.output_format {
-        OutputFormat::StarDict => {
-            stardict::StarDict::new(dict_info, input_data_path, igen.as_ref(), logger)
-                .build_files()?
-        }
-        OutputFormat::AjDict => {
-            ajdict::AjDict::new(dict_info, input_data_path, igen.as_ref(), logger).build_files()?
-        }
-    };
+    let dict = dict_info.output_format.generator().build_files(
+        dict_info,
+        input_data_path,
+        igen.as_ref(),
+        logger,
+    )?;
 
-    validate_dictionary_files(&dict_files, logger)?;
+    validate_dictionary_files(&dict.files, logger)?;
 
     let base_path = create_base_path(
         input_data_path,
         dict_info.output_folder,
         dict_info.short_name,
     )?;
-    write_dictionary(&base_path, &dict_files, logger)
+    write_dictionary(&base_path, &dict.files, logger)?;
+
+    if dict_info.package {
+        package::create_packages(dict_info, &base_path, &dict, logger)?;
+    }
+
+    log_peak_memory_usage(logger);
+    Ok(())
+}
+
+pub fn diff(
+    old_data_path: &str,
+    new_data_path: &str,
+    input_format: &InputFormat,
+    output_folder: &str,
+    logger: &dyn PlsLogger,
+) -> Result<(), String> {
+    let old_data_path = Path::new(old_data_path);
+    let new_data_path = Path::new(new_data_path);
+    let changelog_files = match input_format {
+        InputFormat::Dpd => {
+            changelog::create_changelog_files::<DpdPaliWord>(old_data_path, new_data_path, logger)?
+        }
+        InputFormat::Dps => {
+            changelog::create_changelog_files::<DpsPaliWord>(old_data_path, new_data_path, logger)?
+        }
+    };
+
+    let base_path = create_base_path(new_data_path, output_folder, "changelog")?;
+    write_dictionary(&base_path, &changelog_files, logger)
+}
+
+pub fn serve_dict(
+    index_path: &str,
+    dict_path: &str,
+    port: u16,
+    logger: &dyn PlsLogger,
+) -> Result<(), String> {
+    let db = dictd::server::DictDatabase::load(Path::new(index_path), Path::new(dict_path))?;
+    dictd::server::serve(&db, &format!("127.0.0.1:{}", port), logger)
+}
+
+pub fn build_inflections_db(
+    input_data_path: &str,
+    patterns_path: Option<&str>,
+    inflections_db_path: &str,
+    logger: &dyn PlsLogger,
+) -> Result<(), String> {
+    inflections::db::build_inflections_db(
+        Path::new(input_data_path),
+        patterns_path.map(Path::new),
+        Path::new(inflections_db_path),
+        logger,
+    )
 }
 
 fn validate_dictionary_files(
@@ -110,6 +230,7 @@ fn validate_dictionary_files(
                 &dict_file.extension,
             );
             logger.info(&msg);
+            remove_file_backed_data(dict_files);
             return Err(msg);
         }
     }
@@ -117,7 +238,18 @@ fn validate_dictionary_files(
     Ok(())
 }
 
-fn create_base_path(
+///
+/// NOTE: The data streamed to temporary files is not going to be written, so it is removed.
+///
+fn remove_file_backed_data(dict_files: &[DictionaryFile]) {
+    for dict_file in dict_files {
+        if let DictionaryData::FileBacked { path, .. } = &dict_file.data {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+pub(crate) fn create_base_path(
     input_data_path: &Path,
     output_folder: &str,
     dict_short_name: &str,
@@ -136,6 +268,39 @@ fn create_base_path(
     Ok(base_path)
 }
 
+///
+/// The extension can also name a file in a folder, e.g. site/index.html for the html site.
+///
+pub(crate) fn make_file_path(base_path: &Path, extension: &str) -> PathBuf {
+    match extension.split_once('/') {
+        Some((folder, file_name)) => base_path.with_extension(folder).join(file_name),
+        None => base_path.with_extension(extension),
+    }
+}
+
+///
+/// Escapes text for xml or html element content and double quoted attribute values.
+///
+pub(crate) fn escape_xml(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+///
+/// Tera filter for attribute values and elements such as TEI orth, where no markup is allowed.
+///
+pub(crate) fn xml_filter(
+    value: &tera::Value,
+    _args: &HashMap<String, tera::Value>,
+) -> tera::Result<tera::Value> {
+    value
+        .as_str()
+        .map(|s| tera::Value::String(escape_xml(&HTML_TAG.replace_all(s, ""))))
+        .ok_or_else(|| tera::Error::msg("Filter 'xml' expects a string"))
+}
+
 pub fn resolve_file_in_manifest_dir(file_name: &str) -> Result<PathBuf, String> {
     let root = std::path::Path::new(env!("CARGO_MANIFEST_DIR"));
     let p1 = root.join(file_name);
@@ -149,19 +314,57 @@ pub fn resolve_file_in_manifest_dir(file_name: &str) -> Result<PathBuf, String>
     Ok(file_path)
 }
 
+///
+/// Folders are written afresh, so that pages of words no longer in the input do not linger.
+///
+fn clear_folders(base_path: &Path, dict_files: &[DictionaryFile]) -> Result<(), String> {
+    let folders: Vec<PathBuf> = dict_files
+        .iter()
+        .filter_map(|f| f.extension.split_once('/'))
+        .map(|(folder, _)| base_path.with_extension(folder))
+        .unique()
+        .collect();
+    for folder in folders {
+        if folder.exists() {
+            fs::remove_dir_all(&folder).map_err(|e| e.to_string())?;
+        }
+    }
+
+    Ok(())
+}
+
 fn write_dictionary(
     base_path: &Path,
     dict_files: &[DictionaryFile],
     logger: &dyn PlsLogger,
 ) -> Result<(), String> {
+    clear_folders(base_path, dict_files)?;
     for dict_file in dict_files {
-        let f_name = base_path.with_extension(&dict_file.extension);
+        let f_name = make_file_path(base_path, &dict_file.extension);
         logger.info(&format!("Writing {:?}.", &f_name));
-        let mut f = File::create(&f_name).map_err(|e| e.to_string())?;
-        if !&dict_file.bom.is_empty() {
-            f.write_all(&dict_file.bom).map_err(|e| e.to_string())?;
+        if let Some(parent) = f_name.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        match &dict_file.data {
+            DictionaryData::InMemory(data) => {
+                let mut f = File::create(&f_name).map_err(|e| e.to_string())?;
+                if !&dict_file.bom.is_empty() {
+                    f.write_all(&dict_file.bom).map_err(|e| e.to_string())?;
+                }
+                f.write_all(data).map_err(|e| e.to_string())?;
+            }
+            DictionaryData::FileBacked { path, .. } => {
+                if dict_file.bom.is_empty() {
+                    fs::rename(path, &f_name).map_err(|e| e.to_string())?;
+                } else {
+                    let mut f = File::create(&f_name).map_err(|e| e.to_string())?;
+                    f.write_all(&dict_file.bom).map_err(|e| e.to_string())?;
+                    let mut src = File::open(path).map_err(|e| e.to_string())?;
+                    io::copy(&mut src, &mut f).map_err(|e| e.to_string())?;
+                    fs::remove_file(path).map_err(|e| e.to_string())?;
+                }
+            }
         }
-        f.write_all(&dict_file.data).map_err(|e| e.to_string())?;
         logger.info(&format!(
             "... done ({:.2} MB)...",
             dict_file.data.len() as f32 / 1024.0 / 1024.0
@@ -171,10 +374,32 @@ fn write_dictionary(
     Ok(())
 }
 
+///
+/// NOTE: Peak resident set size is only available from procfs, i.e. on Linux.
+///
+fn peak_memory_usage_kb() -> Option<u64> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+
+    status
+        .lines()
+        .find(|l| l.starts_with("VmHWM:"))
+        .and_then(|l| l.split_whitespace().nth(1))
+        .and_then(|kb| kb.parse::<u64>().ok())
+}
+
+fn log_peak_memory_usage(logger: &dyn PlsLogger) {
+    match peak_memory_usage_kb() {
+        Some(kb) => logger.info(&format!("Peak memory usage: {:.2} MB.", kb as f32 / 1024.0)),
+        None => logger.info("Peak memory usage: not available on this platform."),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use pls_core_extras::logger::PlsLogger;
+    use std::str::FromStr;
+    use test_case::test_case;
 
     pub struct TestLogger {}
 
@@ -211,4 +436,253 @@ mod tests {
             vec![format!("{}_1", pali1), format!("{}_2", pali1)]
         }
     }
+
+    ///
+    /// The dictionary info the tests start from, overriding the fields they depend on.
+    ///
+    pub fn create_dict_info(output_format: &OutputFormat) -> DictionaryInfo<'_> {
+        DictionaryInfo {
+            name: "Digital Pāli Tools Dictionary (DPD)",
+            input_data_path: "",
+            input_format: &InputFormat::Dpd,
+            output_format,
+            output_folder: "???",
+            short_name: "dpd",
+            author: "Digital Pāli Tools <digitalpalitools@gmail.com>",
+            description: "The next generation comprehensive digital Pāli dictionary.",
+            headings_color: "#7986cb",
+            links_color: "#ff4081",
+            time_stamp: "2021-01-02T03:04:05Z",
+            icon: vec![],
+            icon_path: None,
+            feedback_form_url: "http://feedback.form/???",
+            host_url: "this is the host",
+            host_version: "host version",
+            inflections_db_path: None,
+            render_cache_path: None,
+            version: "1.0.0",
+            concise: false,
+            package: false,
+            collision_report: false,
+            prefer_headwords: false,
+            deconstruct_path: None,
+            sutta_link_pattern: None,
+            frequency_path: None,
+            min_frequency: 0,
+            mdd: false,
+            site_url: None,
+        }
+    }
+
+    ///
+    /// Validates the document with xmllint against the schema in the given file, e.g.
+    /// "--relaxng" for a RELAX NG schema or "--dtdvalid" for a DTD.
+    ///
+    pub fn validate_with_xmllint(schema_option: &str, schema_file: &str, xml: &str) {
+        let schema = resolve_file_in_manifest_dir(schema_file).expect("Unexpected");
+        assert!(schema.exists(), "The schema {:?} is missing.", schema);
+        let dir = tempfile::tempdir().expect("Unexpected");
+        let xml_path = dir.path().join("dictionary.xml");
+        fs::write(&xml_path, xml).expect("Unexpected");
+
+        let output = std::process::Command::new("xmllint")
+            .arg("--noout")
+            .arg(schema_option)
+            .arg(&schema)
+            .arg(&xml_path)
+            .output()
+            .expect("xmllint is needed to validate the document");
+
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    #[test]
+    fn write_dictionary_moves_file_backed_data_test() {
+        let dir = tempfile::tempdir().expect("Unexpected");
+        let part_path = dir.path().join("test.dict.part");
+        fs::write(&part_path, b"abc").expect("Unexpected");
+        let dict_files = vec![
+            DictionaryFile {
+                extension: "dict".to_string(),
+                bom: vec![],
+                data: DictionaryData::FileBacked {
+                    path: part_path.clone(),
+                    size: 3,
+                },
+                can_be_empty: false,
+            },
+            DictionaryFile {
+                extension: "txt".to_string(),
+                bom: vec![0xEF, 0xBB, 0xBF],
+                data: DictionaryData::InMemory(b"d".to_vec()),
+                can_be_empty: false,
+            },
+        ];
+
+        write_dictionary(&dir.path().join("test"), &dict_files, &TestLogger::new())
+            .expect("Unexpected");
+
+        assert!(!part_path.exists());
+        assert_eq!(
+            fs::read(dir.path().join("test.dict")).expect("Unexpected"),
+            b"abc"
+        );
+        assert_eq!(
+            fs::read(dir.path().join("test.txt")).expect("Unexpected"),
+            vec![0xEF, 0xBB, 0xBF, b'd']
+        );
+    }
+
+    #[test]
+    fn write_dictionary_clears_folders_test() {
+        let dir = tempfile::tempdir().expect("Unexpected");
+        let stale_path = dir.path().join("test.site/words/stale.html");
+        fs::create_dir_all(stale_path.parent().expect("Unexpected")).expect("Unexpected");
+        fs::write(&stale_path, b"abc").expect("Unexpected");
+        let dict_files = vec![DictionaryFile {
+            extension: "site/index.html".to_string(),
+            bom: vec![],
+            data: DictionaryData::InMemory(b"d".to_vec()),
+            can_be_empty: false,
+        }];
+
+        write_dictionary(&dir.path().join("test"), &dict_files, &TestLogger::new())
+            .expect("Unexpected");
+
+        assert!(!stale_path.exists());
+        assert_eq!(
+            fs::read(dir.path().join("test.site/index.html")).expect("Unexpected"),
+            b"d"
+        );
+    }
+
+    #[test]
+    fn validate_dictionary_files_removes_file_backed_data_test() {
+        let dir = tempfile::tempdir().expect("Unexpected");
+        let part_path = dir.path().join("test.dict.part");
+        fs::write(&part_path, b"abc").expect("Unexpected");
+        let dict_files = vec![
+            DictionaryFile {
+                extension: "dict".to_string(),
+                bom: vec![],
+                data: DictionaryData::FileBacked {
+                    path: part_path.clone(),
+                    size: 3,
+                },
+                can_be_empty: false,
+            },
+            DictionaryFile {
+                extension: "idx".to_string(),
+                bom: vec![],
+                data: DictionaryData::InMemory(vec![]),
+                can_be_empty: false,
+            },
+        ];
+
+        let ret = validate_dictionary_files(&dict_files, &TestLogger::new());
+
+        assert!(ret.is_err());
+        assert!(!part_path.exists());
+    }
+
+    #[test_case("abala", "abala")]
+    #[test_case("<b>a</b> & \"b\"", "a &amp; &quot;b&quot;"; "markup is removed and escaped")]
+    fn xml_filter_test(value: &str, expected: &str) {
+        let filtered = xml_filter(&tera::Value::from(value), &HashMap::new()).expect("Unexpected");
+
+        assert_eq!(filtered, tera::Value::from(expected));
+    }
+
+    #[test_case("dict", "dicts/dpd.dict")]
+    #[test_case("tei.xml", "dicts/dpd.tei.xml")]
+    #[test_case("site/index.html", "dicts/dpd.site/index.html")]
+    #[test_case("site/words/abala.html", "dicts/dpd.site/words/abala.html")]
+    fn make_file_path_test(extension: &str, expected: &str) {
+        assert_eq!(
+            make_file_path(Path::new("dicts/dpd"), extension),
+            PathBuf::from(expected)
+        );
+    }
+
+    fn read_files(folder: &Path, files: &mut Vec<(PathBuf, Vec<u8>)>) {
+        for e in fs::read_dir(folder).expect("Unexpected") {
+            let path = e.expect("Unexpected").path();
+            if path.is_dir() {
+                read_files(&path, files);
+            } else {
+                files.push((path.clone(), fs::read(&path).expect("Unexpected")));
+            }
+        }
+    }
+
+    fn build_dictionary(
+        output_format: &OutputFormat,
+        output_folder: &Path,
+        configure: impl Fn(&mut DictionaryInfo),
+    ) -> Vec<(PathBuf, Vec<u8>)> {
+        let input_data_path =
+            resolve_file_in_manifest_dir("Pali_English_Dictionary_10_rows-full.csv")
+                .expect("must exist!");
+        let mut dict_info = DictionaryInfo {
+            input_data_path: input_data_path.to_str().expect("Unexpected"),
+            output_folder: output_folder.to_str().expect("Unexpected"),
+            time_stamp: "2021-01-01T00:00:00Z",
+            icon: vec![1, 2, 3],
+            ..create_dict_info(output_format)
+        };
+        configure(&mut dict_info);
+
+        run(&dict_info, &TestLogger::new()).expect("Unexpected");
+
+        let mut files = Vec::new();
+        read_files(output_folder, &mut files);
+        let mut files: Vec<(PathBuf, Vec<u8>)> = files
+            .into_iter()
+            .map(|(path, data)| {
+                let path = path.strip_prefix(output_folder).expect("Unexpected");
+                (path.to_path_buf(), data)
+            })
+            .collect();
+        files.sort();
+
+        files
+    }
+
+    #[test]
+    fn builds_are_reproducible_test() {
+        for output_format in OutputFormat::all() {
+            let test_dir = tempfile::tempdir().expect("Unexpected");
+
+            let build1 = build_dictionary(&output_format, &test_dir.path().join("1"), |_| {});
+            let build2 = build_dictionary(&output_format, &test_dir.path().join("2"), |_| {});
+
+            assert!(!build1.is_empty(), "{}", output_format);
+            assert!(build1 == build2, "{} is not reproducible", output_format);
+        }
+    }
+
+    #[test_case("stardict")]
+    #[test_case("ajdict")]
+    fn builds_with_options_are_reproducible_test(output_format: &str) {
+        let output_format = OutputFormat::from_str(output_format).expect("Unexpected");
+        let test_dir = tempfile::tempdir().expect("Unexpected");
+        let configure = |dict_info: &mut DictionaryInfo| {
+            dict_info.package = true;
+            dict_info.collision_report = true;
+            dict_info.prefer_headwords = true;
+            dict_info.sutta_link_pattern = Some("https://suttacentral.net/{id}");
+        };
+
+        let build1 = build_dictionary(&output_format, &test_dir.path().join("1"), configure);
+        let build2 = build_dictionary(&output_format, &test_dir.path().join("2"), configure);
+
+        assert!(build1
+            .iter()
+            .any(|(path, _)| path.to_string_lossy().ends_with(".zip")));
+        assert_eq!(build1, build2);
+    }
 }
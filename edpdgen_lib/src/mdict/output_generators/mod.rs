@@ -0,0 +1,600 @@
+use crate::stardict::{create_stylesheet, ArticleReader, IdxEntry};
+use crate::{escape_xml, DictionaryData, DictionaryFile, DictionaryFiles, DictionaryInfo};
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use pls_core_extras::logger::PlsLogger;
+use regex::Regex;
+use std::io::Write;
+
+lazy_static! {
+    static ref INLINE_STYLE: Regex = Regex::new(r"(?s)<style>.*?</style>").expect("is valid regex");
+}
+
+/// Decompressed size above which a key or record block is closed.
+const BLOCK_SIZE: usize = 64 * 1024;
+
+/// Marks a zlib compressed block.
+const ZLIB_COMPRESSION: [u8; 4] = [0x02, 0x00, 0x00, 0x00];
+
+/// Characters ignored when ordering keys, as readers do for StripKey="Yes".
+const STRIP_CHARS: &str = " _=,.;:!?@%&#~`()[]<>{}/\\$+-*^'\"\t|";
+
+#[derive(Clone, Copy)]
+enum KeyEncoding {
+    Utf8,
+    Utf16,
+}
+
+impl KeyEncoding {
+    fn encode(&self, s: &str) -> Vec<u8> {
+        match self {
+            KeyEncoding::Utf8 => s.as_bytes().to_vec(),
+            KeyEncoding::Utf16 => s
+                .encode_utf16()
+                .flat_map(|c| c.to_le_bytes().to_vec())
+                .collect(),
+        }
+    }
+
+    fn unit_size(&self) -> usize {
+        match self {
+            KeyEncoding::Utf8 => 1,
+            KeyEncoding::Utf16 => 2,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            KeyEncoding::Utf8 => "UTF-8",
+            KeyEncoding::Utf16 => "UTF-16",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum MdxRecord {
+    Article { data_offset: i32, data_size: i32 },
+    Link(String),
+}
+
+struct KeyBlock {
+    entry_count: usize,
+    first_key: String,
+    last_key: String,
+    data: Vec<u8>,
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (a, b) = data.iter().fold((1u32, 0u32), |(a, b), &d| {
+        let a = (a + d as u32) % 65521;
+        (a, (b + a) % 65521)
+    });
+
+    (b << 16) | a
+}
+
+fn compress_block(data: &[u8]) -> Result<Vec<u8>, String> {
+    let mut block = ZLIB_COMPRESSION.to_vec();
+    block.extend_from_slice(&adler32(data).to_be_bytes());
+
+    let mut encoder = ZlibEncoder::new(block, Compression::default());
+    encoder.write_all(data).map_err(|e| e.to_string())?;
+    encoder.finish().map_err(|e| e.to_string())
+}
+
+fn make_mdict_sort_key(key: &str) -> String {
+    key.to_lowercase()
+        .chars()
+        .filter(|c| !STRIP_CHARS.contains(*c))
+        .collect()
+}
+
+fn create_header(tag: &str, attributes: &[(&str, &str)]) -> Vec<u8> {
+    let attributes: Vec<String> = attributes
+        .iter()
+        .map(|(n, v)| format!("{}=\"{}\"", n, escape_xml(v)))
+        .collect();
+    let header = KeyEncoding::Utf16.encode(&format!("<{} {}/>\r\n\0", tag, attributes.join(" ")));
+
+    let mut data = Vec::with_capacity(header.len() + 8);
+    data.extend_from_slice(&(header.len() as u32).to_be_bytes());
+    data.extend_from_slice(&header);
+    data.extend_from_slice(&adler32(&header).to_le_bytes());
+    data
+}
+
+///
+/// Writes the keys, which must already be in reader order, and their records. The record
+/// of a key runs up to the offset of the next key, so records are kept whole in a block.
+///
+/// See https://github.com/zhansliu/writemdict/blob/master/fileformat.md
+///
+fn create_mdict_data<R>(
+    header: Vec<u8>,
+    encoding: KeyEncoding,
+    entries: &[(String, R)],
+    mut read_record: impl FnMut(&R) -> Result<Vec<u8>, String>,
+) -> Result<Vec<u8>, String> {
+    let mut record_offsets: Vec<u64> = Vec::with_capacity(entries.len());
+    let mut record_block_sizes: Vec<(u64, u64)> = Vec::new();
+    let mut record_blocks: Vec<u8> = Vec::new();
+    let mut block: Vec<u8> = Vec::with_capacity(BLOCK_SIZE);
+    let mut offset = 0u64;
+    for (i, (_, r)) in entries.iter().enumerate() {
+        let record = read_record(r)?;
+        record_offsets.push(offset);
+        offset += record.len() as u64;
+        block.extend_from_slice(&record);
+
+        if block.len() >= BLOCK_SIZE || i + 1 == entries.len() {
+            let compressed = compress_block(&block)?;
+            record_block_sizes.push((compressed.len() as u64, block.len() as u64));
+            record_blocks.extend_from_slice(&compressed);
+            block.clear();
+        }
+    }
+
+    let terminator = vec![0u8; encoding.unit_size()];
+    let mut key_blocks: Vec<KeyBlock> = Vec::new();
+    for ((key, _), offset) in entries.iter().zip(record_offsets) {
+        let is_full = match key_blocks.last() {
+            Some(kb) => kb.data.len() >= BLOCK_SIZE,
+            None => true,
+        };
+        if is_full {
+            key_blocks.push(KeyBlock {
+                entry_count: 0,
+                first_key: key.clone(),
+                last_key: key.clone(),
+                data: Vec::with_capacity(BLOCK_SIZE),
+            });
+        }
+
+        let kb = key_blocks.last_mut().expect("A block was just added");
+        kb.entry_count += 1;
+        kb.last_key = key.clone();
+        kb.data.extend_from_slice(&offset.to_be_bytes());
+        kb.data.extend_from_slice(&encoding.encode(key));
+        kb.data.extend_from_slice(&terminator);
+    }
+
+    let mut key_block_info: Vec<u8> = Vec::new();
+    let mut compressed_key_blocks: Vec<u8> = Vec::new();
+    for kb in &key_blocks {
+        let compressed = compress_block(&kb.data)?;
+        key_block_info.extend_from_slice(&(kb.entry_count as u64).to_be_bytes());
+        for key in &[&kb.first_key, &kb.last_key] {
+            let key = encoding.encode(key);
+            key_block_info
+                .extend_from_slice(&((key.len() / encoding.unit_size()) as u16).to_be_bytes());
+            key_block_info.extend_from_slice(&key);
+            key_block_info.extend_from_slice(&terminator);
+        }
+        key_block_info.extend_from_slice(&(compressed.len() as u64).to_be_bytes());
+        key_block_info.extend_from_slice(&(kb.data.len() as u64).to_be_bytes());
+        compressed_key_blocks.extend_from_slice(&compressed);
+    }
+    let compressed_key_block_info = compress_block(&key_block_info)?;
+
+    let mut data = header;
+
+    let mut key_section: Vec<u8> = Vec::with_capacity(40);
+    for n in &[
+        key_blocks.len(),
+        entries.len(),
+        key_block_info.len(),
+        compressed_key_block_info.len(),
+        compressed_key_blocks.len(),
+    ] {
+        key_section.extend_from_slice(&(*n as u64).to_be_bytes());
+    }
+    data.extend_from_slice(&key_section);
+    data.extend_from_slice(&adler32(&key_section).to_be_bytes());
+    data.extend_from_slice(&compressed_key_block_info);
+    data.extend_from_slice(&compressed_key_blocks);
+
+    for n in &[
+        record_block_sizes.len(),
+        entries.len(),
+        record_block_sizes.len() * 16,
+        record_blocks.len(),
+    ] {
+        data.extend_from_slice(&(*n as u64).to_be_bytes());
+    }
+    for (compressed_size, size) in &record_block_sizes {
+        data.extend_from_slice(&compressed_size.to_be_bytes());
+        data.extend_from_slice(&size.to_be_bytes());
+    }
+    data.extend_from_slice(&record_blocks);
+
+    Ok(data)
+}
+
+///
+/// Each article under its headword, and a @@@LINK= redirect to it under each of its synonyms,
+/// i.e. the inflections. Headword articles come ahead of redirects with the same key.
+///
+fn create_mdx_entries(
+    idx_entries: Vec<IdxEntry>,
+    logger: &dyn PlsLogger,
+) -> Vec<(String, MdxRecord)> {
+    let mut entries: Vec<(String, MdxRecord)> = Vec::new();
+    for e in idx_entries {
+        for sw in e.synonym_words {
+            if sw != e.word {
+                entries.push((sw, MdxRecord::Link(e.word.clone())));
+            }
+        }
+        entries.push((
+            e.word,
+            MdxRecord::Article {
+                data_offset: e.data_offset,
+                data_size: e.data_size,
+            },
+        ));
+    }
+
+    logger.info(&format!("Sorting {} mdx entries.", entries.len()));
+    entries.sort_by_cached_key(|e| (make_mdict_sort_key(&e.0), e.clone()));
+    entries.dedup();
+
+    entries
+}
+
+fn create_mdx(
+    dict_info: &DictionaryInfo,
+    dict: &DictionaryData,
+    idx_entries: Vec<IdxEntry>,
+    logger: &dyn PlsLogger,
+) -> Result<Vec<u8>, String> {
+    let entries = create_mdx_entries(idx_entries, logger);
+    logger.info(&format!("Creating mdx with {} entries.", entries.len()));
+
+    let header = create_header(
+        "Dictionary",
+        &[
+            ("GeneratedByEngineVersion", "2.0"),
+            ("RequiredEngineVersion", "2.0"),
+            ("Encrypted", "0"),
+            ("Encoding", KeyEncoding::Utf8.name()),
+            ("Format", "Html"),
+            ("StripKey", "Yes"),
+            (
+                "CreationDate",
+                dict_info
+                    .time_stamp
+                    .get(..10)
+                    .unwrap_or(dict_info.time_stamp),
+            ),
+            ("Compact", "No"),
+            ("Compat", "No"),
+            ("KeyCaseSensitive", "No"),
+            ("Description", dict_info.description),
+            ("Title", dict_info.name),
+            ("DataSourceFormat", "106"),
+            ("StyleSheet", ""),
+            ("Left2Right", "Yes"),
+            ("RegisterBy", ""),
+        ],
+    );
+
+    // NOTE: StarDict links between articles with bword://, MDict with entry://.
+    // With an mdd the articles link to its stylesheet instead of embedding it.
+    let stylesheet_link = format!(
+        "<link rel=\"stylesheet\" type=\"text/css\" href=\"{}.css\"/>",
+        dict_info.short_name
+    );
+    let mut reader = ArticleReader::new(dict)?;
+    let mdx = create_mdict_data(header, KeyEncoding::Utf8, &entries, |r| {
+        let text = match r {
+            MdxRecord::Article {
+                data_offset,
+                data_size,
+            } => {
+                let article = reader
+                    .read(*data_offset, *data_size)?
+                    .replace("href=\"bword://", "href=\"entry://");
+                if dict_info.mdd {
+                    INLINE_STYLE
+                        .replace(&article, stylesheet_link.as_str())
+                        .into_owned()
+                } else {
+                    article
+                }
+            }
+            MdxRecord::Link(word) => format!("@@@LINK={}\r\n", word),
+        };
+
+        let mut record = text.into_bytes();
+        record.push(0u8);
+        Ok(record)
+    })?;
+
+    logger.info(&format!("... done creating mdx of {} bytes.", mdx.len()));
+    Ok(mdx)
+}
+
+///
+/// The resources shared by the articles, i.e. the stylesheet they link to and the icon.
+///
+fn create_mdd(dict_info: &DictionaryInfo, logger: &dyn PlsLogger) -> Result<Vec<u8>, String> {
+    let mut entries: Vec<(String, Vec<u8>)> = vec![(
+        format!("\\{}.css", dict_info.short_name),
+        create_stylesheet(dict_info)?.into_bytes(),
+    )];
+    if !dict_info.icon.is_empty() {
+        entries.push((
+            format!("\\{}.png", dict_info.short_name),
+            dict_info.icon.clone(),
+        ));
+    }
+    entries.sort_by_cached_key(|e| (make_mdict_sort_key(&e.0), e.0.clone()));
+    logger.info(&format!("Creating mdd with {} resources.", entries.len()));
+
+    let header = create_header(
+        "Library_Data",
+        &[
+            ("GeneratedByEngineVersion", "2.0"),
+            ("RequiredEngineVersion", "2.0"),
+            ("Encrypted", "0"),
+            ("Encoding", KeyEncoding::Utf16.name()),
+            ("Format", ""),
+            (
+                "CreationDate",
+                dict_info
+                    .time_stamp
+                    .get(..10)
+                    .unwrap_or(dict_info.time_stamp),
+            ),
+            ("Compact", "No"),
+            ("Compat", "No"),
+            ("KeyCaseSensitive", "No"),
+            ("Description", ""),
+            ("Title", ""),
+            ("DataSourceFormat", "106"),
+            ("StyleSheet", ""),
+            ("RegisterBy", ""),
+        ],
+    );
+
+    create_mdict_data(header, KeyEncoding::Utf16, &entries, |r| Ok(r.clone()))
+}
+
+///
+/// See https://github.com/zhansliu/writemdict/blob/master/fileformat.md
+///
+pub fn create_dictionary(
+    dict_info: &DictionaryInfo,
+    dict: &DictionaryData,
+    idx_entries: Vec<IdxEntry>,
+    logger: &dyn PlsLogger,
+) -> Result<DictionaryFiles, String> {
+    let word_count = idx_entries.len();
+    let mdx = create_mdx(dict_info, dict, idx_entries, logger)?;
+
+    let mut png = Vec::new();
+    png.extend_from_slice(&dict_info.icon);
+
+    let mut files = vec![
+        DictionaryFile {
+            extension: "mdx".to_string(),
+            bom: vec![],
+            data: DictionaryData::InMemory(mdx),
+            can_be_empty: false,
+        },
+        DictionaryFile {
+            extension: "png".to_string(),
+            bom: vec![],
+            data: DictionaryData::InMemory(png),
+            can_be_empty: false,
+        },
+    ];
+    if dict_info.mdd {
+        files.push(DictionaryFile {
+            extension: "mdd".to_string(),
+            bom: vec![],
+            data: DictionaryData::InMemory(create_mdd(dict_info, logger)?),
+            can_be_empty: false,
+        });
+    }
+
+    Ok(DictionaryFiles { word_count, files })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::TestLogger;
+    use crate::OutputFormat;
+    use flate2::read::ZlibDecoder;
+    use std::convert::TryInto;
+    use std::io::Read;
+    use test_case::test_case;
+
+    fn create_dict_info<'a>() -> DictionaryInfo<'a> {
+        DictionaryInfo {
+            mdd: true,
+            ..crate::tests::create_dict_info(&OutputFormat(&crate::mdict::MdictGenerator))
+        }
+    }
+
+    fn read_u64(data: &[u8], pos: &mut usize) -> u64 {
+        let n = u64::from_be_bytes(data[*pos..*pos + 8].try_into().expect("Unexpected"));
+        *pos += 8;
+        n
+    }
+
+    fn decompress_block(data: &[u8]) -> Vec<u8> {
+        assert_eq!(data[..4], ZLIB_COMPRESSION);
+        let mut block = Vec::new();
+        ZlibDecoder::new(&data[8..])
+            .read_to_end(&mut block)
+            .expect("Unexpected");
+        assert_eq!(data[4..8], adler32(&block).to_be_bytes());
+
+        block
+    }
+
+    ///
+    /// Reads the keys and records back as a reader would, assuming a single key block.
+    ///
+    fn read_mdict_data(data: &[u8], unit_size: usize) -> Vec<(Vec<u8>, Vec<u8>)> {
+        let header_size = u32::from_be_bytes(data[..4].try_into().expect("Unexpected")) as usize;
+        assert_eq!(
+            data[4 + header_size..8 + header_size],
+            adler32(&data[4..4 + header_size]).to_le_bytes()
+        );
+
+        let mut pos = 8 + header_size;
+        let key_section = &data[pos..pos + 40];
+        let key_block_count = read_u64(data, &mut pos);
+        let entry_count = read_u64(data, &mut pos) as usize;
+        let _ = read_u64(data, &mut pos);
+        let key_block_info_size = read_u64(data, &mut pos) as usize;
+        let key_blocks_size = read_u64(data, &mut pos) as usize;
+        assert_eq!(data[pos..pos + 4], adler32(key_section).to_be_bytes());
+        pos += 4;
+        assert_eq!(key_block_count, 1);
+
+        pos += key_block_info_size;
+        let key_block = decompress_block(&data[pos..pos + key_blocks_size]);
+        pos += key_blocks_size;
+
+        let mut keys: Vec<(u64, Vec<u8>)> = Vec::new();
+        let mut kpos = 0;
+        while kpos < key_block.len() {
+            let offset = read_u64(&key_block, &mut kpos);
+            let mut end = kpos;
+            while key_block[end..end + unit_size].iter().any(|b| *b != 0) {
+                end += unit_size;
+            }
+            keys.push((offset, key_block[kpos..end].to_vec()));
+            kpos = end + unit_size;
+        }
+        assert_eq!(keys.len(), entry_count);
+
+        let record_block_count = read_u64(data, &mut pos) as usize;
+        let _ = read_u64(data, &mut pos);
+        let _ = read_u64(data, &mut pos);
+        let _ = read_u64(data, &mut pos);
+        let mut records: Vec<u8> = Vec::new();
+        let mut block_pos = pos + record_block_count * 16;
+        for _ in 0..record_block_count {
+            let compressed_size = read_u64(data, &mut pos) as usize;
+            let size = read_u64(data, &mut pos) as usize;
+            let block = decompress_block(&data[block_pos..block_pos + compressed_size]);
+            assert_eq!(block.len(), size);
+            records.extend(block);
+            block_pos += compressed_size;
+        }
+        assert_eq!(block_pos, data.len());
+
+        let ends: Vec<u64> = keys
+            .iter()
+            .skip(1)
+            .map(|k| k.0)
+            .chain(std::iter::once(records.len() as u64))
+            .collect();
+        keys.into_iter()
+            .zip(ends)
+            .map(|((start, key), end)| (key, records[start as usize..end as usize].to_vec()))
+            .collect()
+    }
+
+    fn create_idx_entry(
+        word: &str,
+        data_offset: i32,
+        data_size: i32,
+        synonyms: &[&str],
+    ) -> IdxEntry {
+        IdxEntry {
+            word: word.to_string(),
+            data_offset,
+            data_size,
+            synonym_words: synonyms.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn adler32_test() {
+        assert_eq!(adler32(b"Wikipedia"), 0x11E6_0398);
+    }
+
+    #[test]
+    fn create_mdx_test() {
+        let dict =
+            DictionaryData::InMemory(b"<a href=\"bword://abala\">abala</a>aba 1aba 2".to_vec());
+        let idx_entries = vec![
+            create_idx_entry("Abala", 0, 33, &["abalo", "abalaṃ", "Abala"]),
+            create_idx_entry("aba", 33, 10, &["abalo"]),
+        ];
+        let mdx = create_mdx(&create_dict_info(), &dict, idx_entries, &TestLogger::new())
+            .expect("Unexpected");
+
+        let entries: Vec<(String, String)> = read_mdict_data(&mdx, 1)
+            .into_iter()
+            .map(|(k, r)| {
+                (
+                    String::from_utf8(k).expect("Unexpected"),
+                    String::from_utf8(r).expect("Unexpected"),
+                )
+            })
+            .collect();
+        insta::assert_debug_snapshot!(entries);
+    }
+
+    #[test_case(true, "<html><head><link rel=\"stylesheet\" type=\"text/css\" href=\"dpd.css\"/></head><body>abala</body></html>\0")]
+    #[test_case(
+        false,
+        "<html><head><style>*{}</style></head><body>abala</body></html>\0"
+    )]
+    fn create_mdx_stylesheet_test(mdd: bool, expected: &str) {
+        let article = "<html><head><style>*{}</style></head><body>abala</body></html>";
+        let dict = DictionaryData::InMemory(article.as_bytes().to_vec());
+        let idx_entries = vec![create_idx_entry("abala", 0, article.len() as i32, &[])];
+        let dict_info = DictionaryInfo {
+            mdd,
+            ..create_dict_info()
+        };
+
+        let mdx =
+            create_mdx(&dict_info, &dict, idx_entries, &TestLogger::new()).expect("Unexpected");
+
+        let entries = read_mdict_data(&mdx, 1);
+        assert_eq!(String::from_utf8_lossy(&entries[0].1), expected);
+    }
+
+    #[test]
+    fn create_header_test() {
+        let header = create_header("Dictionary", &[("StripKey", "Yes"), ("Title", "a & b")]);
+
+        let text = String::from_utf16(
+            &header[4..header.len() - 4]
+                .chunks(2)
+                .map(|c| u16::from_le_bytes([c[0], c[1]]))
+                .collect::<Vec<_>>(),
+        )
+        .expect("Unexpected");
+        assert_eq!(
+            text,
+            "<Dictionary StripKey=\"Yes\" Title=\"a &amp; b\"/>\r\n\0"
+        );
+    }
+
+    #[test]
+    fn create_mdd_test() {
+        let dict_info = DictionaryInfo {
+            icon: vec![0x89, 0x50, 0x4E, 0x47],
+            ..create_dict_info()
+        };
+
+        let mdd = create_mdd(&dict_info, &TestLogger::new()).expect("Unexpected");
+
+        let entries = read_mdict_data(&mdd, 2);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].0, KeyEncoding::Utf16.encode("\\dpd.css"));
+        assert!(String::from_utf8_lossy(&entries[0].1).contains("a.dictionary-root-link-dpd"));
+        assert_eq!(entries[1].0, KeyEncoding::Utf16.encode("\\dpd.png"));
+        assert_eq!(entries[1].1, vec![0x89, 0x50, 0x4E, 0x47]);
+    }
+}
@@ -1 +1,2 @@
-pub mod output_format;
+pub mod output_format;
+pub(crate) mod output_generator;
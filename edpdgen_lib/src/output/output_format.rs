@@ -1,31 +1,127 @@
-use std::fmt;
-use std::str::FromStr;
-
-#[derive(Debug)]
-pub enum OutputFormat {
-    /// StarDict and GoldenDict formats.
-    StarDict,
-    /// Ven. Anandajyoti Dictionary format.
-    AjDict,
-}
-
-impl fmt::Display for OutputFormat {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            OutputFormat::StarDict => write!(f, "stardict"),
-            OutputFormat::AjDict => write!(f, "ajdict"),
-        }
-    }
-}
-
-impl FromStr for OutputFormat {
-    type Err = String;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s.to_lowercase().as_str() {
-            "stardict" => Ok(OutputFormat::StarDict),
-            "ajdict" => Ok(OutputFormat::AjDict),
-            _ => Err("Unknown output format".to_string()),
-        }
-    }
-}
+use crate::input::input_format::InputFormat;
+use crate::output::output_generator::OutputGenerator;
+use crate::{
+    ajdict, apple, dictd, dsl, html, jsonl, kobo, mdict, slob, sqlite, stardict, tei, xdxf,
+};
+use std::fmt;
+use std::str::FromStr;
+
+///
+/// The output formats, in the order they are listed in. A new format only has to be added here.
+///
+static OUTPUT_GENERATORS: &[&dyn OutputGenerator] = &[
+    &stardict::StarDictGenerator,
+    &ajdict::AjDictGenerator,
+    &dsl::DslGenerator,
+    &mdict::MdictGenerator,
+    &xdxf::XdxfGenerator,
+    &dictd::DictdGenerator,
+    &tei::TeiGenerator,
+    &sqlite::SqliteGenerator,
+    &jsonl::JsonlGenerator,
+    &html::HtmlGenerator,
+    &kobo::KoboGenerator,
+    &apple::AppleGenerator,
+    &slob::SlobGenerator,
+];
+
+#[derive(Clone, Copy)]
+pub struct OutputFormat(pub(crate) &'static dyn OutputGenerator);
+
+impl OutputFormat {
+    pub fn all() -> impl Iterator<Item = OutputFormat> {
+        OUTPUT_GENERATORS.iter().map(|g| OutputFormat(*g))
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.0.name()
+    }
+
+    pub fn description(&self) -> &'static str {
+        self.0.description()
+    }
+
+    pub fn input_formats(&self) -> &'static [InputFormat] {
+        self.0.input_formats()
+    }
+
+    pub fn options(&self) -> &'static [&'static str] {
+        self.0.options()
+    }
+
+    pub fn supports(&self, input_format: &InputFormat) -> bool {
+        self.input_formats().contains(input_format)
+    }
+
+    pub(crate) fn generator(&self) -> &'static dyn OutputGenerator {
+        self.0
+    }
+}
+
+impl fmt::Debug for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.to_lowercase();
+        OutputFormat::all()
+            .find(|f| f.name() == s)
+            .ok_or_else(|| "Unknown output format".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use itertools::Itertools;
+    use test_case::test_case;
+
+    #[test]
+    fn names_are_unique_test() {
+        assert_eq!(
+            OutputFormat::all().map(|f| f.name()).unique().count(),
+            OUTPUT_GENERATORS.len()
+        );
+    }
+
+    #[test]
+    fn every_format_supports_dpd_test() {
+        assert!(OutputFormat::all().all(|f| f.supports(&InputFormat::Dpd)));
+    }
+
+    #[test_case("stardict", "stardict")]
+    #[test_case("Kobo", "kobo")]
+    #[test_case("SLOB", "slob")]
+    fn from_str_test(s: &str, expected: &str) {
+        let output_format = OutputFormat::from_str(s).expect("Unexpected");
+
+        assert_eq!(output_format.to_string(), expected);
+    }
+
+    #[test]
+    fn from_str_unknown_test() {
+        assert_eq!(
+            OutputFormat::from_str("epub").err(),
+            Some("Unknown output format".to_string())
+        );
+    }
+
+    #[test]
+    fn supports_test() {
+        let output_format = OutputFormat::from_str("ajdict").expect("Unexpected");
+
+        assert!(output_format.supports(&InputFormat::Dpd));
+        assert!(!output_format.supports(&InputFormat::Dps));
+    }
+}
@@ -0,0 +1,24 @@
+use crate::input::input_format::InputFormat;
+use crate::{DictionaryFiles, DictionaryInfo};
+use pls_core_extras::inflection_generator::InflectionGenerator;
+use pls_core_extras::logger::PlsLogger;
+use std::path::Path;
+
+///
+/// A dictionary format, registered once in the list of output formats.
+///
+pub(crate) trait OutputGenerator: Sync {
+    /// The name the format is chosen by on the command line.
+    fn name(&self) -> &'static str;
+    fn description(&self) -> &'static str;
+    fn input_formats(&self) -> &'static [InputFormat];
+    /// The command line options that change the output of the format.
+    fn options(&self) -> &'static [&'static str];
+    fn build_files(
+        &self,
+        dict_info: &DictionaryInfo,
+        input_data_path: &Path,
+        igen: &dyn InflectionGenerator,
+        logger: &dyn PlsLogger,
+    ) -> Result<DictionaryFiles, String>;
+}
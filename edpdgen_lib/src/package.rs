@@ -0,0 +1,406 @@
+use crate::{make_file_path, DictionaryFiles, DictionaryInfo};
+use chrono::{DateTime, Datelike, Timelike};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use pls_core_extras::logger::PlsLogger;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::{BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+const CHECKSUMS_FILE_NAME: &str = "SHA256SUMS";
+const MANIFEST_FILE_NAME: &str = "manifest.json";
+
+#[derive(Serialize)]
+struct ManifestFile {
+    name: String,
+    size: u64,
+    sha256: String,
+}
+
+#[derive(Serialize)]
+struct Manifest<'a> {
+    name: &'a str,
+    short_name: &'a str,
+    version: &'a str,
+    format: String,
+    input_format: String,
+    word_count: usize,
+    time_stamp: &'a str,
+    files: Vec<ManifestFile>,
+}
+
+///
+/// Bundles the written dictionary files along with a manifest into .zip and .tar.gz
+/// archives named <short_name>-<version>-<format> and records their checksums in the
+/// SHA256SUMS file of the output folder.
+///
+/// NOTE: All archive entries get the dictionary time stamp, so packages are reproducible.
+///
+pub fn create_packages(
+    dict_info: &DictionaryInfo,
+    base_path: &Path,
+    dict: &DictionaryFiles,
+    logger: &dyn PlsLogger,
+) -> Result<(), String> {
+    let mtime = DateTime::parse_from_rfc3339(dict_info.time_stamp).map_err(|e| e.to_string())?;
+    let output_folder = base_path
+        .parent()
+        .ok_or_else(|| format!("Unable to get parent folder for {:?}.", &base_path))?;
+    let package_name = format!(
+        "{}-{}-{}",
+        dict_info.short_name, dict_info.version, dict_info.output_format
+    );
+
+    let files = dict
+        .files
+        .iter()
+        .map(|f| {
+            let path = make_file_path(base_path, &f.extension);
+            Ok((entry_name(output_folder, &path)?, path))
+        })
+        .collect::<Result<Vec<(String, PathBuf)>, String>>()?;
+    let manifest = create_manifest(dict_info, dict.word_count, &files)?;
+
+    let zip_path = output_folder.join(format!("{}.zip", package_name));
+    logger.info(&format!("Packaging {:?}.", &zip_path));
+    create_zip(&zip_path, &package_name, &files, &manifest, &mtime)?;
+
+    let tar_gz_path = output_folder.join(format!("{}.tar.gz", package_name));
+    logger.info(&format!("Packaging {:?}.", &tar_gz_path));
+    create_tar_gz(&tar_gz_path, &package_name, &files, &manifest, &mtime)?;
+
+    update_checksums(output_folder, &[zip_path, tar_gz_path], logger)
+}
+
+fn file_name(path: &Path) -> Result<String, String> {
+    path.file_name()
+        .and_then(|n| n.to_str())
+        .map(|n| n.to_string())
+        .ok_or_else(|| format!("Unable to get file name for {:?}.", path))
+}
+
+///
+/// The path relative to the output folder, as files can be nested, e.g. those of the html site.
+///
+fn entry_name(output_folder: &Path, path: &Path) -> Result<String, String> {
+    let relative_path = path
+        .strip_prefix(output_folder)
+        .map_err(|e| e.to_string())?;
+
+    relative_path
+        .components()
+        .map(|c| {
+            c.as_os_str()
+                .to_str()
+                .ok_or_else(|| format!("Unable to get file name for {:?}.", path))
+        })
+        .collect::<Result<Vec<&str>, String>>()
+        .map(|cs| cs.join("/"))
+}
+
+fn file_sha256(path: &Path) -> Result<String, String> {
+    let mut f = File::open(path).map_err(|e| e.to_string())?;
+    let mut hasher = Sha256::new();
+    io::copy(&mut f, &mut hasher).map_err(|e| e.to_string())?;
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+fn create_manifest(
+    dict_info: &DictionaryInfo,
+    word_count: usize,
+    files: &[(String, PathBuf)],
+) -> Result<Vec<u8>, String> {
+    let files = files
+        .iter()
+        .map(|(name, f)| {
+            Ok(ManifestFile {
+                name: name.clone(),
+                size: fs::metadata(f).map_err(|e| e.to_string())?.len(),
+                sha256: file_sha256(f)?,
+            })
+        })
+        .collect::<Result<Vec<ManifestFile>, String>>()?;
+
+    let manifest = Manifest {
+        name: dict_info.name,
+        short_name: dict_info.short_name,
+        version: dict_info.version,
+        format: dict_info.output_format.to_string(),
+        input_format: dict_info.input_format.to_string(),
+        word_count,
+        time_stamp: dict_info.time_stamp,
+        files,
+    };
+
+    serde_json::to_vec_pretty(&manifest).map_err(|e| e.to_string())
+}
+
+fn create_zip<Tz: chrono::TimeZone>(
+    zip_path: &Path,
+    package_name: &str,
+    files: &[(String, PathBuf)],
+    manifest: &[u8],
+    mtime: &DateTime<Tz>,
+) -> Result<(), String> {
+    // NOTE: Zip cannot represent dates before 1980, those fall back to the zip epoch.
+    let mtime = zip::DateTime::from_date_and_time(
+        mtime.year() as u16,
+        mtime.month() as u8,
+        mtime.day() as u8,
+        mtime.hour() as u8,
+        mtime.minute() as u8,
+        mtime.second() as u8,
+    )
+    .unwrap_or_default();
+    let options = FileOptions::default()
+        .compression_method(CompressionMethod::Deflated)
+        .last_modified_time(mtime)
+        .unix_permissions(0o644);
+
+    let f = File::create(zip_path).map_err(|e| e.to_string())?;
+    let mut zip = ZipWriter::new(BufWriter::new(f));
+
+    zip.start_file(format!("{}/{}", package_name, MANIFEST_FILE_NAME), options)
+        .map_err(|e| e.to_string())?;
+    zip.write_all(manifest).map_err(|e| e.to_string())?;
+
+    for (name, path) in files {
+        zip.start_file(format!("{}/{}", package_name, name), options)
+            .map_err(|e| e.to_string())?;
+        let mut src = File::open(path).map_err(|e| e.to_string())?;
+        io::copy(&mut src, &mut zip).map_err(|e| e.to_string())?;
+    }
+
+    zip.finish()
+        .map_err(|e| e.to_string())?
+        .flush()
+        .map_err(|e| e.to_string())
+}
+
+fn append_tar_entry<W: Write>(
+    tar: &mut tar::Builder<W>,
+    path: &str,
+    size: u64,
+    mtime: u64,
+    data: impl Read,
+) -> Result<(), String> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(size);
+    header.set_mode(0o644);
+    header.set_mtime(mtime);
+    header.set_uid(0);
+    header.set_gid(0);
+
+    tar.append_data(&mut header, path, data)
+        .map_err(|e| e.to_string())
+}
+
+fn create_tar_gz<Tz: chrono::TimeZone>(
+    tar_gz_path: &Path,
+    package_name: &str,
+    files: &[(String, PathBuf)],
+    manifest: &[u8],
+    mtime: &DateTime<Tz>,
+) -> Result<(), String> {
+    let mtime = mtime.timestamp().max(0) as u64;
+
+    let f = File::create(tar_gz_path).map_err(|e| e.to_string())?;
+    let mut tar = tar::Builder::new(GzEncoder::new(BufWriter::new(f), Compression::default()));
+
+    append_tar_entry(
+        &mut tar,
+        &format!("{}/{}", package_name, MANIFEST_FILE_NAME),
+        manifest.len() as u64,
+        mtime,
+        manifest,
+    )?;
+
+    for (name, path) in files {
+        let src = File::open(path).map_err(|e| e.to_string())?;
+        let size = src.metadata().map_err(|e| e.to_string())?.len();
+        append_tar_entry(
+            &mut tar,
+            &format!("{}/{}", package_name, name),
+            size,
+            mtime,
+            src,
+        )?;
+    }
+
+    tar.into_inner()
+        .map_err(|e| e.to_string())?
+        .finish()
+        .map_err(|e| e.to_string())?
+        .flush()
+        .map_err(|e| e.to_string())
+}
+
+///
+/// SHA256SUMS is shared by all dictionaries in the output folder, so existing entries for
+/// other packages are preserved. The format is that of sha256sum, i.e. sha256sum -c works.
+///
+fn update_checksums(
+    output_folder: &Path,
+    packages: &[PathBuf],
+    logger: &dyn PlsLogger,
+) -> Result<(), String> {
+    let checksums_path = output_folder.join(CHECKSUMS_FILE_NAME);
+
+    let mut checksums: BTreeMap<String, String> = fs::read_to_string(&checksums_path)
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|l| l.split_once("  "))
+        .map(|(sum, name)| (name.to_string(), sum.to_string()))
+        .collect();
+    for package in packages {
+        checksums.insert(file_name(package)?, file_sha256(package)?);
+    }
+
+    logger.info(&format!("Writing {:?}.", &checksums_path));
+    let sums = checksums
+        .iter()
+        .map(|(name, sum)| format!("{}  {}\n", sum, name))
+        .collect::<String>();
+    fs::write(&checksums_path, sums).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::output_format::OutputFormat;
+    use crate::tests::TestLogger;
+    use crate::{DictionaryData, DictionaryFile};
+    use flate2::read::GzDecoder;
+    use zip::ZipArchive;
+
+    fn create_dict_info(output_folder: &str) -> DictionaryInfo<'_> {
+        DictionaryInfo {
+            output_folder,
+            time_stamp: "2021-01-01T00:00:00Z",
+            version: "1.2.3",
+            package: true,
+            ..crate::tests::create_dict_info(&OutputFormat(&crate::stardict::StarDictGenerator))
+        }
+    }
+
+    fn create_test_package(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join("edpdgen_package_test").join(name);
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).expect("Unexpected");
+        let base_path = dir.join("dpd");
+        fs::write(base_path.with_extension("idx"), b"idx data").expect("Unexpected");
+        fs::write(base_path.with_extension("ifo"), b"ifo data").expect("Unexpected");
+        fs::write(dir.join(CHECKSUMS_FILE_NAME), "0123  other.zip\n").expect("Unexpected");
+
+        let dict = DictionaryFiles {
+            word_count: 2,
+            files: ["idx", "ifo"]
+                .iter()
+                .map(|e| DictionaryFile {
+                    extension: e.to_string(),
+                    bom: vec![],
+                    data: DictionaryData::InMemory(vec![]),
+                    can_be_empty: false,
+                })
+                .collect(),
+        };
+
+        let dict_info = create_dict_info(dir.to_str().expect("Unexpected"));
+        create_packages(&dict_info, &base_path, &dict, &TestLogger::new()).expect("Unexpected");
+
+        dir
+    }
+
+    #[test]
+    fn create_zip_package_test() {
+        let dir = create_test_package("zip");
+
+        let f = File::open(dir.join("dpd-1.2.3-stardict.zip")).expect("Unexpected");
+        let mut zip = ZipArchive::new(f).expect("Unexpected");
+        let mut names: Vec<&str> = zip.file_names().collect();
+        names.sort_unstable();
+        assert_eq!(
+            names,
+            vec![
+                "dpd-1.2.3-stardict/dpd.idx",
+                "dpd-1.2.3-stardict/dpd.ifo",
+                "dpd-1.2.3-stardict/manifest.json"
+            ]
+        );
+
+        let mut manifest = String::new();
+        zip.by_name("dpd-1.2.3-stardict/manifest.json")
+            .expect("Unexpected")
+            .read_to_string(&mut manifest)
+            .expect("Unexpected");
+        let manifest: serde_json::Value = serde_json::from_str(&manifest).expect("Unexpected");
+        assert_eq!(manifest["version"], "1.2.3");
+        assert_eq!(manifest["format"], "stardict");
+        assert_eq!(manifest["word_count"], 2);
+        assert_eq!(manifest["time_stamp"], "2021-01-01T00:00:00Z");
+        assert_eq!(manifest["files"][0]["name"], "dpd.idx");
+        assert_eq!(manifest["files"][0]["size"], 8);
+        assert_eq!(
+            manifest["files"][0]["sha256"],
+            format!("{:x}", Sha256::digest(b"idx data"))
+        );
+    }
+
+    #[test]
+    fn create_tar_gz_package_test() {
+        let dir = create_test_package("tar_gz");
+
+        let f = File::open(dir.join("dpd-1.2.3-stardict.tar.gz")).expect("Unexpected");
+        let mut tar = tar::Archive::new(GzDecoder::new(f));
+        let entries: Vec<(String, u64, String)> = tar
+            .entries()
+            .expect("Unexpected")
+            .map(|e| {
+                let mut e = e.expect("Unexpected");
+                let mut data = String::new();
+                e.read_to_string(&mut data).expect("Unexpected");
+                (
+                    e.path().expect("Unexpected").display().to_string(),
+                    e.header().mtime().expect("Unexpected"),
+                    data,
+                )
+            })
+            .collect();
+
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].0, "dpd-1.2.3-stardict/manifest.json");
+        assert_eq!(
+            (entries[1].0.as_str(), entries[1].1, entries[1].2.as_str()),
+            ("dpd-1.2.3-stardict/dpd.idx", 1609459200, "idx data")
+        );
+    }
+
+    #[test]
+    fn checksums_are_merged_test() {
+        let dir = create_test_package("checksums");
+
+        let sums = fs::read_to_string(dir.join(CHECKSUMS_FILE_NAME)).expect("Unexpected");
+        let names: Vec<&str> = sums
+            .lines()
+            .filter_map(|l| l.split_once("  "))
+            .map(|(_, name)| name)
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                "dpd-1.2.3-stardict.tar.gz",
+                "dpd-1.2.3-stardict.zip",
+                "other.zip"
+            ]
+        );
+        let zip_sum = file_sha256(&dir.join("dpd-1.2.3-stardict.zip")).expect("Unexpected");
+        assert!(sums.contains(&format!("{}  dpd-1.2.3-stardict.zip\n", zip_sum)));
+    }
+}
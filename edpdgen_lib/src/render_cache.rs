@@ -0,0 +1,202 @@
+use pls_core_extras::logger::PlsLogger;
+use rusqlite::{params, Connection, OptionalExtension};
+use sha2::{Digest, Sha256};
+use std::cell::Cell;
+use std::fs;
+use std::path::Path;
+
+///
+/// Content addressed cache of rendered word groups, persisted in a local SQLite file.
+///
+/// Entries are keyed on the serialized words of a group combined with a fingerprint of
+/// everything else that affects the render (templates, dictionary settings, inflection db).
+/// Entries not used by a build are pruned at the end of that build.
+///
+pub struct RenderCache {
+    conn: Connection,
+    fingerprint: String,
+    build_id: i64,
+    hits: Cell<usize>,
+    misses: Cell<usize>,
+}
+
+pub struct CachedRender {
+    pub html: String,
+    pub inflections: Vec<String>,
+}
+
+impl RenderCache {
+    pub fn open(path: &Path, fingerprint: &str, logger: &dyn PlsLogger) -> Result<Self, String> {
+        logger.info(&format!("Opening render cache {:?}.", path));
+
+        let conn = Connection::open(path).map_err(|e| e.to_string())?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS render_cache (
+                key TEXT PRIMARY KEY,
+                html TEXT NOT NULL,
+                inflections TEXT NOT NULL,
+                build_id INTEGER NOT NULL
+            );
+            BEGIN;",
+        )
+        .map_err(|e| e.to_string())?;
+
+        let build_id: i64 = conn
+            .query_row(
+                "SELECT COALESCE(MAX(build_id), 0) + 1 FROM render_cache",
+                params![],
+                |r| r.get(0),
+            )
+            .map_err(|e| e.to_string())?;
+
+        Ok(RenderCache {
+            conn,
+            fingerprint: fingerprint.to_string(),
+            build_id,
+            hits: Cell::new(0),
+            misses: Cell::new(0),
+        })
+    }
+
+    pub fn make_key(&self, word_jsons: &[String]) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(self.fingerprint.as_bytes());
+        for wj in word_jsons {
+            hasher.update([0u8]);
+            hasher.update(wj.as_bytes());
+        }
+
+        format!("{:x}", hasher.finalize())
+    }
+
+    pub fn get(&self, key: &str) -> Result<Option<CachedRender>, String> {
+        let entry: Option<(String, String)> = self
+            .conn
+            .query_row(
+                "SELECT html, inflections FROM render_cache WHERE key = ?1",
+                params![key],
+                |r| Ok((r.get(0)?, r.get(1)?)),
+            )
+            .optional()
+            .map_err(|e| e.to_string())?;
+
+        match entry {
+            Some((html, inflections)) => {
+                self.conn
+                    .execute(
+                        "UPDATE render_cache SET build_id = ?1 WHERE key = ?2",
+                        params![self.build_id, key],
+                    )
+                    .map_err(|e| e.to_string())?;
+                self.hits.set(self.hits.get() + 1);
+
+                Ok(Some(CachedRender {
+                    html,
+                    inflections: serde_json::from_str(&inflections).map_err(|e| e.to_string())?,
+                }))
+            }
+            None => {
+                self.misses.set(self.misses.get() + 1);
+                Ok(None)
+            }
+        }
+    }
+
+    pub fn put(&self, key: &str, render: &CachedRender) -> Result<(), String> {
+        let inflections = serde_json::to_string(&render.inflections).map_err(|e| e.to_string())?;
+        self.conn
+            .execute(
+                "INSERT OR REPLACE INTO render_cache (key, html, inflections, build_id) VALUES (?1, ?2, ?3, ?4)",
+                params![key, render.html, inflections, self.build_id],
+            )
+            .map_err(|e| e.to_string())?;
+
+        Ok(())
+    }
+
+    pub fn finish(self, logger: &dyn PlsLogger) -> Result<(), String> {
+        let stale = self
+            .conn
+            .execute(
+                "DELETE FROM render_cache WHERE build_id < ?1",
+                params![self.build_id],
+            )
+            .map_err(|e| e.to_string())?;
+        self.conn
+            .execute_batch("COMMIT;")
+            .map_err(|e| e.to_string())?;
+
+        logger.info(&format!(
+            "Render cache: {} hits, {} misses, {} stale entries removed.",
+            self.hits.get(),
+            self.misses.get(),
+            stale
+        ));
+
+        Ok(())
+    }
+}
+
+///
+/// Identifies the contents of a data file such as the inflections db, so cached renders are
+/// invalidated when the file is regenerated.
+///
+pub fn data_file_version(path: Option<&str>) -> Result<String, String> {
+    match path {
+        Some(path) => {
+            let data = fs::read(path).map_err(|e| e.to_string())?;
+            Ok(format!("{:x}", Sha256::digest(&data)))
+        }
+        None => Ok("none".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::TestLogger;
+
+    fn make_render(html: &str) -> CachedRender {
+        CachedRender {
+            html: html.to_string(),
+            inflections: vec![format!("{}_1", html), format!("{}_2", html)],
+        }
+    }
+
+    #[test]
+    fn make_key_depends_on_fingerprint_and_words_test() {
+        let l = TestLogger::new();
+        let dir = tempfile::tempdir().expect("Unexpected");
+        let c1 = RenderCache::open(&dir.path().join("key1.db"), "fp1", &l).expect("Unexpected");
+        let c2 = RenderCache::open(&dir.path().join("key2.db"), "fp2", &l).expect("Unexpected");
+        let words = vec!["{\"a\":1}".to_string()];
+
+        assert_eq!(c1.make_key(&words), c1.make_key(&words));
+        assert_ne!(c1.make_key(&words), c2.make_key(&words));
+        assert_ne!(c1.make_key(&words), c1.make_key(&["{\"a\":2}".to_string()]));
+    }
+
+    #[test]
+    fn hits_survive_rebuild_and_stale_entries_are_pruned_test() {
+        let l = TestLogger::new();
+        let dir = tempfile::tempdir().expect("Unexpected");
+        let path = dir.path().join("prune.db");
+
+        let cache = RenderCache::open(&path, "fp", &l).expect("Unexpected");
+        cache.put("k1", &make_render("a")).expect("Unexpected");
+        cache.put("k2", &make_render("b")).expect("Unexpected");
+        cache.finish(&l).expect("Unexpected");
+
+        let cache = RenderCache::open(&path, "fp", &l).expect("Unexpected");
+        let hit = cache.get("k1").expect("Unexpected").expect("Unexpected");
+        assert_eq!(hit.html, "a");
+        assert_eq!(hit.inflections, vec!["a_1", "a_2"]);
+        assert!(cache.get("k3").expect("Unexpected").is_none());
+        assert_eq!((cache.hits.get(), cache.misses.get()), (1, 1));
+        cache.finish(&l).expect("Unexpected");
+
+        let cache = RenderCache::open(&path, "fp", &l).expect("Unexpected");
+        assert!(cache.get("k1").expect("Unexpected").is_some());
+        assert!(cache.get("k2").expect("Unexpected").is_none());
+    }
+}
@@ -0,0 +1,132 @@
+use crate::input::dpd::DpdPaliWord;
+use crate::input::{create_csv_reader, make_group_id, make_sort_key};
+use itertools::Itertools;
+use pls_core_extras::logger::PlsLogger;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+#[derive(Debug, Serialize)]
+pub struct RootWord {
+    pub pali1: String,
+    pub group_id: String,
+    pub pos: String,
+    pub meaning: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Family {
+    pub name: String,
+    pub words: Vec<RootWord>,
+}
+
+///
+/// All words derived from a Pāli root, e.g. √gam, grouped by their Family column.
+///
+#[derive(Debug, Serialize)]
+pub struct RootFamily {
+    pub root: String,
+    pub meaning: String,
+    pub v: String,
+    pub grp: String,
+    pub sgn: String,
+    pub families: Vec<Family>,
+}
+
+impl RootFamily {
+    /// The root without the √, so that it can also be looked up by typing.
+    pub fn bare_root(&self) -> &str {
+        self.root.trim_start_matches('√')
+    }
+}
+
+pub fn create_root_families(words: impl Iterator<Item = DpdPaliWord>) -> Vec<RootFamily> {
+    let mut roots: BTreeMap<String, Vec<DpdPaliWord>> = BTreeMap::new();
+    for w in words.filter(|w| !w.pali_root.trim().is_empty()) {
+        roots
+            .entry(w.pali_root.trim().to_string())
+            .or_default()
+            .push(w);
+    }
+
+    roots
+        .into_iter()
+        .map(|(root, words)| {
+            // NOTE: Root info is repeated on every row, the first non-empty one wins.
+            let first_of = |f: fn(&DpdPaliWord) -> &str| {
+                words
+                    .iter()
+                    .map(f)
+                    .find(|v| !v.is_empty())
+                    .unwrap_or_default()
+                    .to_string()
+            };
+
+            RootFamily {
+                meaning: first_of(|w| &w.root_meaning),
+                v: first_of(|w| &w.v),
+                grp: first_of(|w| &w.grp),
+                sgn: first_of(|w| &w.sgn),
+                families: create_families(&words),
+                root,
+            }
+        })
+        .collect()
+}
+
+fn create_families(words: &[DpdPaliWord]) -> Vec<Family> {
+    words
+        .iter()
+        .sorted_by_key(|w| (w.family.clone(), make_sort_key(&w.pali1)))
+        .group_by(|w| w.family.clone())
+        .into_iter()
+        .map(|(name, words)| Family {
+            name,
+            words: words
+                .map(|w| RootWord {
+                    pali1: w.pali1.clone(),
+                    group_id: make_group_id(&w.pali1),
+                    pos: w.pos.clone(),
+                    meaning: if w.in_english.is_empty() {
+                        w.buddhadatta.clone()
+                    } else {
+                        w.in_english.clone()
+                    },
+                })
+                .collect(),
+        })
+        .collect()
+}
+
+pub fn load_root_families(
+    input_data_path: &Path,
+    logger: &dyn PlsLogger,
+) -> Result<Vec<RootFamily>, String> {
+    let rdr = create_csv_reader(input_data_path, logger)?;
+
+    // NOTE: Records that fail to deserialize are reported when the words are loaded.
+    let root_families =
+        create_root_families(rdr.into_deserialize::<DpdPaliWord>().filter_map(|r| r.ok()));
+
+    logger.info(&format!(
+        "... done loading {} root families.",
+        root_families.len()
+    ));
+    Ok(root_families)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resolve_file_in_manifest_dir;
+    use crate::tests::TestLogger;
+
+    #[test]
+    fn load_root_families_test() {
+        let path = resolve_file_in_manifest_dir("Pali_English_Dictionary_10_rows-full.csv")
+            .expect("must exist!");
+
+        let root_families = load_root_families(&path, &TestLogger::new()).expect("Unexpected");
+
+        insta::assert_yaml_snapshot!(root_families);
+    }
+}
@@ -0,0 +1,76 @@
+use crate::input::dpd::DpdPaliWord;
+use crate::input::dps::DpsPaliWord;
+use crate::input::input_format::InputFormat;
+use crate::output::output_generator::OutputGenerator;
+use crate::stardict::{create_articles, StarDictPaliWord};
+use crate::{DictionaryData, DictionaryFiles, DictionaryInfo};
+use pls_core_extras::inflection_generator::InflectionGenerator;
+use pls_core_extras::logger::PlsLogger;
+use std::fs;
+use std::path::Path;
+
+mod output_generators;
+
+pub struct SlobGenerator;
+
+impl OutputGenerator for SlobGenerator {
+    fn name(&self) -> &'static str {
+        "slob"
+    }
+
+    fn description(&self) -> &'static str {
+        "Slob format for Aard 2."
+    }
+
+    fn input_formats(&self) -> &'static [InputFormat] {
+        &[InputFormat::Dpd, InputFormat::Dps]
+    }
+
+    fn options(&self) -> &'static [&'static str] {
+        &[
+            "concise",
+            "deconstruct",
+            "sutta-links",
+            "frequency",
+            "min-frequency",
+            "render-cache",
+        ]
+    }
+
+    fn build_files(
+        &self,
+        dict_info: &DictionaryInfo,
+        input_data_path: &Path,
+        igen: &dyn InflectionGenerator,
+        logger: &dyn PlsLogger,
+    ) -> Result<DictionaryFiles, String> {
+        match dict_info.input_format {
+            InputFormat::Dpd => {
+                run_for_ods_type::<DpdPaliWord>(dict_info, input_data_path, igen, logger)
+            }
+            InputFormat::Dps => {
+                run_for_ods_type::<DpsPaliWord>(dict_info, input_data_path, igen, logger)
+            }
+        }
+    }
+}
+
+///
+/// The entries are the html of the StarDict output, so they look the same as in the dictionary.
+///
+pub fn run_for_ods_type<'a, T: 'a + serde::de::DeserializeOwned + StarDictPaliWord>(
+    dict_info: &DictionaryInfo,
+    input_data_path: &Path,
+    igen: &dyn InflectionGenerator,
+    logger: &dyn PlsLogger,
+) -> Result<DictionaryFiles, String> {
+    let (dict, idx_entries) = create_articles::<T>(dict_info, input_data_path, igen, logger)?;
+    let slob_files = output_generators::create_dictionary(dict_info, &dict, idx_entries, logger);
+
+    // NOTE: The .dict data is only an intermediate here, the articles now live in the bins.
+    if let DictionaryData::FileBacked { path, .. } = &dict {
+        let _ = fs::remove_file(path);
+    }
+
+    slob_files
+}
@@ -0,0 +1,577 @@
+use crate::stardict::{create_stylesheet, ArticleReader, IdxEntry};
+use crate::{DictionaryData, DictionaryFile, DictionaryFiles, DictionaryInfo};
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use icu_collator::{AlternateHandling, Collator, CollatorOptions, Strength};
+use itertools::Itertools;
+use pls_core_extras::logger::PlsLogger;
+use regex::{Captures, Regex};
+use sha2::{Digest, Sha256};
+use std::io::Write;
+
+lazy_static! {
+    static ref ARTICLE_HEAD: Regex = Regex::new(r"(?s)<head>.*</head>").expect("is valid regex");
+    static ref HTML_START: Regex = Regex::new(r"<html[^>]*>").expect("is valid regex");
+    static ref BWORD_LINK: Regex = Regex::new(r#"href="bword://([^"]*)""#).expect("is valid regex");
+}
+
+/// See https://github.com/itkach/slob
+const MAGIC: &[u8; 8] = b"!-1SLOB\x1F";
+
+const ENCODING: &str = "utf-8";
+
+const COMPRESSION: &str = "zlib";
+
+const HTML_CONTENT_TYPE: &str = "text/html; charset=utf-8";
+
+const CSS_CONTENT_TYPE: &str = "text/css";
+
+/// Keys of resources start with ~/ as the articles are at the root of the slob.
+const CSS_KEY: &str = "~/css/style.css";
+
+/// As in slob.py, the uncompressed size a bin is filled up to.
+const MAX_BIN_SIZE: usize = 384 * 1024;
+
+struct SlobRef {
+    key: String,
+    bin_index: u32,
+    item_index: u16,
+}
+
+#[derive(Default)]
+struct SlobStore {
+    bins: Vec<(Vec<u8>, Vec<Vec<u8>>)>,
+    bin_size: usize,
+    blob_count: u32,
+}
+
+impl SlobStore {
+    fn add(&mut self, content_type_id: u8, content: Vec<u8>) -> (u32, u16) {
+        let full = self.bins.last().is_none_or(|(ids, _)| {
+            self.bin_size + content.len() > MAX_BIN_SIZE || ids.len() == u16::MAX as usize
+        });
+        if full {
+            self.bins.push((vec![], vec![]));
+            self.bin_size = 0;
+        }
+
+        let bin_index = (self.bins.len() - 1) as u32;
+        let (ids, items) = self.bins.last_mut().expect("Unexpected");
+        ids.push(content_type_id);
+        self.bin_size += content.len();
+        items.push(content);
+        self.blob_count += 1;
+
+        (bin_index, (ids.len() - 1) as u16)
+    }
+}
+
+fn write_tiny_text(out: &mut Vec<u8>, s: &str) {
+    out.push(s.len() as u8);
+    out.extend(s.as_bytes());
+}
+
+fn write_text(out: &mut Vec<u8>, s: &str) {
+    out.extend(&(s.len() as u16).to_be_bytes());
+    out.extend(s.as_bytes());
+}
+
+///
+/// Lists are the count, the positions of the items relative to the end of the positions and then
+/// the items.
+///
+fn write_item_list(out: &mut Vec<u8>, items: &[Vec<u8>]) {
+    out.extend(&(items.len() as u32).to_be_bytes());
+    let mut pos = 0u64;
+    for item in items {
+        out.extend(&pos.to_be_bytes());
+        pos += item.len() as u64;
+    }
+    for item in items {
+        out.extend(item);
+    }
+}
+
+///
+/// Items in a bin are positioned with 32 bit offsets, unlike the store and the refs.
+///
+fn create_bin(items: &[Vec<u8>]) -> Vec<u8> {
+    let mut bin = Vec::new();
+    let mut pos = 0u32;
+    for item in items {
+        bin.extend(&pos.to_be_bytes());
+        pos += 4 + item.len() as u32;
+    }
+    for item in items {
+        bin.extend(&(item.len() as u32).to_be_bytes());
+        bin.extend(item);
+    }
+
+    bin
+}
+
+fn compress(data: &[u8]) -> Result<Vec<u8>, String> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data).map_err(|e| e.to_string())?;
+    encoder.finish().map_err(|e| e.to_string())
+}
+
+fn make_url(key: &str) -> String {
+    key.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+///
+/// The articles link to the stylesheet resource and to each other by key, relative to the article.
+///
+fn make_article(article: &str) -> String {
+    let head = format!(
+        "<head><meta charset=\"utf-8\"/><link rel=\"stylesheet\" href=\"{}\" type=\"text/css\"/></head>",
+        CSS_KEY
+    );
+    let article = if ARTICLE_HEAD.is_match(article) {
+        ARTICLE_HEAD.replace(article, head.as_str())
+    } else {
+        match HTML_START.find(article) {
+            Some(m) => format!("{}{}{}", m.as_str(), head, &article[m.end()..]).into(),
+            None => format!("{}{}", head, article).into(),
+        }
+    };
+
+    BWORD_LINK
+        .replace_all(&article, |caps: &Captures| {
+            format!("href=\"{}\"", make_url(&caps[1]))
+        })
+        .to_string()
+}
+
+///
+/// Aard 2 looks keys up with binary search, so the refs have to be in the order slob.py sorts
+/// them in, i.e. the root collation with punctuation ignored.
+///
+fn create_collator() -> Result<Collator, String> {
+    let mut options = CollatorOptions::new();
+    options.strength = Some(Strength::Identical);
+    options.alternate_handling = Some(AlternateHandling::Shifted);
+
+    Collator::try_new(&Default::default(), options).map_err(|e| e.to_string())
+}
+
+fn create_refs(refs: &mut [SlobRef]) -> Result<Vec<Vec<u8>>, String> {
+    let collator = create_collator()?;
+    refs.sort_by(|r1, r2| collator.compare(&r1.key, &r2.key));
+
+    Ok(refs
+        .iter()
+        .map(|r| {
+            let mut item = Vec::new();
+            write_text(&mut item, &r.key);
+            item.extend(&r.bin_index.to_be_bytes());
+            item.extend(&r.item_index.to_be_bytes());
+            write_tiny_text(&mut item, "");
+            item
+        })
+        .collect())
+}
+
+fn create_store(store: &SlobStore, logger: &dyn PlsLogger) -> Result<Vec<Vec<u8>>, String> {
+    logger.info(&format!("Compressing {} slob bins.", store.bins.len()));
+
+    store
+        .bins
+        .iter()
+        .map(|(ids, items)| {
+            let content = compress(&create_bin(items))?;
+            let mut item = Vec::new();
+            item.extend(&(ids.len() as u32).to_be_bytes());
+            item.extend(ids);
+            item.extend(&(content.len() as u32).to_be_bytes());
+            item.extend(content);
+            Ok(item)
+        })
+        .collect()
+}
+
+///
+/// The uuid is derived from the dictionary info so builds stay reproducible.
+///
+fn create_uuid(dict_info: &DictionaryInfo) -> [u8; 16] {
+    let hash = Sha256::digest(
+        format!(
+            "{}|{}|{}",
+            dict_info.short_name, dict_info.version, dict_info.time_stamp
+        )
+        .as_bytes(),
+    );
+    let mut uuid = [0u8; 16];
+    uuid.copy_from_slice(&hash[..16]);
+    uuid[6] = (uuid[6] & 0x0F) | 0x80;
+    uuid[8] = (uuid[8] & 0x3F) | 0x80;
+
+    uuid
+}
+
+fn create_header(dict_info: &DictionaryInfo, content_types: &[&str], blob_count: u32) -> Vec<u8> {
+    let tags = [
+        ("label", dict_info.name),
+        ("created.by", dict_info.author),
+        ("created.at", dict_info.time_stamp),
+        ("version", dict_info.version),
+    ];
+
+    let mut header = MAGIC.to_vec();
+    header.extend(&create_uuid(dict_info));
+    write_tiny_text(&mut header, ENCODING);
+    write_tiny_text(&mut header, COMPRESSION);
+    header.push(tags.len() as u8);
+    for (key, value) in tags.iter() {
+        write_tiny_text(&mut header, key);
+        write_tiny_text(&mut header, value);
+    }
+    header.push(content_types.len() as u8);
+    for content_type in content_types {
+        write_text(&mut header, content_type);
+    }
+    header.extend(&blob_count.to_be_bytes());
+
+    header
+}
+
+///
+/// The articles are the StarDict html with the stylesheet as a resource, the inflections become
+/// keys of their own pointing to the same article.
+///
+pub fn create_dictionary(
+    dict_info: &DictionaryInfo,
+    dict: &DictionaryData,
+    idx_entries: Vec<IdxEntry>,
+    logger: &dyn PlsLogger,
+) -> Result<DictionaryFiles, String> {
+    logger.info(&format!("Creating {} slob articles.", idx_entries.len()));
+
+    let content_types = [HTML_CONTENT_TYPE, CSS_CONTENT_TYPE];
+    let mut store = SlobStore::default();
+    let mut refs = Vec::new();
+
+    let mut reader = ArticleReader::new(dict)?;
+    for (n, idx_entry) in idx_entries.iter().enumerate() {
+        let article = reader.read(idx_entry.data_offset, idx_entry.data_size)?;
+        let (bin_index, item_index) = store.add(0, make_article(&article).into_bytes());
+        for key in std::iter::once(&idx_entry.word)
+            .chain(idx_entry.synonym_words.iter())
+            .unique()
+        {
+            refs.push(SlobRef {
+                key: key.clone(),
+                bin_index,
+                item_index,
+            });
+        }
+
+        if n % 1_000 == 0 && n != 0 {
+            logger.info(&format!(
+                "... created {:05} slob articles, ending with '{}'.",
+                n, idx_entry.word
+            ));
+        }
+    }
+
+    let (bin_index, item_index) = store.add(1, create_stylesheet(dict_info)?.into_bytes());
+    refs.push(SlobRef {
+        key: CSS_KEY.to_string(),
+        bin_index,
+        item_index,
+    });
+
+    let ref_items = create_refs(&mut refs)?;
+    let store_items = create_store(&store, logger)?;
+
+    // NOTE: The header ends with the offset of the store and the size of the file.
+    let mut slob = create_header(dict_info, &content_types, store.blob_count);
+    let mut refs_data = Vec::new();
+    write_item_list(&mut refs_data, &ref_items);
+    let mut store_data = Vec::new();
+    write_item_list(&mut store_data, &store_items);
+    let store_offset = (slob.len() + 16 + refs_data.len()) as u64;
+    let size = store_offset + store_data.len() as u64;
+    slob.extend(&store_offset.to_be_bytes());
+    slob.extend(&size.to_be_bytes());
+    slob.extend(refs_data);
+    slob.extend(store_data);
+
+    logger.info(&format!(
+        "... done creating {} slob articles with {} keys.",
+        idx_entries.len(),
+        refs.len()
+    ));
+
+    Ok(DictionaryFiles {
+        word_count: idx_entries.len(),
+        files: vec![DictionaryFile {
+            extension: "slob".to_string(),
+            bom: vec![],
+            data: DictionaryData::InMemory(slob),
+            can_be_empty: false,
+        }],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{create_dict_info, TestLogger};
+    use crate::OutputFormat;
+    use flate2::read::ZlibDecoder;
+    use std::cmp::Ordering;
+    use std::convert::TryInto;
+    use std::io::Read;
+    use test_case::test_case;
+
+    const ARTICLES: &[(&str, &[&str], &str)] = &[
+        (
+            "abala",
+            &["abalo", "abalaṃ"],
+            "<html><head><style>*{}</style></head><body>\n<a href=\"bword://√bal\">√bal</a> <a href=\"bword://bal (derivation)\">bal</a></body></html>",
+        ),
+        ("Ābādha", &["ābādhā", "abala"], "<html><body>\nābādha</body></html>"),
+        ("kharo", &[], "<html><body>\nkharo</body></html>"),
+        ("√bal", &["bal"], "<html><body>\n√bal</body></html>"),
+    ];
+
+    fn create_slob() -> Vec<u8> {
+        let mut dict = Vec::new();
+        let mut idx_entries = Vec::new();
+        for (word, synonyms, html) in ARTICLES.iter() {
+            idx_entries.push(IdxEntry {
+                word: word.to_string(),
+                data_offset: dict.len() as i32,
+                data_size: html.len() as i32,
+                synonym_words: synonyms.iter().map(|s| s.to_string()).collect(),
+            });
+            dict.extend_from_slice(html.as_bytes());
+        }
+
+        let files = create_dictionary(
+            &create_dict_info(&OutputFormat(&crate::slob::SlobGenerator)),
+            &DictionaryData::InMemory(dict),
+            idx_entries,
+            &TestLogger::new(),
+        )
+        .expect("Unexpected");
+        assert_eq!(files.word_count, ARTICLES.len());
+        match &files.files[0].data {
+            DictionaryData::InMemory(data) => data.clone(),
+            _ => panic!("Unexpected"),
+        }
+    }
+
+    ///
+    /// Reads slobs the way slob.py does.
+    ///
+    struct SlobReader<'a> {
+        data: &'a [u8],
+        pos: usize,
+    }
+
+    impl<'a> SlobReader<'a> {
+        fn bytes(&mut self, len: usize) -> &'a [u8] {
+            self.pos += len;
+            &self.data[self.pos - len..self.pos]
+        }
+
+        fn u8(&mut self) -> u8 {
+            self.bytes(1)[0]
+        }
+
+        fn u16(&mut self) -> u16 {
+            u16::from_be_bytes(self.bytes(2).try_into().expect("Unexpected"))
+        }
+
+        fn u32(&mut self) -> u32 {
+            u32::from_be_bytes(self.bytes(4).try_into().expect("Unexpected"))
+        }
+
+        fn u64(&mut self) -> u64 {
+            u64::from_be_bytes(self.bytes(8).try_into().expect("Unexpected"))
+        }
+
+        fn tiny_text(&mut self) -> String {
+            let len = self.u8() as usize;
+            String::from_utf8(self.bytes(len).to_vec()).expect("Unexpected")
+        }
+
+        fn text(&mut self) -> String {
+            let len = self.u16() as usize;
+            String::from_utf8(self.bytes(len).to_vec()).expect("Unexpected")
+        }
+
+        ///
+        /// The start of each item of the list at the current position.
+        ///
+        fn item_list(&mut self, count: usize, pos_size: usize) -> Vec<usize> {
+            let positions: Vec<u64> = (0..count)
+                .map(|_| match pos_size {
+                    4 => u64::from(self.u32()),
+                    _ => self.u64(),
+                })
+                .collect();
+            let data_offset = self.pos;
+            positions
+                .into_iter()
+                .map(|p| data_offset + p as usize)
+                .collect()
+        }
+    }
+
+    struct Slob {
+        tags: Vec<(String, String)>,
+        content_types: Vec<String>,
+        blob_count: u32,
+        refs: Vec<(String, u32, u16, String)>,
+        bins: Vec<(Vec<u8>, Vec<u8>)>,
+    }
+
+    fn read_slob(data: &[u8]) -> Slob {
+        let mut r = SlobReader { data, pos: 0 };
+        assert_eq!(r.bytes(8), MAGIC);
+        r.bytes(16);
+        assert_eq!(r.tiny_text(), "utf-8");
+        assert_eq!(r.tiny_text(), "zlib");
+        let tags = (0..r.u8())
+            .map(|_| (r.tiny_text(), r.tiny_text()))
+            .collect();
+        let content_types = (0..r.u8()).map(|_| r.text()).collect();
+        let blob_count = r.u32();
+        let store_offset = r.u64() as usize;
+        assert_eq!(r.u64() as usize, data.len());
+
+        let count = r.u32() as usize;
+        let refs = r
+            .item_list(count, 8)
+            .into_iter()
+            .map(|pos| {
+                r.pos = pos;
+                (r.text(), r.u32(), r.u16(), r.tiny_text())
+            })
+            .collect();
+
+        r.pos = store_offset;
+        let count = r.u32() as usize;
+        let bins = r
+            .item_list(count, 8)
+            .into_iter()
+            .map(|pos| {
+                r.pos = pos;
+                let item_count = r.u32() as usize;
+                let content_type_ids = r.bytes(item_count).to_vec();
+                let len = r.u32() as usize;
+                let mut content = Vec::new();
+                ZlibDecoder::new(r.bytes(len))
+                    .read_to_end(&mut content)
+                    .expect("Unexpected");
+                (content_type_ids, content)
+            })
+            .collect();
+
+        Slob {
+            tags,
+            content_types,
+            blob_count,
+            refs,
+            bins,
+        }
+    }
+
+    impl Slob {
+        fn get(&self, key: &str) -> Option<(&str, String)> {
+            let (_, bin_index, item_index, _) = self.refs.iter().find(|r| r.0 == key)?;
+            let (content_type_ids, bin) = &self.bins[*bin_index as usize];
+            let mut r = SlobReader { data: bin, pos: 0 };
+            let positions = r.item_list(content_type_ids.len(), 4);
+            r.pos = positions[*item_index as usize];
+            let len = r.u32() as usize;
+            let content = String::from_utf8(r.bytes(len).to_vec()).expect("Unexpected");
+            let content_type_id = content_type_ids[*item_index as usize] as usize;
+
+            Some((&self.content_types[content_type_id], content))
+        }
+    }
+
+    #[test]
+    fn slob_round_trip_test() {
+        let slob = read_slob(&create_slob());
+
+        assert_eq!(slob.blob_count, ARTICLES.len() as u32 + 1);
+        assert_eq!(
+            slob.tags[0],
+            (
+                "label".to_string(),
+                "Digital Pāli Tools Dictionary (DPD)".to_string()
+            )
+        );
+        for (word, synonyms, html) in ARTICLES {
+            let (content_type, article) = slob.get(word).expect("Unexpected");
+            assert_eq!(content_type, HTML_CONTENT_TYPE);
+            assert_eq!(article, make_article(html));
+            assert!(article.contains("<link rel=\"stylesheet\" href=\"~/css/style.css\""));
+            for synonym in synonyms.iter().filter(|s| **s != "abala") {
+                assert_eq!(slob.get(synonym).expect("Unexpected").1, article);
+            }
+        }
+        let (content_type, css) = slob.get(CSS_KEY).expect("Unexpected");
+        assert_eq!(content_type, CSS_CONTENT_TYPE);
+        assert_eq!(
+            css,
+            create_stylesheet(&create_dict_info(&OutputFormat(
+                &crate::slob::SlobGenerator
+            )))
+            .expect("Unexpected")
+        );
+        assert_eq!(slob.refs.iter().filter(|r| r.0 == "abala").count(), 2);
+    }
+
+    #[test]
+    fn refs_are_sorted_test() {
+        let slob = read_slob(&create_slob());
+        let collator = create_collator().expect("Unexpected");
+
+        let keys: Vec<&str> = slob.refs.iter().map(|r| r.0.as_str()).collect();
+
+        assert!(keys
+            .windows(2)
+            .all(|w| collator.compare(w[0], w[1]) != Ordering::Greater));
+        insta::assert_snapshot!(keys.join("\n"));
+    }
+
+    #[test]
+    fn make_article_test() {
+        let article = make_article(ARTICLES[0].2);
+
+        assert_eq!(article, "<html><head><meta charset=\"utf-8\"/><link rel=\"stylesheet\" href=\"~/css/style.css\" type=\"text/css\"/></head><body>\n<a href=\"%E2%88%9Abal\">√bal</a> <a href=\"bal%20%28derivation%29\">bal</a></body></html>");
+    }
+
+    #[test_case("<html><body>x</body></html>", "<html><head><meta charset=\"utf-8\"/><link rel=\"stylesheet\" href=\"~/css/style.css\" type=\"text/css\"/></head><body>x</body></html>")]
+    #[test_case("<html lang=\"en\"><body>x</body></html>", "<html lang=\"en\"><head><meta charset=\"utf-8\"/><link rel=\"stylesheet\" href=\"~/css/style.css\" type=\"text/css\"/></head><body>x</body></html>")]
+    #[test_case("<p>x</p>", "<head><meta charset=\"utf-8\"/><link rel=\"stylesheet\" href=\"~/css/style.css\" type=\"text/css\"/></head><p>x</p>")]
+    fn make_article_without_head_test(article: &str, expected: &str) {
+        assert_eq!(make_article(article), expected);
+    }
+
+    #[test]
+    fn bins_are_split_test() {
+        let mut store = SlobStore::default();
+
+        let locations: Vec<(u32, u16)> = (0..5)
+            .map(|_| store.add(0, vec![0u8; MAX_BIN_SIZE / 2]))
+            .collect();
+
+        assert_eq!(locations, vec![(0, 0), (0, 1), (1, 0), (1, 1), (2, 0)]);
+        assert_eq!(store.blob_count, 5);
+    }
+}
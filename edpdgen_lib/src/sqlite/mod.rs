@@ -0,0 +1,88 @@
+use crate::input::create_csv_reader;
+use crate::input::dpd::DpdPaliWord;
+use crate::input::input_format::InputFormat;
+use crate::output::output_generator::OutputGenerator;
+use crate::roots::load_root_families;
+use crate::{create_base_path, DictionaryFiles, DictionaryInfo};
+use pls_core_extras::inflection_generator::InflectionGenerator;
+use pls_core_extras::logger::PlsLogger;
+use std::path::Path;
+
+mod output_generators;
+
+pub struct SqliteGenerator;
+
+impl OutputGenerator for SqliteGenerator {
+    fn name(&self) -> &'static str {
+        "sqlite"
+    }
+
+    fn description(&self) -> &'static str {
+        "SQLite database with an FTS5 index over the meanings."
+    }
+
+    fn input_formats(&self) -> &'static [InputFormat] {
+        &[InputFormat::Dpd]
+    }
+
+    fn options(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    fn build_files(
+        &self,
+        dict_info: &DictionaryInfo,
+        input_data_path: &Path,
+        igen: &dyn InflectionGenerator,
+        logger: &dyn PlsLogger,
+    ) -> Result<DictionaryFiles, String> {
+        run_for_dpd(dict_info, input_data_path, igen, logger)
+    }
+}
+
+fn load_words(input_data_path: &Path, logger: &dyn PlsLogger) -> Result<Vec<DpdPaliWord>, String> {
+    let rdr = create_csv_reader(input_data_path, logger)?;
+
+    let words: Vec<DpdPaliWord> = rdr
+        .into_deserialize::<DpdPaliWord>()
+        .enumerate()
+        .filter_map(|(i, r)| match r {
+            Ok(w) => Some(w),
+            Err(e) => {
+                logger.error(&format!(
+                    "Unable to deserialize record #{}. Error: {}.",
+                    i, e
+                ));
+                None
+            }
+        })
+        .collect();
+
+    logger.info(&format!(
+        "... done loading {} words from {:?}.",
+        words.len(),
+        &input_data_path
+    ));
+    Ok(words)
+}
+
+///
+/// The database is built next to the other output files and moved into place when written.
+///
+fn run_for_dpd(
+    dict_info: &DictionaryInfo,
+    input_data_path: &Path,
+    igen: &dyn InflectionGenerator,
+    logger: &dyn PlsLogger,
+) -> Result<DictionaryFiles, String> {
+    let words = load_words(input_data_path, logger)?;
+    let root_families = load_root_families(input_data_path, logger)?;
+    let db_path = create_base_path(
+        input_data_path,
+        dict_info.output_folder,
+        dict_info.short_name,
+    )?
+    .with_extension("sqlite.part");
+
+    output_generators::create_dictionary(dict_info, &words, &root_families, igen, &db_path, logger)
+}
@@ -0,0 +1,391 @@
+use crate::input::dpd::DpdPaliWord;
+use crate::input::{make_group_id, make_sort_key};
+use crate::roots::RootFamily;
+use crate::{DictionaryData, DictionaryFile, DictionaryFiles, DictionaryInfo};
+use itertools::Itertools;
+use pls_core_extras::inflection_generator::InflectionGenerator;
+use pls_core_extras::logger::PlsLogger;
+use rusqlite::types::ToSql;
+use rusqlite::{params, Connection, Transaction};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+const SCHEMA: &str = "
+    CREATE TABLE info (
+        key TEXT PRIMARY KEY,
+        value TEXT NOT NULL
+    );
+    CREATE TABLE roots (
+        id INTEGER PRIMARY KEY,
+        root TEXT NOT NULL UNIQUE,
+        bare_root TEXT NOT NULL,
+        meaning TEXT NOT NULL,
+        v TEXT NOT NULL,
+        grp TEXT NOT NULL,
+        sgn TEXT NOT NULL
+    );
+    CREATE TABLE inflections (
+        form TEXT NOT NULL,
+        word_id INTEGER NOT NULL REFERENCES words(id),
+        PRIMARY KEY (form, word_id)
+    ) WITHOUT ROWID;
+    CREATE TABLE cross_references (
+        word_id INTEGER NOT NULL REFERENCES words(id),
+        kind TEXT NOT NULL,
+        target TEXT NOT NULL
+    );
+    CREATE VIRTUAL TABLE meanings USING fts5(
+        meaning,
+        literal_meaning,
+        tokenize = 'unicode61 remove_diacritics 2'
+    );
+";
+
+const INDICES: &str = "
+    CREATE UNIQUE INDEX words_pāli1 ON words(\"pāli1\");
+    CREATE INDEX words_headword ON words(headword);
+    CREATE INDEX words_root_id ON words(root_id);
+    CREATE INDEX inflections_word_id ON inflections(word_id);
+    CREATE INDEX cross_references_word_id ON cross_references(word_id);
+    CREATE INDEX cross_references_target ON cross_references(target);
+";
+
+///
+/// An sql friendly name for a column of the input, e.g. 'meaning_in_context' for
+/// 'Meaning IN CONTEXT'.
+///
+fn make_column_name(name: &str) -> String {
+    name.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|p| !p.is_empty())
+        .join("_")
+}
+
+fn to_columns(word: &DpdPaliWord) -> Result<Vec<(String, String)>, String> {
+    match serde_json::to_value(word).map_err(|e| e.to_string())? {
+        serde_json::Value::Object(map) => Ok(map
+            .into_iter()
+            .map(|(k, v)| (k, v.as_str().unwrap_or_default().to_string()))
+            .collect()),
+        _ => Err("Words are expected to serialize to a map".to_string()),
+    }
+}
+
+///
+/// The kind and target of the references to other words, which are comma separated.
+///
+fn cross_references(word: &DpdPaliWord) -> impl Iterator<Item = (&'static str, &str)> {
+    vec![
+        ("derived_from", &word.derived_from),
+        ("synonym", &word.synonyms),
+        ("antonym", &word.antonyms),
+        ("variant", &word.variant),
+    ]
+    .into_iter()
+    .flat_map(|(kind, targets)| {
+        targets
+            .split(&[',', ';'][..])
+            .map(|t| t.trim())
+            .filter(|t| !t.is_empty())
+            .map(move |t| (kind, t))
+    })
+}
+
+fn write_info(tx: &Transaction, dict_info: &DictionaryInfo) -> Result<(), String> {
+    let info = vec![
+        ("name", dict_info.name),
+        ("short_name", dict_info.short_name),
+        ("description", dict_info.description),
+        ("author", dict_info.author),
+        ("version", dict_info.version),
+        ("time_stamp", dict_info.time_stamp),
+    ];
+
+    let mut stmt = tx
+        .prepare("INSERT INTO info (key, value) VALUES (?1, ?2)")
+        .map_err(|e| e.to_string())?;
+    for (key, value) in info {
+        stmt.execute(params![key, value])
+            .map_err(|e| e.to_string())?;
+    }
+
+    Ok(())
+}
+
+///
+/// Returns the id of the root of each word.
+///
+fn write_roots(
+    tx: &Transaction,
+    root_families: &[RootFamily],
+) -> Result<HashMap<String, i64>, String> {
+    let mut stmt = tx
+        .prepare(
+            "INSERT INTO roots (id, root, bare_root, meaning, v, grp, sgn) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        )
+        .map_err(|e| e.to_string())?;
+
+    let mut root_ids: HashMap<String, i64> = HashMap::new();
+    for (i, rf) in root_families.iter().enumerate() {
+        let id = i as i64 + 1;
+        stmt.execute(params![
+            id,
+            rf.root,
+            rf.bare_root(),
+            rf.meaning,
+            rf.v,
+            rf.grp,
+            rf.sgn
+        ])
+        .map_err(|e| e.to_string())?;
+
+        for w in rf.families.iter().flat_map(|f| f.words.iter()) {
+            root_ids.insert(w.pali1.clone(), id);
+        }
+    }
+
+    Ok(root_ids)
+}
+
+fn write_words(
+    tx: &Transaction,
+    words: &[DpdPaliWord],
+    root_ids: &HashMap<String, i64>,
+    igen: &dyn InflectionGenerator,
+    logger: &dyn PlsLogger,
+) -> Result<(), String> {
+    let words: Vec<&DpdPaliWord> = words
+        .iter()
+        .sorted_by_cached_key(|w| make_sort_key(&w.pali1))
+        .collect();
+    let column_names: Vec<String> = match words.first() {
+        Some(w) => to_columns(w)?.into_iter().map(|(k, _)| k).collect(),
+        None => vec![],
+    };
+
+    tx.execute_batch(&format!(
+        "CREATE TABLE words (
+            id INTEGER PRIMARY KEY,
+            headword TEXT NOT NULL,
+            sort_key TEXT NOT NULL,
+            root_id INTEGER REFERENCES roots(id){}
+        );",
+        column_names
+            .iter()
+            .map(|c| format!(",\n            \"{}\" TEXT NOT NULL", make_column_name(c)))
+            .join("")
+    ))
+    .map_err(|e| e.to_string())?;
+
+    let mut word_stmt = tx
+        .prepare(&format!(
+            "INSERT INTO words (id, headword, sort_key, root_id{}) VALUES (?1, ?2, ?3, ?4{})",
+            column_names
+                .iter()
+                .map(|c| format!(", \"{}\"", make_column_name(c)))
+                .join(""),
+            (0..column_names.len())
+                .map(|i| format!(", ?{}", i + 5))
+                .join("")
+        ))
+        .map_err(|e| e.to_string())?;
+    let mut inflection_stmt = tx
+        .prepare("INSERT OR IGNORE INTO inflections (form, word_id) VALUES (?1, ?2)")
+        .map_err(|e| e.to_string())?;
+    let mut cross_reference_stmt = tx
+        .prepare("INSERT INTO cross_references (word_id, kind, target) VALUES (?1, ?2, ?3)")
+        .map_err(|e| e.to_string())?;
+    let mut meaning_stmt = tx
+        .prepare("INSERT INTO meanings (rowid, meaning, literal_meaning) VALUES (?1, ?2, ?3)")
+        .map_err(|e| e.to_string())?;
+
+    for (i, w) in words.iter().enumerate() {
+        let id = i as i64 + 1;
+        let headword = make_group_id(&w.pali1);
+        let sort_key = make_sort_key(&w.pali1);
+        let root_id = root_ids.get(&w.pali1);
+        let columns = to_columns(w)?;
+
+        let mut values: Vec<&dyn ToSql> = vec![&id, &headword, &sort_key, &root_id];
+        values.extend(columns.iter().map(|(_, v)| v as &dyn ToSql));
+        word_stmt.execute(values).map_err(|e| e.to_string())?;
+
+        for form in std::iter::once(headword.clone()).chain(igen.generate_all_inflections(&w.pali1))
+        {
+            inflection_stmt
+                .execute(params![form, id])
+                .map_err(|e| e.to_string())?;
+        }
+
+        for (kind, target) in cross_references(w) {
+            cross_reference_stmt
+                .execute(params![id, kind, target])
+                .map_err(|e| e.to_string())?;
+        }
+
+        let meaning = if w.in_english.is_empty() {
+            &w.buddhadatta
+        } else {
+            &w.in_english
+        };
+        meaning_stmt
+            .execute(params![id, meaning, w.literal_meaning])
+            .map_err(|e| e.to_string())?;
+
+        if i % 1_000 == 0 && i != 0 {
+            logger.info(&format!(
+                "... written {:05} words, ending with '{}'.",
+                i, w.pali1
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+///
+/// Writes the words with all their columns, inflections, roots and cross references into
+/// normalised tables and an FTS5 table over the meanings, whose rowid is the id of the word:
+///
+/// SELECT words.* FROM meanings JOIN words ON words.id = meanings.rowid WHERE meanings MATCH 'weak';
+/// SELECT words.* FROM inflections JOIN words ON words.id = inflections.word_id WHERE form = 'abalo';
+///
+fn create_database(
+    conn: &mut Connection,
+    dict_info: &DictionaryInfo,
+    words: &[DpdPaliWord],
+    root_families: &[RootFamily],
+    igen: &dyn InflectionGenerator,
+    logger: &dyn PlsLogger,
+) -> Result<(), String> {
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    tx.execute_batch(SCHEMA).map_err(|e| e.to_string())?;
+    write_info(&tx, dict_info)?;
+    let root_ids = write_roots(&tx, root_families)?;
+    write_words(&tx, words, &root_ids, igen, logger)?;
+    tx.execute_batch(INDICES).map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())?;
+
+    logger.info(&format!(
+        "... done writing {} words and {} roots.",
+        words.len(),
+        root_families.len()
+    ));
+    Ok(())
+}
+
+pub fn create_dictionary(
+    dict_info: &DictionaryInfo,
+    words: &[DpdPaliWord],
+    root_families: &[RootFamily],
+    igen: &dyn InflectionGenerator,
+    db_path: &Path,
+    logger: &dyn PlsLogger,
+) -> Result<DictionaryFiles, String> {
+    logger.info(&format!("Writing {:?}.", db_path));
+    if db_path.exists() {
+        fs::remove_file(db_path).map_err(|e| e.to_string())?;
+    }
+
+    let result = Connection::open(db_path)
+        .map_err(|e| e.to_string())
+        .and_then(|mut conn| {
+            create_database(&mut conn, dict_info, words, root_families, igen, logger)
+        });
+    if let Err(e) = result {
+        let _ = fs::remove_file(db_path);
+        return Err(e);
+    }
+    let size = fs::metadata(db_path).map_err(|e| e.to_string())?.len();
+
+    Ok(DictionaryFiles {
+        word_count: words.len(),
+        files: vec![DictionaryFile {
+            extension: "sqlite".to_string(),
+            bom: vec![],
+            data: DictionaryData::FileBacked {
+                path: db_path.to_path_buf(),
+                size,
+            },
+            can_be_empty: false,
+        }],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::create_csv_reader;
+    use crate::resolve_file_in_manifest_dir;
+    use crate::roots::load_root_families;
+    use crate::tests::{create_dict_info, TestInflectionGenerator, TestLogger};
+    use crate::OutputFormat;
+    use test_case::test_case;
+
+    fn create_test_database() -> Connection {
+        let path = resolve_file_in_manifest_dir("Pali_English_Dictionary_10_rows-full.csv")
+            .expect("must exist!");
+        let l = TestLogger::new();
+        let words: Vec<DpdPaliWord> = create_csv_reader(&path, &l)
+            .expect("Unexpected")
+            .into_deserialize::<DpdPaliWord>()
+            .map(|r| r.expect("Unexpected"))
+            .collect();
+        let root_families = load_root_families(&path, &l).expect("Unexpected");
+        let mut conn = Connection::open_in_memory().expect("Unexpected");
+
+        create_database(
+            &mut conn,
+            &create_dict_info(&OutputFormat(&crate::sqlite::SqliteGenerator)),
+            &words,
+            &root_families,
+            &TestInflectionGenerator::new(),
+            &l,
+        )
+        .expect("Unexpected");
+
+        conn
+    }
+
+    fn query(conn: &Connection, sql: &str) -> Vec<String> {
+        let mut stmt = conn.prepare(sql).expect("Unexpected");
+        let rows = stmt
+            .query_map(params![], |r| r.get::<_, String>(0))
+            .expect("Unexpected");
+
+        rows.map(|r| r.expect("Unexpected")).collect()
+    }
+
+    #[test_case("Meaning IN CONTEXT", "meaning_in_context")]
+    #[test_case("Synonyms – different word", "synonyms_different_word"; "punctuation")]
+    #[test_case("Pāli1", "pāli1")]
+    fn make_column_name_test(name: &str, expected: &str) {
+        assert_eq!(make_column_name(name), expected);
+    }
+
+    #[test]
+    fn schema_test() {
+        let conn = create_test_database();
+
+        let schema = query(
+            &conn,
+            "SELECT sql FROM sqlite_master WHERE sql IS NOT NULL AND name NOT LIKE 'meanings_%' ORDER BY type, name",
+        );
+
+        insta::assert_snapshot!(schema.join("\n"));
+    }
+
+    #[test_case("SELECT w.\"pāli1\" FROM meanings m JOIN words w ON w.id = m.rowid WHERE meanings MATCH 'weak' ORDER BY w.id", &["abala 1", "abala 2", "abalabala"]; "by meaning")]
+    #[test_case("SELECT w.\"pāli1\" FROM meanings m JOIN words w ON w.id = m.rowid WHERE meanings MATCH 'dísease' ORDER BY w.id", &["ābādha", "ābādhato"]; "by meaning without diacritics")]
+    #[test_case("SELECT w.\"pāli1\" FROM inflections i JOIN words w ON w.id = i.word_id WHERE i.form = 'abala' ORDER BY w.id", &["abala 1", "abala 2"]; "by headword")]
+    #[test_case("SELECT w.\"pāli1\" FROM inflections i JOIN words w ON w.id = i.word_id WHERE i.form = 'abala 2_1'", &["abala 2"]; "by inflection")]
+    #[test_case("SELECT w.\"pāli1\" FROM roots r JOIN words w ON w.root_id = r.id WHERE r.bare_root = 'bal' ORDER BY w.id", &["abala 1", "abala 2"]; "by root")]
+    #[test_case("SELECT c.target FROM cross_references c JOIN words w ON w.id = c.word_id WHERE w.\"pāli1\" = 'abyāpajjha' ORDER BY c.kind, c.target", &["byāpajjha", "vyābādheti", "abyābajjha", "avyāpajjha"]; "cross references")]
+    #[test_case("SELECT value FROM info WHERE key = 'version'", &["1.0.0"]; "info")]
+    fn query_test(sql: &str, expected: &[&str]) {
+        let conn = create_test_database();
+
+        assert_eq!(query(&conn, sql), expected);
+    }
+}
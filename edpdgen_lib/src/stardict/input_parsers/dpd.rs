@@ -1,7 +1,9 @@
+use crate::frequency::Frequency;
 use crate::input::dpd::DpdPaliWord;
-use crate::input::make_sort_key;
-use crate::stardict::input_parsers::{make_group_id, make_toc_id};
+use crate::input::{make_group_id, make_sort_key};
+use crate::stardict::input_parsers::make_toc_id;
 use crate::stardict::StarDictPaliWord;
+use crate::suttas::create_sutta_link;
 use pls_core_extras::inflection_generator::InflectionGenerator;
 use tera::{Context, Tera};
 
@@ -32,6 +34,10 @@ struct WordDataViewModel<'a> {
     host_url: &'a str,
     host_version: &'a str,
     inflection_table: &'a str,
+    source1_link: Option<String>,
+    source2_link: Option<String>,
+    frequency: Option<&'a Frequency>,
+    link_families: bool,
 }
 
 impl StarDictPaliWord for DpdPaliWord {
@@ -51,6 +57,10 @@ impl StarDictPaliWord for DpdPaliWord {
         make_toc_id(self.id(), dict_short_name)
     }
 
+    fn to_json(&self) -> Result<String, String> {
+        serde_json::to_string(self).map_err(|e| e.to_string())
+    }
+
     fn toc_entry(&self, dict_short_name: &str, concise: bool) -> Result<String, String> {
         let mut context = Context::new();
         context.insert("dict_short_name", dict_short_name);
@@ -73,8 +83,11 @@ impl StarDictPaliWord for DpdPaliWord {
         feedback_form_url: &str,
         host_url: &str,
         host_version: &str,
+        sutta_link_pattern: Option<&str>,
+        frequency: Option<&Frequency>,
         igen: &dyn InflectionGenerator,
         concise: bool,
+        link_families: bool,
     ) -> Result<String, String> {
         if concise {
             Ok("".to_string())
@@ -87,6 +100,10 @@ impl StarDictPaliWord for DpdPaliWord {
                 host_url,
                 host_version,
                 inflection_table: &igen.generate_inflection_table_html(&self.pali1),
+                source1_link: create_sutta_link(&self.source1, sutta_link_pattern),
+                source2_link: create_sutta_link(&self.source2, sutta_link_pattern),
+                frequency,
+                link_families,
             };
 
             let context = Context::from_serialize(&vm).map_err(|e| e.to_string())?;
@@ -165,11 +182,118 @@ mod tests {
         let word_data = recs
             .nth(rec_number)
             .map(|r| {
-                r.word_data_entry("dpd", "fb_url", "host url", "host version", &igen, concise)
-                    .expect("unexpected")
+                r.word_data_entry(
+                    "dpd",
+                    "fb_url",
+                    "host url",
+                    "host version",
+                    None,
+                    None,
+                    &igen,
+                    concise,
+                    true,
+                )
+                .expect("unexpected")
             })
             .expect("unexpected");
 
         insta::assert_snapshot!(word_data);
     }
+
+    #[test_case(0, "https://suttacentral.net/{id}", "https://suttacentral.net/sn22.59")]
+    #[test_case(2, "bword://{id}", "bword://an3.36")]
+    #[test_case(
+        10,
+        "https://suttacentral.net/{id}",
+        "https://suttacentral.net/pli-tv-bu-vb-pc11"
+    )]
+    fn word_data_sutta_links_tests(rec_number: usize, pattern: &str, expected_link: &str) {
+        let l = TestLogger::new();
+        let mut recs = load_words::<DpdPaliWord>(&get_csv_path(), &l).expect("unexpected");
+        let igen = TestInflectionGenerator::new();
+
+        let word_data = recs
+            .nth(rec_number)
+            .map(|r| {
+                r.word_data_entry(
+                    "dpd",
+                    "fb_url",
+                    "host url",
+                    "host version",
+                    Some(pattern),
+                    None,
+                    &igen,
+                    false,
+                    true,
+                )
+                .expect("unexpected")
+            })
+            .expect("unexpected");
+
+        assert!(word_data.contains(&format!("href=\"{}\"", expected_link)));
+    }
+
+    #[test]
+    fn word_data_frequency_test() {
+        let l = TestLogger::new();
+        let mut recs = load_words::<DpdPaliWord>(&get_csv_path(), &l).expect("unexpected");
+        let igen = TestInflectionGenerator::new();
+        let frequency = Frequency {
+            vinaya: 2,
+            sutta: 12,
+            abhidhamma: 3,
+            total: 17,
+        };
+
+        let word_data = recs
+            .next()
+            .map(|r| {
+                r.word_data_entry(
+                    "dpd",
+                    "fb_url",
+                    "host url",
+                    "host version",
+                    None,
+                    Some(&frequency),
+                    &igen,
+                    false,
+                    true,
+                )
+                .expect("unexpected")
+            })
+            .expect("unexpected");
+
+        assert!(word_data.contains(
+            "<tr><td>Frequency</td><td><span>17 (vin 2, sut 12, abh 3)</span></td></tr>"
+        ));
+    }
+
+    #[test]
+    fn word_data_without_family_articles_test() {
+        let l = TestLogger::new();
+        let mut recs = load_words::<DpdPaliWord>(&get_csv_path(), &l).expect("unexpected");
+        let igen = TestInflectionGenerator::new();
+
+        let word_data = recs
+            .next()
+            .map(|r| {
+                r.word_data_entry(
+                    "dpd",
+                    "fb_url",
+                    "host url",
+                    "host version",
+                    None,
+                    None,
+                    &igen,
+                    false,
+                    false,
+                )
+                .expect("unexpected")
+            })
+            .expect("unexpected");
+
+        assert!(word_data.contains("<tr><td>Root</td><td><span>√bādh<sup>"));
+        assert!(word_data.contains("<tr><td>Family</td><td><span>ā √bādh</span></td></tr>"));
+        assert!(!word_data.contains("bword://"));
+    }
 }
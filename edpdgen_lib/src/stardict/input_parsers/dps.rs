@@ -1,7 +1,9 @@
+use crate::frequency::Frequency;
 use crate::input::dps::DpsPaliWord;
-use crate::input::make_sort_key;
-use crate::stardict::input_parsers::{make_group_id, make_toc_id};
+use crate::input::{make_group_id, make_sort_key};
+use crate::stardict::input_parsers::make_toc_id;
 use crate::stardict::StarDictPaliWord;
+use crate::suttas::create_sutta_link;
 use pls_core_extras::inflection_generator::InflectionGenerator;
 use tera::{Context, Tera};
 
@@ -32,6 +34,9 @@ struct WordDataViewModel<'a> {
     host_url: &'a str,
     host_version: &'a str,
     inflection_table: &'a str,
+    source1_link: Option<String>,
+    source2_link: Option<String>,
+    frequency: Option<&'a Frequency>,
 }
 
 impl StarDictPaliWord for DpsPaliWord {
@@ -51,6 +56,10 @@ impl StarDictPaliWord for DpsPaliWord {
         make_toc_id(self.id(), dict_short_name)
     }
 
+    fn to_json(&self) -> Result<String, String> {
+        serde_json::to_string(self).map_err(|e| e.to_string())
+    }
+
     fn toc_entry(&self, dict_short_name: &str, concise: bool) -> Result<String, String> {
         let mut context = Context::new();
         context.insert("toc_id", &self.toc_id(dict_short_name));
@@ -71,8 +80,11 @@ impl StarDictPaliWord for DpsPaliWord {
         feedback_form_url: &str,
         host_url: &str,
         host_version: &str,
+        sutta_link_pattern: Option<&str>,
+        frequency: Option<&Frequency>,
         igen: &dyn InflectionGenerator,
         concise: bool,
+        _link_families: bool,
     ) -> Result<String, String> {
         if concise {
             Ok("".to_string())
@@ -85,6 +97,9 @@ impl StarDictPaliWord for DpsPaliWord {
                 host_url,
                 host_version,
                 inflection_table: &igen.generate_inflection_table_html(&self.pali),
+                source1_link: create_sutta_link(&self.source1, sutta_link_pattern),
+                source2_link: create_sutta_link(&self.source2, sutta_link_pattern),
+                frequency,
             };
 
             let context = Context::from_serialize(&vm).map_err(|e| e.to_string())?;
@@ -145,8 +160,18 @@ mod tests {
         let word_data = recs
             .nth(rec_number)
             .map(|r| {
-                r.word_data_entry("dps", "fb_url", "host url", "host version", &igen, concise)
-                    .expect("unexpected")
+                r.word_data_entry(
+                    "dps",
+                    "fb_url",
+                    "host url",
+                    "host version",
+                    None,
+                    None,
+                    &igen,
+                    concise,
+                    false,
+                )
+                .expect("unexpected")
             })
             .expect("unexpected");
 
@@ -1,8 +1,6 @@
 use crate::input::create_csv_reader;
-use crate::input::PALI1_CRACKER;
 use crate::stardict::StarDictPaliWord;
 use pls_core_extras::logger::PlsLogger;
-use regex::Captures;
 use std::path::Path;
 
 pub mod dpd;
@@ -32,11 +30,15 @@ pub fn load_words<'a, T: 'a + serde::de::DeserializeOwned + StarDictPaliWord>(
     Ok(words)
 }
 
-fn make_group_id(id: &str) -> String {
-    let gid = PALI1_CRACKER.replace(id, |caps: &Captures| caps[1].to_string());
-
-    gid.into_owned()
-}
+///
+/// All word templates, so that cached renders are invalidated when any of them changes.
+///
+pub const WORD_TEMPLATES: &[&str] = &[
+    include_str!("templates/dpd_toc_summary.html"),
+    include_str!("templates/dpd_word_data.html"),
+    include_str!("templates/dps_toc_summary.html"),
+    include_str!("templates/dps_word_data.html"),
+];
 
 fn make_toc_id(id: &str, dict_short_name: &str) -> String {
     format!("{}_{}", id.replace(" ", "_"), dict_short_name)
@@ -46,6 +48,7 @@ fn make_toc_id(id: &str, dict_short_name: &str) -> String {
 mod tests {
     use super::*;
     use crate::input::dpd::DpdPaliWord;
+    use crate::input::make_group_id;
     use crate::resolve_file_in_manifest_dir;
     use crate::tests::TestLogger;
     use std::path::PathBuf;
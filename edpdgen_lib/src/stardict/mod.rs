@@ -1,7 +1,9 @@
+use crate::frequency::Frequency;
 use crate::input::dpd::DpdPaliWord;
 use crate::input::dps::DpsPaliWord;
 use crate::input::input_format::InputFormat;
-use crate::{DictionaryBuilder, DictionaryFile, DictionaryInfo};
+use crate::output::output_generator::OutputGenerator;
+use crate::{DictionaryData, DictionaryFiles, DictionaryInfo};
 use pls_core_extras::inflection_generator::InflectionGenerator;
 use pls_core_extras::logger::PlsLogger;
 use std::path::Path;
@@ -9,42 +11,53 @@ use std::path::Path;
 mod input_parsers;
 mod output_generators;
 
-pub struct StarDict<'a> {
-    dict_info: &'a DictionaryInfo<'a>,
-    input_data_path: &'a Path,
-    igen: &'a dyn InflectionGenerator,
-    logger: &'a dyn PlsLogger,
-}
+pub(crate) use output_generators::{
+    create_style_context, create_stylesheet, ArticleReader, IdxEntry, DECONSTRUCTION_SUFFIX,
+    DERIVATION_SUFFIX,
+};
 
-impl<'a> DictionaryBuilder<'a> for StarDict<'a> {
-    fn new(
-        dict_info: &'a DictionaryInfo,
-        input_data_path: &'a Path,
-        igen: &'a dyn InflectionGenerator,
-        logger: &'a dyn PlsLogger,
-    ) -> Self {
-        StarDict {
-            dict_info,
-            input_data_path,
-            igen,
-            logger,
-        }
+pub struct StarDictGenerator;
+
+impl OutputGenerator for StarDictGenerator {
+    fn name(&self) -> &'static str {
+        "stardict"
+    }
+
+    fn description(&self) -> &'static str {
+        "StarDict and GoldenDict formats."
     }
 
-    fn build_files(&self) -> Result<Vec<DictionaryFile>, String> {
-        match self.dict_info.input_format {
-            InputFormat::Dpd => run_for_ods_type::<DpdPaliWord>(
-                self.dict_info,
-                self.input_data_path,
-                self.igen,
-                self.logger,
-            ),
-            InputFormat::Dps => run_for_ods_type::<DpsPaliWord>(
-                self.dict_info,
-                self.input_data_path,
-                self.igen,
-                self.logger,
-            ),
+    fn input_formats(&self) -> &'static [InputFormat] {
+        &[InputFormat::Dpd, InputFormat::Dps]
+    }
+
+    fn options(&self) -> &'static [&'static str] {
+        &[
+            "concise",
+            "collision-report",
+            "prefer-headwords",
+            "deconstruct",
+            "sutta-links",
+            "frequency",
+            "min-frequency",
+            "render-cache",
+        ]
+    }
+
+    fn build_files(
+        &self,
+        dict_info: &DictionaryInfo,
+        input_data_path: &Path,
+        igen: &dyn InflectionGenerator,
+        logger: &dyn PlsLogger,
+    ) -> Result<DictionaryFiles, String> {
+        match dict_info.input_format {
+            InputFormat::Dpd => {
+                run_for_ods_type::<DpdPaliWord>(dict_info, input_data_path, igen, logger)
+            }
+            InputFormat::Dps => {
+                run_for_ods_type::<DpsPaliWord>(dict_info, input_data_path, igen, logger)
+            }
         }
     }
 }
@@ -54,15 +67,20 @@ pub trait StarDictPaliWord {
     fn sort_key(&self) -> String;
     fn group_id(&self) -> String;
     fn toc_id(&self, dict_short_name: &str) -> String;
+    fn to_json(&self) -> Result<String, String>;
     fn toc_entry(&self, dict_short_name: &str, concise: bool) -> Result<String, String>;
+    #[allow(clippy::too_many_arguments)]
     fn word_data_entry(
         &self,
         dict_short_name: &str,
         feedback_form_url: &str,
         host_url: &str,
         host_version: &str,
+        sutta_link_pattern: Option<&str>,
+        frequency: Option<&Frequency>,
         igen: &dyn InflectionGenerator,
         concise: bool,
+        link_families: bool,
     ) -> Result<String, String>;
 }
 
@@ -71,9 +89,23 @@ pub fn run_for_ods_type<'a, T: 'a + serde::de::DeserializeOwned + StarDictPaliWo
     input_data_path: &Path,
     igen: &dyn InflectionGenerator,
     logger: &dyn PlsLogger,
-) -> Result<Vec<DictionaryFile>, String> {
+) -> Result<DictionaryFiles, String> {
     let words = input_parsers::load_words::<T>(input_data_path, logger)?;
     let sd_files = output_generators::create_dictionary(dict_info, words, igen, logger)?;
 
     Ok(sd_files)
 }
+
+///
+/// The rendered articles and their idx entries, for formats that share the StarDict html.
+///
+pub(crate) fn create_articles<'a, T: 'a + serde::de::DeserializeOwned + StarDictPaliWord>(
+    dict_info: &DictionaryInfo,
+    input_data_path: &Path,
+    igen: &dyn InflectionGenerator,
+    logger: &dyn PlsLogger,
+) -> Result<(DictionaryData, Vec<IdxEntry>), String> {
+    let words = input_parsers::load_words::<T>(input_data_path, logger)?;
+
+    output_generators::create_articles(dict_info, words, igen, logger)
+}
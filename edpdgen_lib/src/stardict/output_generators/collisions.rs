@@ -0,0 +1,135 @@
+use crate::stardict::output_generators::{IdxEntry, DECONSTRUCTION_SUFFIX, DERIVATION_SUFFIX};
+use pls_core_extras::logger::PlsLogger;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+
+///
+/// An inflected form that either leads to more than one word group or is itself the
+/// headword of another group, i.e. a lookup for it may land on a surprising entry.
+///
+#[derive(Debug, PartialEq)]
+pub struct Collision<'a> {
+    pub form: &'a str,
+    pub headword: Option<&'a str>,
+    pub groups: Vec<&'a str>,
+}
+
+///
+/// Root family, derivation tree and deconstruction articles are not word groups.
+///
+fn is_word_group(e: &IdxEntry) -> bool {
+    !e.word.starts_with('√')
+        && !e.word.ends_with(DERIVATION_SUFFIX)
+        && !e.word.ends_with(DECONSTRUCTION_SUFFIX)
+}
+
+pub fn find_collisions(idx_entries: &[IdxEntry]) -> Vec<Collision<'_>> {
+    let headwords: BTreeSet<&str> = idx_entries
+        .iter()
+        .filter(|e| is_word_group(e))
+        .map(|e| e.word.as_str())
+        .collect();
+
+    let mut forms: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+    for e in idx_entries.iter().filter(|e| is_word_group(e)) {
+        for sw in &e.synonym_words {
+            forms.entry(sw).or_default().insert(&e.word);
+        }
+    }
+
+    forms
+        .into_iter()
+        .filter_map(|(form, groups)| {
+            let headword = headwords.get(form).copied();
+            let is_other_headword = headword.iter().any(|h| groups.iter().any(|g| g != h));
+            if groups.len() > 1 || is_other_headword {
+                Some(Collision {
+                    form,
+                    headword,
+                    groups: groups.into_iter().collect(),
+                })
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+pub fn write_collision_report(
+    report_path: &Path,
+    collisions: &[Collision],
+    logger: &dyn PlsLogger,
+) -> Result<(), String> {
+    logger.info(&format!("Writing {:?}.", report_path));
+
+    let report = collisions
+        .iter()
+        .fold("form\theadword\tgroups\n".to_string(), |mut acc, c| {
+            acc.push_str(&format!(
+                "{}\t{}\t{}\n",
+                c.form,
+                c.headword.unwrap_or(""),
+                c.groups.join(", ")
+            ));
+            acc
+        });
+
+    fs::write(report_path, report).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_idx_entry(word: &str, synonym_words: &[&str]) -> IdxEntry {
+        IdxEntry {
+            word: word.to_string(),
+            data_offset: 0,
+            data_size: 0,
+            synonym_words: synonym_words.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn find_collisions_test() {
+        let idx_entries = vec![
+            create_idx_entry("buddha", &["buddho", "buddhe", "buddhā"]),
+            create_idx_entry("buddhe", &[]),
+            create_idx_entry("dhamma", &["dhammo", "dhamme"]),
+            create_idx_entry("dhammā", &["dhammo"]),
+        ];
+
+        let collisions = find_collisions(&idx_entries);
+
+        assert_eq!(
+            collisions,
+            vec![
+                Collision {
+                    form: "buddhe",
+                    headword: Some("buddhe"),
+                    groups: vec!["buddha"],
+                },
+                Collision {
+                    form: "dhammo",
+                    headword: None,
+                    groups: vec!["dhamma", "dhammā"],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn find_collisions_ignores_generated_articles_test() {
+        let idx_entries = vec![
+            create_idx_entry("bala", &["bal", "balo"]),
+            create_idx_entry("√bal", &["bal"]),
+            create_idx_entry("bal (derivation)", &["bal"]),
+            create_idx_entry("balo (deconstruction)", &["balo"]),
+        ];
+
+        let collisions = find_collisions(&idx_entries);
+
+        assert_eq!(collisions, vec![]);
+    }
+}
@@ -1,31 +1,71 @@
+use crate::deconstruction::{load_compound_members, load_forms, Deconstructor, Split};
+use crate::derivations::{load_derivation_trees, DerivationTree};
+use crate::frequency::{load_frequency_table, with_frequencies, Frequency};
+use crate::inflections::INFLECTION_DATA;
+use crate::input::input_format::InputFormat;
+use crate::render_cache::{data_file_version, CachedRender, RenderCache};
+use crate::roots::{load_root_families, RootFamily};
+use crate::stardict::input_parsers::WORD_TEMPLATES;
 use crate::stardict::StarDictPaliWord;
-use crate::{glib, DictionaryFile, DictionaryInfo};
+use crate::suttas::SUTTA_DATA;
+use crate::{
+    create_base_path, glib, DictionaryData, DictionaryFile, DictionaryFiles, DictionaryInfo,
+};
 use itertools::Itertools;
 use pls_core_extras::inflection_generator::InflectionGenerator;
 use pls_core_extras::logger::PlsLogger;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::fs::File;
+use std::io::{BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
 use tera::{Context, Tera};
 
+mod collisions;
+
 lazy_static! {
     static ref TEMPLATES: Tera = {
         let mut tera = Tera::default();
-        tera.add_raw_templates(vec![(
-            "word_group",
-            include_str!("templates/word_group.html"),
-        )])
+        tera.add_raw_templates(vec![
+            ("word_group_style", include_str!("templates/word_group.css")),
+            ("word_group", include_str!("templates/word_group.html")),
+        ])
         .expect("Unexpected failure adding template");
         tera.add_raw_templates(vec![("ifo_file", include_str!("templates/ifo_file.txt"))])
             .expect("Unexpected failure adding template");
+        tera.add_raw_templates(vec![(
+            "root_family",
+            include_str!("templates/root_family.html"),
+        )])
+        .expect("Unexpected failure adding template");
+        tera.add_raw_templates(vec![
+            (
+                "derivation_macros",
+                include_str!("templates/derivation_macros.html"),
+            ),
+            (
+                "derivation_tree",
+                include_str!("templates/derivation_tree.html"),
+            ),
+        ])
+        .expect("Unexpected failure adding template");
+        tera.add_raw_templates(vec![(
+            "deconstruction",
+            include_str!("templates/deconstruction.html"),
+        )])
+        .expect("Unexpected failure adding template");
         tera.autoescape_on(vec!["html"]);
         tera
     };
 }
 
 #[derive(Debug, Serialize)]
-struct IdxEntry {
-    word: String,
-    data_offset: i32,
-    data_size: i32,
-    synonym_words: Vec<String>,
+pub(crate) struct IdxEntry {
+    pub word: String,
+    pub data_offset: i32,
+    pub data_size: i32,
+    pub synonym_words: Vec<String>,
 }
 
 #[derive(Serialize)]
@@ -37,6 +77,13 @@ struct WordGroupViewModel<'a> {
     descriptions: &'a [String],
 }
 
+#[derive(Serialize)]
+struct StyleViewModel<'a> {
+    dict_short_name: &'a str,
+    links_color: &'a str,
+    headings_color: &'a str,
+}
+
 #[derive(Serialize)]
 struct IfoViewModel<'a> {
     name: &'a str,
@@ -65,40 +112,37 @@ fn log_return_error(
 
 fn get_ids_and_html_for_word_group(
     dict_info: &DictionaryInfo,
-    words: impl Iterator<Item = impl StarDictPaliWord>,
+    words: impl Iterator<Item = (impl StarDictPaliWord, Option<Frequency>)>,
     igen: &dyn InflectionGenerator,
     logger: &dyn PlsLogger,
 ) -> Result<(Vec<String>, String), String> {
-    let mut word_info: Vec<(String, String, String, String)> = words
-        .map(|w| {
-            (
-                w.sort_key(),
-                w.id().to_string(),
-                w.toc_entry(dict_info.short_name, dict_info.concise)
-                    .unwrap_or_else(|e| log_return_error(&w, "table of contents", e, logger)),
-                w.word_data_entry(
-                    dict_info.short_name,
-                    dict_info.feedback_form_url,
-                    dict_info.host_url,
-                    dict_info.host_version,
-                    igen,
-                    dict_info.concise,
-                )
-                .unwrap_or_else(|e| log_return_error(&w, "word data", e, logger)),
+    let mut words: Vec<_> = words.collect();
+    words.sort_by_cached_key(|(w, _)| w.sort_key());
+
+    let mut ids = Vec::with_capacity(words.len());
+    let mut toc_entries = Vec::with_capacity(words.len());
+    let mut descriptions = Vec::with_capacity(words.len());
+    for (w, frequency) in words {
+        toc_entries.push(
+            w.toc_entry(dict_info.short_name, dict_info.concise)
+                .unwrap_or_else(|e| log_return_error(&w, "table of contents", e, logger)),
+        );
+        descriptions.push(
+            w.word_data_entry(
+                dict_info.short_name,
+                dict_info.feedback_form_url,
+                dict_info.host_url,
+                dict_info.host_version,
+                dict_info.sutta_link_pattern,
+                frequency.as_ref(),
+                igen,
+                dict_info.concise,
+                has_family_articles(dict_info),
             )
-        })
-        .collect();
-    word_info.sort_by(|a, b| a.0.cmp(&b.0));
-
-    let (ids, toc_entries, descriptions) =
-        word_info
-            .into_iter()
-            .fold((Vec::new(), Vec::new(), Vec::new()), |mut acc, e| {
-                acc.0.push(e.1);
-                acc.1.push(e.2);
-                acc.2.push(e.3);
-                acc
-            });
+            .unwrap_or_else(|e| log_return_error(&w, "word data", e, logger)),
+        );
+        ids.push(w.id().to_string());
+    }
 
     let vm = WordGroupViewModel {
         dict_short_name: dict_info.short_name,
@@ -116,38 +160,82 @@ fn get_ids_and_html_for_word_group(
     Ok((ids, html))
 }
 
-type DictData = (Vec<u8>, Vec<IdxEntry>);
+fn render_word_group(
+    dict_info: &DictionaryInfo,
+    words: impl Iterator<Item = (impl StarDictPaliWord, Option<Frequency>)>,
+    igen: &dyn InflectionGenerator,
+    logger: &dyn PlsLogger,
+) -> Result<CachedRender, String> {
+    let (ids, html) = get_ids_and_html_for_word_group(dict_info, words, igen, logger)?;
 
+    let mut inflections: Vec<String> = ids
+        .iter()
+        .flat_map(|id| igen.generate_all_inflections(id))
+        .unique()
+        .collect();
+    inflections.shrink_to_fit();
+
+    Ok(CachedRender { html, inflections })
+}
+
+fn render_word_group_cached(
+    dict_info: &DictionaryInfo,
+    words: impl Iterator<Item = (impl StarDictPaliWord, Option<Frequency>)>,
+    igen: &dyn InflectionGenerator,
+    render_cache: &RenderCache,
+    logger: &dyn PlsLogger,
+) -> Result<CachedRender, String> {
+    let words: Vec<_> = words.collect();
+    let word_jsons = words
+        .iter()
+        .map(|(w, _)| w.to_json())
+        .collect::<Result<Vec<String>, String>>()?;
+    let key = render_cache.make_key(&word_jsons);
+
+    match render_cache.get(&key)? {
+        Some(render) => Ok(render),
+        None => {
+            let render = render_word_group(dict_info, words.into_iter(), igen, logger)?;
+            render_cache.put(&key, &render)?;
+            Ok(render)
+        }
+    }
+}
+
+///
+/// Renders each word group and streams its html straight into `dict_writer`, so
+/// only the (compact) idx entries are held in memory.
+///
 fn create_dict(
     dict_info: &DictionaryInfo,
-    words: impl Iterator<Item = impl StarDictPaliWord>,
+    words: impl Iterator<Item = (impl StarDictPaliWord, Option<Frequency>)>,
     igen: &dyn InflectionGenerator,
+    render_cache: Option<&RenderCache>,
+    dict_writer: &mut impl Write,
     logger: &dyn PlsLogger,
-) -> Result<DictData, String> {
+) -> Result<Vec<IdxEntry>, String> {
     logger.info(&"Creating dict entries.".to_string());
-    let word_groups = words.group_by(|pw| pw.group_id());
+    let word_groups = words.group_by(|(pw, _)| pw.group_id());
 
-    let mut dict_buffer: Vec<u8> = Vec::new();
+    let mut data_offset: i32 = 0;
     let mut idx_words: Vec<IdxEntry> = Vec::new();
     for (n, (key, word_group)) in (&word_groups).into_iter().enumerate() {
-        let (ids, html_str) = get_ids_and_html_for_word_group(dict_info, word_group, igen, logger)?;
+        let render = match render_cache {
+            Some(rc) => render_word_group_cached(dict_info, word_group, igen, rc, logger)?,
+            None => render_word_group(dict_info, word_group, igen, logger)?,
+        };
 
-        let synonym_words: Vec<String> = ids
-            .into_iter()
-            .flat_map(|id| igen.generate_all_inflections(&id))
-            .collect();
-        let mut html_bytes = html_str.into_bytes();
+        let html_bytes = render.html.as_bytes();
+        dict_writer
+            .write_all(html_bytes)
+            .map_err(|e| e.to_string())?;
         idx_words.push(IdxEntry {
             word: key,
-            data_offset: if n == 0 {
-                0
-            } else {
-                idx_words[n - 1].data_offset + idx_words[n - 1].data_size
-            },
+            data_offset,
             data_size: html_bytes.len() as i32,
-            synonym_words,
+            synonym_words: render.inflections,
         });
-        dict_buffer.append(&mut html_bytes);
+        data_offset += html_bytes.len() as i32;
 
         if n % 1_000 == 0 && n != 0 {
             logger.info(&format!(
@@ -157,11 +245,316 @@ fn create_dict(
         }
     }
 
+    dict_writer.flush().map_err(|e| e.to_string())?;
+    idx_words.shrink_to_fit();
+
     logger.info(&format!(
         "... done creating {} dict entries.",
         idx_words.len()
     ));
-    Ok((dict_buffer, idx_words))
+    Ok(idx_words)
+}
+
+///
+/// Appends a synthetic (i.e. not from the input words) article after the word groups.
+///
+fn append_article(
+    idx_entries: &mut Vec<IdxEntry>,
+    dict_writer: &mut impl Write,
+    word: String,
+    html: &str,
+    synonym_words: Vec<String>,
+) -> Result<(), String> {
+    let data_offset = idx_entries
+        .last()
+        .map(|e| e.data_offset + e.data_size)
+        .unwrap_or(0);
+
+    dict_writer
+        .write_all(html.as_bytes())
+        .map_err(|e| e.to_string())?;
+    idx_entries.push(IdxEntry {
+        word,
+        data_offset,
+        data_size: html.len() as i32,
+        synonym_words,
+    });
+
+    Ok(())
+}
+
+///
+/// Root family and derivation tree articles are only generated for full DPD builds, the word
+/// data links to them only then.
+///
+fn has_family_articles(dict_info: &DictionaryInfo) -> bool {
+    matches!(dict_info.input_format, InputFormat::Dpd) && !dict_info.concise
+}
+
+#[derive(Serialize)]
+struct RootFamilyViewModel<'a> {
+    dict_short_name: &'a str,
+    root_family: &'a RootFamily,
+}
+
+///
+/// Appends an article for every Pāli root listing the words derived from it, linked from the
+/// Root row of those words.
+///
+fn create_root_families(
+    dict_info: &DictionaryInfo,
+    idx_entries: &mut Vec<IdxEntry>,
+    dict_writer: &mut impl Write,
+    logger: &dyn PlsLogger,
+) -> Result<(), String> {
+    logger.info("Creating root family entries.");
+    let root_families = load_root_families(Path::new(dict_info.input_data_path), logger)?;
+
+    for root_family in &root_families {
+        let vm = RootFamilyViewModel {
+            dict_short_name: dict_info.short_name,
+            root_family,
+        };
+        let context = Context::from_serialize(&vm).map_err(|e| e.to_string())?;
+        let html = TEMPLATES
+            .render("root_family", &context)
+            .map_err(|e| e.to_string())?;
+
+        append_article(
+            idx_entries,
+            dict_writer,
+            root_family.root.clone(),
+            &html,
+            vec![root_family.bare_root().to_string()],
+        )?;
+    }
+
+    dict_writer.flush().map_err(|e| e.to_string())?;
+    logger.info(&format!(
+        "... done creating {} root family entries.",
+        root_families.len()
+    ));
+    Ok(())
+}
+
+/// Distinguishes derivation tree articles from the root and word articles in the idx.
+pub const DERIVATION_SUFFIX: &str = " (derivation)";
+
+#[derive(Serialize)]
+struct DerivationTreeViewModel<'a> {
+    dict_short_name: &'a str,
+    tree: &'a DerivationTree,
+}
+
+///
+/// Appends an article for every family nesting its words under the words they derive from.
+///
+fn create_derivation_trees(
+    dict_info: &DictionaryInfo,
+    idx_entries: &mut Vec<IdxEntry>,
+    dict_writer: &mut impl Write,
+    logger: &dyn PlsLogger,
+) -> Result<(), String> {
+    logger.info("Creating derivation tree entries.");
+    let trees = load_derivation_trees(Path::new(dict_info.input_data_path), logger)?;
+
+    for tree in &trees {
+        let vm = DerivationTreeViewModel {
+            dict_short_name: dict_info.short_name,
+            tree,
+        };
+        let context = Context::from_serialize(&vm).map_err(|e| e.to_string())?;
+        let html = TEMPLATES
+            .render("derivation_tree", &context)
+            .map_err(|e| e.to_string())?;
+
+        append_article(
+            idx_entries,
+            dict_writer,
+            format!("{}{}", tree.family, DERIVATION_SUFFIX),
+            &html,
+            vec![tree.family.clone()],
+        )?;
+    }
+
+    dict_writer.flush().map_err(|e| e.to_string())?;
+    logger.info(&format!(
+        "... done creating {} derivation tree entries.",
+        trees.len()
+    ));
+    Ok(())
+}
+
+/// Distinguishes deconstruction articles from the headwords in the idx.
+pub const DECONSTRUCTION_SUFFIX: &str = " (deconstruction)";
+
+#[derive(Serialize)]
+struct DeconstructionViewModel<'a> {
+    dict_short_name: &'a str,
+    form: &'a str,
+    splits: &'a [Split],
+}
+
+///
+/// Appends an article listing the splits of every form of the given text that is neither
+/// a headword nor an inflection, with the form as synonym of the article.
+///
+fn create_deconstructions(
+    dict_info: &DictionaryInfo,
+    forms_path: &Path,
+    idx_entries: &mut Vec<IdxEntry>,
+    dict_writer: &mut impl Write,
+    logger: &dyn PlsLogger,
+) -> Result<(), String> {
+    let mut deconstructor = Deconstructor::new();
+    for e in idx_entries.iter() {
+        deconstructor.add_known(&e.word, &e.word);
+    }
+    for e in idx_entries.iter() {
+        for sw in &e.synonym_words {
+            deconstructor.add_known(sw, &e.word);
+        }
+    }
+    if let InputFormat::Dpd = dict_info.input_format {
+        for m in load_compound_members(Path::new(dict_info.input_data_path), logger)? {
+            deconstructor.add_member(&m);
+        }
+    }
+
+    let forms = load_forms(forms_path, logger)?;
+    logger.info(&format!("Deconstructing {} forms.", forms.len()));
+
+    let mut count = 0;
+    for form in forms {
+        let splits = deconstructor.deconstruct(&form);
+        if splits.is_empty() {
+            continue;
+        }
+
+        let vm = DeconstructionViewModel {
+            dict_short_name: dict_info.short_name,
+            form: &form,
+            splits: &splits,
+        };
+        let context = Context::from_serialize(&vm).map_err(|e| e.to_string())?;
+        let html = TEMPLATES
+            .render("deconstruction", &context)
+            .map_err(|e| e.to_string())?;
+
+        append_article(
+            idx_entries,
+            dict_writer,
+            format!("{}{}", form, DECONSTRUCTION_SUFFIX),
+            &html,
+            vec![form],
+        )?;
+        count += 1;
+    }
+
+    dict_writer.flush().map_err(|e| e.to_string())?;
+    logger.info(&format!("... done deconstructing {} forms.", count));
+    Ok(())
+}
+
+fn create_dict_file(
+    dict_info: &DictionaryInfo,
+    words: impl Iterator<Item = (impl StarDictPaliWord, Option<Frequency>)>,
+    igen: &dyn InflectionGenerator,
+    render_cache: Option<&RenderCache>,
+    logger: &dyn PlsLogger,
+) -> Result<(DictionaryData, Vec<IdxEntry>), String> {
+    let path = create_base_path(
+        Path::new(dict_info.input_data_path),
+        dict_info.output_folder,
+        dict_info.short_name,
+    )?
+    .with_extension("dict.part");
+
+    let file = File::create(&path).map_err(|e| e.to_string())?;
+    let mut dict_writer = BufWriter::new(file);
+    let idx_entries = create_dict(
+        dict_info,
+        words,
+        igen,
+        render_cache,
+        &mut dict_writer,
+        logger,
+    )
+    .and_then(|mut idx_entries| {
+        if has_family_articles(dict_info) {
+            create_root_families(dict_info, &mut idx_entries, &mut dict_writer, logger)
+                .and_then(|_| {
+                    create_derivation_trees(dict_info, &mut idx_entries, &mut dict_writer, logger)
+                })
+                .map(|_| idx_entries)
+        } else {
+            Ok(idx_entries)
+        }
+    })
+    .and_then(|mut idx_entries| match dict_info.deconstruct_path {
+        Some(forms_path) => create_deconstructions(
+            dict_info,
+            Path::new(forms_path),
+            &mut idx_entries,
+            &mut dict_writer,
+            logger,
+        )
+        .map(|_| idx_entries),
+        None => Ok(idx_entries),
+    });
+
+    match idx_entries {
+        Ok(idx_entries) => {
+            let size = idx_entries
+                .last()
+                .map(|e| (e.data_offset + e.data_size) as u64)
+                .unwrap_or(0);
+            Ok((DictionaryData::FileBacked { path, size }, idx_entries))
+        }
+        Err(e) => {
+            drop(dict_writer);
+            let _ = fs::remove_file(&path);
+            Err(e)
+        }
+    }
+}
+
+///
+/// Reads the html of an article back from the .dict data.
+///
+pub(crate) enum ArticleReader<'a> {
+    InMemory(&'a [u8]),
+    FileBacked(File),
+}
+
+impl<'a> ArticleReader<'a> {
+    pub fn new(dict: &'a DictionaryData) -> Result<Self, String> {
+        match dict {
+            DictionaryData::InMemory(data) => Ok(ArticleReader::InMemory(data)),
+            DictionaryData::FileBacked { path, .. } => File::open(path)
+                .map(ArticleReader::FileBacked)
+                .map_err(|e| e.to_string()),
+        }
+    }
+
+    pub fn read(&mut self, data_offset: i32, data_size: i32) -> Result<String, String> {
+        let (offset, size) = (data_offset as usize, data_size as usize);
+        let bytes = match self {
+            ArticleReader::InMemory(data) => data
+                .get(offset..offset + size)
+                .ok_or_else(|| "Article is outside of the dict data".to_string())?
+                .to_vec(),
+            ArticleReader::FileBacked(file) => {
+                let mut bytes = vec![0u8; size];
+                file.seek(SeekFrom::Start(offset as u64))
+                    .and_then(|_| file.read_exact(&mut bytes))
+                    .map_err(|e| e.to_string())?;
+                bytes
+            }
+        };
+
+        String::from_utf8(bytes).map_err(|e| e.to_string())
+    }
 }
 
 fn create_idx(idx_entries: &[IdxEntry], logger: &dyn PlsLogger) -> Vec<u8> {
@@ -183,46 +576,117 @@ fn create_idx(idx_entries: &[IdxEntry], logger: &dyn PlsLogger) -> Vec<u8> {
     idx
 }
 
-struct SynEntry {
-    synonym_word: String,
+struct SynEntry<'a> {
+    synonym_word: &'a str,
     original_word_index: i32,
 }
 
-fn create_syn(idx_entries: &[IdxEntry], logger: &dyn PlsLogger) -> (Vec<u8>, usize) {
-    let mut syn_entries = idx_entries
+fn create_syn(
+    idx_entries: &[IdxEntry],
+    prefer_headwords: bool,
+    logger: &dyn PlsLogger,
+) -> (Vec<u8>, usize) {
+    let mut syn_entries: Vec<SynEntry> = idx_entries
         .iter()
         .enumerate()
-        .fold(Vec::new(), |mut acc, (n, e)| {
-            let mut ses: Vec<SynEntry> = e
-                .synonym_words
-                .iter()
-                .map(|sw| SynEntry {
-                    synonym_word: sw.to_owned(),
-                    original_word_index: n as i32,
-                })
-                .collect();
+        .flat_map(|(n, e)| {
+            e.synonym_words.iter().map(move |sw| SynEntry {
+                synonym_word: sw,
+                original_word_index: n as i32,
+            })
+        })
+        .collect();
 
-            acc.append(&mut ses);
+    // NOTE: A form that is also a headword gets an entry for that headword, which is
+    // sorted ahead of the other groups it is an inflection of. Deconstructions are not
+    // headwords, they only cover forms that are neither headwords nor inflections.
+    let headwords: HashMap<&str, i32> = if prefer_headwords {
+        idx_entries
+            .iter()
+            .enumerate()
+            .filter(|(_, e)| !e.word.ends_with(DECONSTRUCTION_SUFFIX))
+            .map(|(n, e)| (e.word.as_str(), n as i32))
+            .collect()
+    } else {
+        HashMap::new()
+    };
+    let headword_entries: Vec<SynEntry> = syn_entries
+        .iter()
+        .filter_map(|e| {
+            headwords.get(e.synonym_word).map(|n| SynEntry {
+                synonym_word: e.synonym_word,
+                original_word_index: *n,
+            })
+        })
+        .collect();
+    syn_entries.extend(headword_entries);
+    let is_headword = |e: &SynEntry| headwords.get(e.synonym_word) == Some(&e.original_word_index);
 
-            acc
-        });
+    logger.info(&format!("Creating {} syn entries.", syn_entries.len()));
 
+    // NOTE: Ties are broken on the word index so that the output is reproducible.
+    syn_entries.sort_by(|w1, w2| {
+        glib::stardict_strcmp(w1.synonym_word, w2.synonym_word)
+            .then(is_headword(w2).cmp(&is_headword(w1)))
+            .then(w1.original_word_index.cmp(&w2.original_word_index))
+    });
+    syn_entries.dedup_by(|w1, w2| {
+        w1.synonym_word == w2.synonym_word && w1.original_word_index == w2.original_word_index
+    });
     let syn_count = syn_entries.len();
-    logger.info(&format!("Creating {} syn entries.", syn_count));
 
-    syn_entries.sort_by(|w1, w2| glib::stardict_strcmp(&w1.synonym_word, &w2.synonym_word));
-    let syn: Vec<u8> = syn_entries.iter().fold(Vec::new(), |mut acc, e| {
-        acc.append(&mut e.synonym_word.to_owned().into_bytes());
-        acc.push(0u8);
-        acc.extend_from_slice(&e.original_word_index.to_be_bytes());
-        acc
-    });
+    let syn_size = syn_entries.iter().map(|e| e.synonym_word.len() + 5).sum();
+    let syn: Vec<u8> = syn_entries
+        .iter()
+        .fold(Vec::with_capacity(syn_size), |mut acc, e| {
+            acc.extend_from_slice(e.synonym_word.as_bytes());
+            acc.push(0u8);
+            acc.extend_from_slice(&e.original_word_index.to_be_bytes());
+            acc
+        });
 
     logger.info(&format!("... done creating {} syn entries.", syn_count));
 
     (syn, syn_count)
 }
 
+///
+/// Renders the word groups and the synthetic articles into the .dict data, with an idx entry
+/// for each article. Also used by the formats that share the StarDict html.
+///
+pub(crate) fn create_articles(
+    dict_info: &DictionaryInfo,
+    words: impl Iterator<Item = impl StarDictPaliWord>,
+    igen: &dyn InflectionGenerator,
+    logger: &dyn PlsLogger,
+) -> Result<(DictionaryData, Vec<IdxEntry>), String> {
+    let render_cache = match dict_info.render_cache_path {
+        Some(path) => Some(RenderCache::open(
+            Path::new(path),
+            &create_render_fingerprint(dict_info)?,
+            logger,
+        )?),
+        None => None,
+    };
+
+    // NOTE: Words below the minimum frequency are left out, e.g. for learners' concise builds.
+    let frequencies = load_frequency_table(dict_info.frequency_path, logger)?;
+    let words = with_frequencies(
+        words,
+        |w| w.id(),
+        frequencies.as_ref(),
+        igen,
+        dict_info.min_frequency,
+    );
+
+    let articles = create_dict_file(dict_info, words, igen, render_cache.as_ref(), logger)?;
+    if let Some(rc) = render_cache {
+        rc.finish(logger)?;
+    }
+
+    Ok(articles)
+}
+
 ///
 /// See https://github.com/huzheng001/stardict-3/blob/master/dict/doc/StarDictFileFormat
 ///
@@ -231,46 +695,124 @@ pub fn create_dictionary(
     words: impl Iterator<Item = impl StarDictPaliWord>,
     igen: &dyn InflectionGenerator,
     logger: &dyn PlsLogger,
-) -> Result<Vec<DictionaryFile>, String> {
-    let (dict, mut idx_entries) = create_dict(dict_info, words, igen, logger)?;
+) -> Result<DictionaryFiles, String> {
+    let (dict, mut idx_entries) = create_articles(dict_info, words, igen, logger)?;
+
     idx_entries.sort_by(|w1, w2| glib::stardict_strcmp(&w1.word, &w2.word));
     let idx = create_idx(&idx_entries, logger);
-    let (syn, syn_count) = create_syn(&idx_entries, logger);
+    if dict_info.collision_report {
+        let collisions = collisions::find_collisions(&idx_entries);
+        logger.info(&format!(
+            "Found {} colliding inflections, {} of which are also headwords.",
+            collisions.len(),
+            collisions.iter().filter(|c| c.headword.is_some()).count()
+        ));
+        let report_path = create_base_path(
+            Path::new(dict_info.input_data_path),
+            dict_info.output_folder,
+            dict_info.short_name,
+        )?
+        .with_extension("collisions.tsv");
+        collisions::write_collision_report(&report_path, &collisions, logger)?;
+    }
+
+    let (syn, syn_count) = create_syn(&idx_entries, dict_info.prefer_headwords, logger);
     let ifo = create_ifo(dict_info, idx_entries.len(), syn_count, idx.len())?;
     let png = create_png(dict_info);
 
-    Ok(vec![
-        DictionaryFile {
-            extension: "idx".to_string(),
-            bom: vec![],
-            data: idx,
-            can_be_empty: false,
-        },
-        DictionaryFile {
-            extension: "dict".to_string(),
-            bom: vec![],
-            data: dict,
-            can_be_empty: false,
-        },
-        DictionaryFile {
-            extension: "syn".to_string(),
-            bom: vec![],
-            data: syn,
-            can_be_empty: true,
-        },
-        DictionaryFile {
-            extension: "ifo".to_string(),
-            bom: vec![],
-            data: ifo,
-            can_be_empty: false,
-        },
-        DictionaryFile {
-            extension: "png".to_string(),
-            bom: vec![],
-            data: png,
-            can_be_empty: false,
-        },
-    ])
+    Ok(DictionaryFiles {
+        word_count: idx_entries.len(),
+        files: vec![
+            DictionaryFile {
+                extension: "idx".to_string(),
+                bom: vec![],
+                data: DictionaryData::InMemory(idx),
+                can_be_empty: false,
+            },
+            DictionaryFile {
+                extension: "dict".to_string(),
+                bom: vec![],
+                data: dict,
+                can_be_empty: false,
+            },
+            DictionaryFile {
+                extension: "syn".to_string(),
+                bom: vec![],
+                data: DictionaryData::InMemory(syn),
+                can_be_empty: true,
+            },
+            DictionaryFile {
+                extension: "ifo".to_string(),
+                bom: vec![],
+                data: DictionaryData::InMemory(ifo),
+                can_be_empty: false,
+            },
+            DictionaryFile {
+                extension: "png".to_string(),
+                bom: vec![],
+                data: DictionaryData::InMemory(png),
+                can_be_empty: false,
+            },
+        ],
+    })
+}
+
+///
+/// Everything other than the words themselves that affects the rendered html.
+///
+fn create_render_fingerprint(dict_info: &DictionaryInfo) -> Result<String, String> {
+    let mut hasher = Sha256::new();
+    for part in WORD_TEMPLATES
+        .iter()
+        .chain(&[
+            include_str!("templates/word_group.html"),
+            include_str!("templates/word_group.css"),
+        ])
+        .chain(INFLECTION_DATA)
+        .chain(SUTTA_DATA)
+    {
+        hasher.update(part.as_bytes());
+    }
+    for part in &[
+        env!("CARGO_PKG_VERSION"),
+        dict_info.short_name,
+        dict_info.links_color,
+        dict_info.headings_color,
+        dict_info.feedback_form_url,
+        dict_info.host_url,
+        dict_info.host_version,
+        dict_info.sutta_link_pattern.unwrap_or(""),
+        if dict_info.concise { "concise" } else { "full" },
+        &data_file_version(dict_info.inflections_db_path)?,
+        &data_file_version(dict_info.frequency_path)?,
+    ] {
+        hasher.update([0u8]);
+        hasher.update(part.as_bytes());
+    }
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+///
+/// The context of the word group css, also for the formats that add their own css to it.
+///
+pub(crate) fn create_style_context(dict_info: &DictionaryInfo) -> Result<Context, String> {
+    let vm = StyleViewModel {
+        dict_short_name: dict_info.short_name,
+        links_color: dict_info.links_color,
+        headings_color: dict_info.headings_color,
+    };
+
+    Context::from_serialize(&vm).map_err(|e| e.to_string())
+}
+
+///
+/// The css that every word group article embeds, for formats that ship it as a resource.
+///
+pub(crate) fn create_stylesheet(dict_info: &DictionaryInfo) -> Result<String, String> {
+    TEMPLATES
+        .render("word_group_style", &create_style_context(dict_info)?)
+        .map_err(|e| e.to_string())
 }
 
 fn create_ifo(
@@ -310,7 +852,7 @@ mod tests {
     use crate::tests::{TestInflectionGenerator, TestLogger};
     use crate::{resolve_file_in_manifest_dir, InputFormat, OutputFormat};
 
-    #[derive(Debug, Deserialize)]
+    #[derive(Debug, Serialize, Deserialize)]
     struct TestPaliWord {
         id: String,
         sort_key: String,
@@ -337,6 +879,10 @@ mod tests {
             self.toc_id.clone()
         }
 
+        fn to_json(&self) -> Result<String, String> {
+            serde_json::to_string(self).map_err(|e| e.to_string())
+        }
+
         fn toc_entry(&self, _dict_short_namet: &str, _concise: bool) -> Result<String, String> {
             Ok(self.toc_entry.clone())
         }
@@ -347,8 +893,11 @@ mod tests {
             feedback_form_url: &str,
             host_url: &str,
             host_version: &str,
+            _sutta_link_pattern: Option<&str>,
+            _frequency: Option<&Frequency>,
             igen: &dyn InflectionGenerator,
             _concise: bool,
+            _link_families: bool,
         ) -> Result<String, String> {
             Ok(format!(
                 "{}-{}-{}-{}-{}-{}",
@@ -376,34 +925,28 @@ mod tests {
 
     fn create_dict_info<'a>() -> DictionaryInfo<'a> {
         DictionaryInfo {
-            name: "Digital Pāli Tools Dictionary (DPD)",
-            input_data_path: "",
             input_format: &InputFormat::Dps,
-            output_format: &OutputFormat::StarDict,
-            output_folder: "???",
             short_name: "dps",
-            author: "Digital Pāli Tools <digitalpalitools@gmail.com>",
-            description: "The next generation comprehensive digital Pāli dictionary.",
-            headings_color: "#7986cb",
-            links_color: "#ff4081",
             time_stamp: "xxxx",
-            icon: vec![],
-            icon_path: None,
-            feedback_form_url: "http://feedback.form/???",
-            host_url: "this is the host",
-            host_version: "host version",
-            inflections_db_path: None,
-            concise: false,
+            ..crate::tests::create_dict_info(&OutputFormat(&crate::stardict::StarDictGenerator))
         }
     }
 
     #[test]
     fn create_dict_test() {
-        let words = read_pali_words();
+        let words = read_pali_words().map(|w| (w, None));
         let igen = TestInflectionGenerator::new();
 
-        let (dict_data, idx_entries) =
-            create_dict(&create_dict_info(), words, &igen, &TestLogger::new()).expect("Unexpected");
+        let mut dict_data: Vec<u8> = Vec::new();
+        let idx_entries = create_dict(
+            &create_dict_info(),
+            words,
+            &igen,
+            None,
+            &mut dict_data,
+            &TestLogger::new(),
+        )
+        .expect("Unexpected");
         let dict_entries: Vec<String> = idx_entries
             .iter()
             .map(|ie| {
@@ -419,6 +962,246 @@ mod tests {
         insta::assert_yaml_snapshot!(idx_entries);
     }
 
+    #[test]
+    fn create_dict_with_render_cache_test() {
+        let igen = TestInflectionGenerator::new();
+        let cache_dir = tempfile::tempdir().expect("Unexpected");
+        let cache_path = cache_dir.path().join("render_cache.db");
+
+        let mut dict_datas: Vec<Vec<u8>> = Vec::new();
+        for _ in 0..2 {
+            let rc = RenderCache::open(&cache_path, "fp", &TestLogger::new()).expect("Unexpected");
+            let mut dict_data: Vec<u8> = Vec::new();
+            create_dict(
+                &create_dict_info(),
+                read_pali_words().map(|w| (w, None)),
+                &igen,
+                Some(&rc),
+                &mut dict_data,
+                &TestLogger::new(),
+            )
+            .expect("Unexpected");
+            rc.finish(&TestLogger::new()).expect("Unexpected");
+            dict_datas.push(dict_data);
+        }
+
+        let mut uncached_dict_data: Vec<u8> = Vec::new();
+        create_dict(
+            &create_dict_info(),
+            read_pali_words().map(|w| (w, None)),
+            &igen,
+            None,
+            &mut uncached_dict_data,
+            &TestLogger::new(),
+        )
+        .expect("Unexpected");
+
+        assert_eq!(dict_datas[0], uncached_dict_data);
+        assert_eq!(dict_datas[1], uncached_dict_data);
+    }
+
+    #[test]
+    fn create_deconstructions_test() {
+        let dir = tempfile::tempdir().expect("Unexpected");
+        let forms_path = dir.path().join("text.txt");
+        fs::write(&forms_path, "Dhammavinayo ca, tañca dhammo. Xyz").expect("Unexpected");
+        let mut idx_entries = vec![
+            IdxEntry {
+                word: "dhamma".to_string(),
+                data_offset: 0,
+                data_size: 3,
+                synonym_words: vec!["dhammo".to_string()],
+            },
+            IdxEntry {
+                word: "vinaya".to_string(),
+                data_offset: 3,
+                data_size: 2,
+                synonym_words: vec!["vinayo".to_string()],
+            },
+        ];
+
+        let mut dict_data: Vec<u8> = Vec::new();
+        create_deconstructions(
+            &create_dict_info(),
+            &forms_path,
+            &mut idx_entries,
+            &mut dict_data,
+            &TestLogger::new(),
+        )
+        .expect("Unexpected");
+
+        assert_eq!(idx_entries.len(), 3);
+        assert_eq!(idx_entries[2].word, "dhammavinayo (deconstruction)");
+        assert_eq!(idx_entries[2].synonym_words, vec!["dhammavinayo"]);
+        assert_eq!(idx_entries[2].data_offset, 5);
+        assert_eq!(idx_entries[2].data_size as usize, dict_data.len());
+        insta::assert_snapshot!(String::from_utf8(dict_data).expect("Unexpected"));
+    }
+
+    #[test]
+    fn create_root_families_test() {
+        let path = resolve_file_in_manifest_dir("Pali_English_Dictionary_10_rows-full.csv")
+            .expect("must exist!");
+        let dict_info = DictionaryInfo {
+            input_data_path: path.to_str().expect("Unexpected"),
+            ..create_dict_info()
+        };
+        let mut idx_entries = vec![IdxEntry {
+            word: "abala".to_string(),
+            data_offset: 0,
+            data_size: 4,
+            synonym_words: vec![],
+        }];
+
+        let mut dict_data: Vec<u8> = Vec::new();
+        create_root_families(
+            &dict_info,
+            &mut idx_entries,
+            &mut dict_data,
+            &TestLogger::new(),
+        )
+        .expect("Unexpected");
+
+        assert_eq!(idx_entries[1].word, "√bal");
+        assert_eq!(idx_entries[1].synonym_words, vec!["bal"]);
+        assert_eq!(idx_entries[1].data_offset, 4);
+        let data_size = idx_entries[1].data_size as usize;
+        insta::assert_snapshot!(
+            String::from_utf8(dict_data[..data_size].to_vec()).expect("Unexpected")
+        );
+    }
+
+    #[test]
+    fn create_derivation_trees_test() {
+        let path = resolve_file_in_manifest_dir("Pali_English_Dictionary_10_rows-full.csv")
+            .expect("must exist!");
+        let dict_info = DictionaryInfo {
+            input_data_path: path.to_str().expect("Unexpected"),
+            ..create_dict_info()
+        };
+        let mut idx_entries = vec![];
+
+        let mut dict_data: Vec<u8> = Vec::new();
+        create_derivation_trees(
+            &dict_info,
+            &mut idx_entries,
+            &mut dict_data,
+            &TestLogger::new(),
+        )
+        .expect("Unexpected");
+
+        let e = idx_entries
+            .iter()
+            .find(|e| e.word == "ā √bādh (derivation)")
+            .expect("Unexpected");
+        assert_eq!(e.synonym_words, vec!["ā √bādh"]);
+        let start = e.data_offset as usize;
+        let end = start + e.data_size as usize;
+        insta::assert_snapshot!(
+            String::from_utf8(dict_data[start..end].to_vec()).expect("Unexpected")
+        );
+    }
+
+    #[test]
+    fn family_links_target_generated_articles_test() {
+        let path = resolve_file_in_manifest_dir("Pali_English_Dictionary_10_rows-full.csv")
+            .expect("must exist!");
+        let output_dir = tempfile::tempdir().expect("Unexpected");
+        let dict_info = DictionaryInfo {
+            input_data_path: path.to_str().expect("Unexpected"),
+            input_format: &InputFormat::Dpd,
+            output_folder: output_dir.path().to_str().expect("Unexpected"),
+            short_name: "dpd",
+            ..create_dict_info()
+        };
+        let l = TestLogger::new();
+        let words =
+            crate::stardict::input_parsers::load_words::<crate::input::dpd::DpdPaliWord>(&path, &l)
+                .expect("Unexpected");
+
+        let (dict, idx_entries) =
+            create_articles(&dict_info, words, &TestInflectionGenerator::new(), &l)
+                .expect("Unexpected");
+
+        let dict_data = match dict {
+            DictionaryData::FileBacked { path, .. } => fs::read(path).expect("Unexpected"),
+            DictionaryData::InMemory(data) => data,
+        };
+        let html = String::from_utf8(dict_data).expect("Unexpected");
+        let link =
+            regex::Regex::new(r#"dictionary-(root|family)-link-dpd" href="bword://([^"]*)""#)
+                .expect("Unexpected");
+        let targets: Vec<(&str, &str)> = link
+            .captures_iter(&html)
+            .map(|c| {
+                (
+                    c.get(1).expect("Unexpected").as_str(),
+                    c.get(2).expect("Unexpected").as_str(),
+                )
+            })
+            .unique()
+            .collect();
+
+        assert!(targets.contains(&("family", "ā √bādh (derivation)")));
+        assert!(targets.contains(&("root", "√bādh")));
+        for (_, target) in targets {
+            assert!(
+                idx_entries.iter().any(|e| e.word == target),
+                "no article for '{}'",
+                target
+            );
+        }
+    }
+
+    #[test]
+    fn deconstruction_links_target_articles_test() {
+        let path = resolve_file_in_manifest_dir("Pali_English_Dictionary_10_rows-full.csv")
+            .expect("must exist!");
+        let output_dir = tempfile::tempdir().expect("Unexpected");
+        let forms_path = output_dir.path().join("text.txt");
+        fs::write(&forms_path, "abalabahulīkata byāpajjhābala").expect("Unexpected");
+        let dict_info = DictionaryInfo {
+            input_data_path: path.to_str().expect("Unexpected"),
+            input_format: &InputFormat::Dpd,
+            output_folder: output_dir.path().to_str().expect("Unexpected"),
+            short_name: "dpd",
+            deconstruct_path: forms_path.to_str(),
+            ..create_dict_info()
+        };
+        let l = TestLogger::new();
+        let words =
+            crate::stardict::input_parsers::load_words::<crate::input::dpd::DpdPaliWord>(&path, &l)
+                .expect("Unexpected");
+
+        let (dict, idx_entries) =
+            create_articles(&dict_info, words, &TestInflectionGenerator::new(), &l)
+                .expect("Unexpected");
+
+        let mut reader = ArticleReader::new(&dict).expect("Unexpected");
+        let articles: Vec<String> = idx_entries
+            .iter()
+            .filter(|e| e.word.ends_with(DECONSTRUCTION_SUFFIX))
+            .map(|e| reader.read(e.data_offset, e.data_size).expect("Unexpected"))
+            .collect();
+        let link = regex::Regex::new(r#"href="bword://([^"]*)">([^<]*)</a>"#).expect("Unexpected");
+        let targets: Vec<&str> = articles
+            .iter()
+            .flat_map(|a| link.captures_iter(a))
+            .map(|c| c.get(1).expect("Unexpected").as_str())
+            .collect();
+
+        assert_eq!(articles.len(), 2);
+        assert!(targets.contains(&"abala"));
+        assert!(articles[0].contains(" + bahulīkata</li>"));
+        for target in targets {
+            assert!(
+                idx_entries.iter().any(|e| e.word == target),
+                "no article for '{}'",
+                target
+            );
+        }
+    }
+
     #[test]
     fn create_idx_test() {
         let idx_entries = vec![
@@ -461,7 +1244,7 @@ mod tests {
             },
         ];
 
-        let (syn, syn_count) = create_syn(&idx_entries, &TestLogger::new());
+        let (syn, syn_count) = create_syn(&idx_entries, false, &TestLogger::new());
 
         assert_eq!(
             syn,
@@ -470,6 +1253,66 @@ mod tests {
         assert_eq!(syn_count, 3);
     }
 
+    #[test]
+    fn create_syn_prefers_headwords_test() {
+        let idx_entries = vec![
+            IdxEntry {
+                word: "a".to_string(),
+                data_offset: 0,
+                data_size: 0,
+                synonym_words: vec!["b".to_string()],
+            },
+            IdxEntry {
+                word: "b".to_string(),
+                data_offset: 0,
+                data_size: 0,
+                synonym_words: vec![],
+            },
+            IdxEntry {
+                word: "c".to_string(),
+                data_offset: 0,
+                data_size: 0,
+                synonym_words: vec!["b".to_string()],
+            },
+        ];
+
+        let (syn, syn_count) = create_syn(&idx_entries, false, &TestLogger::new());
+        assert_eq!(syn, vec![0x62, 0, 0, 0, 0, 0, 0x62, 0, 0, 0, 0, 2]);
+        assert_eq!(syn_count, 2);
+
+        let (syn, syn_count) = create_syn(&idx_entries, true, &TestLogger::new());
+        assert_eq!(
+            syn,
+            vec![0x62, 0, 0, 0, 0, 1, 0x62, 0, 0, 0, 0, 0, 0x62, 0, 0, 0, 0, 2]
+        );
+        assert_eq!(syn_count, 3);
+    }
+
+    #[test]
+    fn create_syn_does_not_prefer_deconstructions_test() {
+        let idx_entries = vec![
+            IdxEntry {
+                word: "a".to_string(),
+                data_offset: 0,
+                data_size: 0,
+                synonym_words: vec!["b (deconstruction)".to_string()],
+            },
+            IdxEntry {
+                word: "b (deconstruction)".to_string(),
+                data_offset: 0,
+                data_size: 0,
+                synonym_words: vec!["b".to_string()],
+            },
+        ];
+
+        let (syn, syn_count) = create_syn(&idx_entries, true, &TestLogger::new());
+
+        assert_eq!(
+            (syn, syn_count),
+            create_syn(&idx_entries, false, &TestLogger::new())
+        );
+    }
+
     #[test]
     fn create_ifo_test() {
         let ifo = create_ifo(&create_dict_info(), 100, 500, 1000).expect("Unexpected");
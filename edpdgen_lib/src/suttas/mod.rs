@@ -0,0 +1,248 @@
+use crate::input::create_csv_reader;
+use itertools::Itertools;
+use pls_core_extras::logger::PlsLogger;
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+const BOOKS: &str = include_str!("books.tsv");
+
+///
+/// The bundled data that affects rendered sutta links, so cached renders are invalidated
+/// when it changes.
+///
+pub const SUTTA_DATA: &[&str] = &[BOOKS];
+
+/// The placeholder in link patterns that is replaced with the SuttaCentral uid, e.g. mn10.
+pub const SUTTA_ID_PLACEHOLDER: &str = "{id}";
+
+/// Columns holding sutta references in both DPD and DPS inputs.
+const SOURCE_COLUMNS: &[&str] = &["Source1", "Source 2"];
+
+#[derive(Debug, Deserialize)]
+struct Book {
+    book: String,
+    abbreviation: String,
+    parts: usize,
+    count: u32,
+    counts: String,
+    groups: String,
+}
+
+impl Book {
+    fn counts(&self) -> Result<Vec<u32>, String> {
+        parse_numbers(&self.counts)
+    }
+
+    fn groups(&self) -> Result<Vec<u32>, String> {
+        parse_numbers(&self.groups)
+    }
+}
+
+fn parse_numbers(numbers: &str) -> Result<Vec<u32>, String> {
+    numbers
+        .split(',')
+        .filter(|n| !n.is_empty())
+        .map(|n| n.parse::<u32>().map_err(|e| e.to_string()))
+        .collect()
+}
+
+/// The number of rules in each vagga of the Bhikkhu Pācittiya, the 8th has 12.
+const PACITTIYA_VAGGAS: &[u32] = &[10, 10, 10, 10, 10, 10, 10, 12, 10];
+
+lazy_static! {
+    static ref SUTTA_REF: Regex =
+        Regex::new(r"^([A-Za-z]+)\s*(\d+(?:\.\d+)*)$").expect("Malformed regex string");
+    static ref BOOK_CATALOGUE: BTreeMap<String, Book> =
+        load_books(BOOKS).expect("Malformed books.tsv");
+}
+
+fn load_books(books: &str) -> Result<BTreeMap<String, Book>, String> {
+    let rdr = csv::ReaderBuilder::new()
+        .delimiter(b'\t')
+        .comment(Some(b'#'))
+        .from_reader(books.as_bytes());
+
+    rdr.into_deserialize::<Book>()
+        .map(|r| {
+            r.map(|b| (b.abbreviation.to_uppercase(), b))
+                .map_err(|e| e.to_string())
+        })
+        .collect()
+}
+
+///
+/// Normalises a reference such as 'SN 56.11' to its SuttaCentral uid 'sn56.11'. Numbers beyond
+/// those identifying the sutta, e.g. the section in 'DN 2.19', are not part of the uid.
+///
+pub fn parse_sutta_ref(source: &str) -> Result<String, String> {
+    let caps = SUTTA_REF
+        .captures(source.trim())
+        .ok_or_else(|| "not of the form 'BOOK 1.2'".to_string())?;
+    let book = BOOK_CATALOGUE
+        .get(&caps[1].to_uppercase())
+        .ok_or_else(|| format!("unknown book '{}'", &caps[1]))?;
+
+    let numbers: Vec<u32> = caps[2]
+        .split('.')
+        .map(|n| n.parse::<u32>().map_err(|e| e.to_string()))
+        .collect::<Result<_, _>>()?;
+    if numbers.len() < book.parts {
+        return Err(format!(
+            "{} needs {} numbers to identify a sutta",
+            book.abbreviation, book.parts
+        ));
+    }
+    check_range(&book.abbreviation, numbers[0], book.count)?;
+    if let Some(&count) = book.counts()?.get(numbers[0] as usize - 1) {
+        check_range(
+            &format!("{} {}", book.abbreviation, numbers[0]),
+            numbers[1],
+            count,
+        )?;
+    }
+
+    let groups = book.groups()?;
+    if book.book == "vin" {
+        create_vinaya_uid(&numbers)
+    } else if let Some(i) = groups.iter().rposition(|&g| g <= numbers[0]) {
+        let last = groups.get(i + 1).map(|g| g - 1).unwrap_or(book.count);
+        Ok(format!("{}{}-{}", book.book, groups[i], last))
+    } else {
+        Ok(format!(
+            "{}{}",
+            book.book,
+            numbers.into_iter().take(book.parts).join(".")
+        ))
+    }
+}
+
+fn check_range(what: &str, number: u32, count: u32) -> Result<(), String> {
+    if number == 0 || number > count {
+        Err(format!("{} runs from 1 to {}", what, count))
+    } else {
+        Ok(())
+    }
+}
+
+///
+/// Vinaya references are numbered volume.kaṇḍa.vagga.rule. Only the Bhikkhu Pācittiya,
+/// kaṇḍa 5 of volume 2, is mapped to SuttaCentral so far.
+///
+fn create_vinaya_uid(numbers: &[u32]) -> Result<String, String> {
+    match numbers {
+        [2, 5, vagga, rule, ..] => {
+            check_range("VIN 2.5", *vagga, PACITTIYA_VAGGAS.len() as u32)?;
+            let vagga = *vagga as usize - 1;
+            check_range(
+                &format!("VIN 2.5.{}", vagga + 1),
+                *rule,
+                PACITTIYA_VAGGAS[vagga],
+            )?;
+
+            let previous: u32 = PACITTIYA_VAGGAS[..vagga].iter().sum();
+            Ok(format!("pli-tv-bu-vb-pc{}", previous + rule))
+        }
+        _ => Err(format!(
+            "no SuttaCentral uid for VIN {}.{}",
+            numbers[0], numbers[1]
+        )),
+    }
+}
+
+///
+/// The link for a reference built from a pattern such as 'https://suttacentral.net/{id}' or
+/// 'bword://{id}'. None when there is no pattern or the reference cannot be resolved.
+///
+pub fn create_sutta_link(source: &str, pattern: Option<&str>) -> Option<String> {
+    pattern.and_then(|p| {
+        parse_sutta_ref(source)
+            .ok()
+            .map(|id| p.replace(SUTTA_ID_PLACEHOLDER, &id))
+    })
+}
+
+///
+/// Reports the references in the input that would not be linked.
+///
+pub fn check_sutta_refs(input_data_path: &Path, logger: &dyn PlsLogger) -> Result<(), String> {
+    logger.info("Checking sutta references.");
+    let mut rdr = create_csv_reader(input_data_path, logger)?;
+    let headers = rdr.headers().map_err(|e| e.to_string())?.clone();
+    let columns: Vec<usize> = headers
+        .iter()
+        .enumerate()
+        .filter(|(_, h)| SOURCE_COLUMNS.contains(h))
+        .map(|(i, _)| i)
+        .collect();
+
+    let mut total = 0;
+    let mut errors: BTreeMap<String, (String, usize)> = BTreeMap::new();
+    // NOTE: Records that fail to parse are reported when the words are loaded.
+    for r in rdr.records().filter_map(|r| r.ok()) {
+        for source in columns.iter().filter_map(|&i| r.get(i)) {
+            if source.trim().is_empty() {
+                continue;
+            }
+
+            total += 1;
+            if let Err(e) = parse_sutta_ref(source) {
+                errors.entry(source.trim().to_string()).or_insert((e, 0)).1 += 1;
+            }
+        }
+    }
+
+    for (source, (e, count)) in &errors {
+        logger.warning(&format!(
+            "Unable to parse sutta reference '{}' ({} words): {}.",
+            source, count, e
+        ));
+    }
+    logger.info(&format!(
+        "... done checking {} sutta references, {} distinct ones could not be parsed.",
+        total,
+        errors.len()
+    ));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("MN 10", "mn10")]
+    #[test_case("SN 56.11", "sn56.11")]
+    #[test_case("DHP 183", "dhp179-196")]
+    #[test_case("DHP 1", "dhp1-20"; "first dhp vagga")]
+    #[test_case("DHP 423", "dhp383-423"; "last dhp vagga")]
+    #[test_case("DN 2.19", "dn2"; "sections are dropped")]
+    #[test_case("VIN 2.5.2.1", "pli-tv-bu-vb-pc11")]
+    #[test_case("VIN 2.5.9.7", "pli-tv-bu-vb-pc89")]
+    #[test_case(" an9.36 ", "an9.36"; "spacing and case")]
+    fn parse_sutta_ref_test(source: &str, expected: &str) {
+        assert_eq!(parse_sutta_ref(source), Ok(expected.to_string()));
+    }
+
+    #[test_case("kaccāyana", "not of the form 'BOOK 1.2'")]
+    #[test_case("CPa 3.15", "unknown book 'CPa'")]
+    #[test_case("SN 22", "SN needs 2 numbers to identify a sutta")]
+    #[test_case("MN 200", "MN runs from 1 to 152")]
+    #[test_case("SN 56.132", "SN 56 runs from 1 to 131")]
+    #[test_case("AN 12.1", "AN runs from 1 to 11")]
+    #[test_case("VIN 2.5.8.13", "VIN 2.5.8 runs from 1 to 12")]
+    #[test_case("VIN 3.1.1.1", "no SuttaCentral uid for VIN 3.1")]
+    fn parse_sutta_ref_errors_test(source: &str, expected: &str) {
+        assert_eq!(parse_sutta_ref(source), Err(expected.to_string()));
+    }
+
+    #[test_case("MN 39", Some("bword://{id}"), Some("bword://mn39"))]
+    #[test_case("MN 39", None, None; "no pattern")]
+    #[test_case("Sri Lanka", Some("bword://{id}"), None; "unparseable")]
+    fn create_sutta_link_test(source: &str, pattern: Option<&str>, expected: Option<&str>) {
+        assert_eq!(
+            create_sutta_link(source, pattern),
+            expected.map(|e| e.to_string())
+        );
+    }
+}
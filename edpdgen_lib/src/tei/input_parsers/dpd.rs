@@ -0,0 +1,97 @@
+use crate::input::dpd::DpdPaliWord;
+use crate::input::{make_group_id, make_sort_key};
+use crate::suttas::create_sutta_link;
+use crate::tei::input_parsers::{make_xml_id, tei_filter};
+use crate::tei::TeiPaliWord;
+use crate::xml_filter;
+use tera::{Context, Tera};
+
+lazy_static! {
+    static ref TEMPLATES: Tera = {
+        let mut tera = Tera::default();
+        tera.add_raw_templates(vec![("dpd_entry", include_str!("templates/dpd_entry.xml"))])
+            .expect("Unexpected failure adding template");
+        tera.register_filter("tei", tei_filter);
+        tera.register_filter("xml", xml_filter);
+        tera
+    };
+}
+
+#[derive(Serialize)]
+struct EntryViewModel<'a> {
+    word: &'a DpdPaliWord,
+    xml_id: String,
+    lemma: String,
+    homograph: &'a str,
+    inflections: &'a [String],
+    concise: bool,
+    source1_link: Option<String>,
+    source2_link: Option<String>,
+}
+
+impl TeiPaliWord for DpdPaliWord {
+    fn id(&self) -> &str {
+        &self.pali1
+    }
+
+    fn sort_key(&self) -> String {
+        make_sort_key(self.id())
+    }
+
+    fn entry(
+        &self,
+        inflections: &[String],
+        concise: bool,
+        sutta_link_pattern: Option<&str>,
+    ) -> Result<String, String> {
+        let lemma = make_group_id(self.id());
+        let homograph = self.id().strip_prefix(lemma.as_str()).unwrap_or("").trim();
+        let vm = EntryViewModel {
+            word: self,
+            xml_id: make_xml_id("dpd", self.id()),
+            lemma,
+            homograph,
+            inflections,
+            concise,
+            source1_link: create_sutta_link(&self.source1, sutta_link_pattern),
+            source2_link: create_sutta_link(&self.source2, sutta_link_pattern),
+        };
+
+        let context = Context::from_serialize(&vm).map_err(|e| e.to_string())?;
+        TEMPLATES
+            .render("dpd_entry", &context)
+            .map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resolve_file_in_manifest_dir;
+    use crate::tei::input_parsers::load_words;
+    use crate::tests::TestLogger;
+    use test_case::test_case;
+
+    #[test_case(0, false)]
+    #[test_case(4, false)]
+    #[test_case(9, false)]
+    #[test_case(0, true)]
+    #[test_case(9, true)]
+    fn entry_tests(rec_number: usize, concise: bool) {
+        let path = resolve_file_in_manifest_dir("Pali_English_Dictionary_10_rows-full.csv")
+            .expect("must exist!");
+        let l = TestLogger::new();
+        let mut recs = load_words::<DpdPaliWord>(&path, &l).expect("unexpected");
+        let inflections = vec!["abalo".to_string(), "abalaṃ".to_string()];
+
+        let entry = recs
+            .nth(rec_number)
+            .map(|r| {
+                r.entry(&inflections, concise, Some("https://suttacentral.net/{id}"))
+                    .expect("unexpected")
+            })
+            .expect("unexpected");
+
+        insta::assert_snapshot!(entry);
+    }
+}
@@ -0,0 +1,106 @@
+use crate::escape_xml;
+use crate::input::create_csv_reader;
+use crate::tei::TeiPaliWord;
+use pls_core_extras::logger::PlsLogger;
+use regex::Regex;
+use std::collections::HashMap;
+use std::path::Path;
+use tera::Value;
+
+pub mod dpd;
+
+lazy_static! {
+    static ref HTML_TAG: Regex = Regex::new(r"<[^>]*>").expect("Malformed regex string");
+}
+
+pub fn load_words<'a, T: 'a + serde::de::DeserializeOwned + TeiPaliWord>(
+    path: &Path,
+    logger: &'a dyn PlsLogger,
+) -> Result<impl Iterator<Item = impl TeiPaliWord> + 'a, String> {
+    let rdr = create_csv_reader(path, logger)?;
+
+    let words = rdr
+        .into_deserialize::<T>()
+        .enumerate()
+        .filter_map(move |(i, r)| match r {
+            Ok(w) => Some(w),
+            Err(e) => {
+                logger.error(&format!(
+                    "Unable to deserialize record #{}. Error: {}.",
+                    i, e
+                ));
+                None
+            }
+        });
+
+    logger.info(&format!("... done loading words from {:?}.", &path));
+    Ok(words)
+}
+
+///
+/// Maps the bold, italics and line breaks of the html in the input data to their TEI
+/// equivalents and escapes everything else.
+///
+pub fn html_to_tei(s: &str) -> String {
+    let s = s.replace("\r", "").replace("\n", "<br/>");
+
+    let mut tei = String::with_capacity(s.len());
+    let mut last = 0;
+    for tag in HTML_TAG.find_iter(&s) {
+        tei.push_str(&escape_xml(&s[last..tag.start()]));
+        match tag.as_str().replace(" ", "").as_str() {
+            "<b>" => tei.push_str("<hi rend=\"bold\">"),
+            "<i>" => tei.push_str("<hi rend=\"italic\">"),
+            "</b>" | "</i>" => tei.push_str("</hi>"),
+            "<br>" | "<br/>" => tei.push_str("<lb/>"),
+            _ => {}
+        }
+        last = tag.end();
+    }
+    tei.push_str(&escape_xml(&s[last..]));
+
+    tei
+}
+
+pub fn tei_filter(value: &Value, _args: &HashMap<String, Value>) -> tera::Result<Value> {
+    value
+        .as_str()
+        .map(|s| Value::String(html_to_tei(s)))
+        .ok_or_else(|| tera::Error::msg("Filter 'tei' expects a string"))
+}
+
+///
+/// An xml:id, which must be an NCName, e.g. 'dpd.abala.1' for 'abala 1'.
+///
+pub fn make_xml_id(prefix: &str, id: &str) -> String {
+    let id: String = id
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(".")
+        .chars()
+        .filter(|c| c.is_alphanumeric() || *c == '.' || *c == '-' || *c == '_')
+        .collect();
+
+    format!("{}.{}", prefix, id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("abala", "abala")]
+    #[test_case("a & b < c", "a &amp; b &lt; c"; "markup characters")]
+    #[test_case("<b>dhammo</b><br/>dhammaṃ\n", "<hi rend=\"bold\">dhammo</hi><lb/>dhammaṃ<lb/>"; "bold and breaks")]
+    #[test_case("<i>x</i> <span>y</span>", "<hi rend=\"italic\">x</hi> y"; "other tags are removed")]
+    fn html_to_tei_test(html: &str, expected: &str) {
+        assert_eq!(html_to_tei(html), expected);
+    }
+
+    #[test_case("abala 1", "dpd.abala.1")]
+    #[test_case("ābādha", "dpd.ābādha")]
+    #[test_case("a-(b) c 2", "dpd.a-b.c.2"; "punctuation")]
+    fn make_xml_id_test(id: &str, expected: &str) {
+        assert_eq!(make_xml_id("dpd", id), expected);
+    }
+}
@@ -0,0 +1,63 @@
+use crate::input::dpd::DpdPaliWord;
+use crate::input::input_format::InputFormat;
+use crate::output::output_generator::OutputGenerator;
+use crate::{DictionaryFiles, DictionaryInfo};
+use pls_core_extras::inflection_generator::InflectionGenerator;
+use pls_core_extras::logger::PlsLogger;
+use std::path::Path;
+
+mod input_parsers;
+mod output_generators;
+
+pub struct TeiGenerator;
+
+impl OutputGenerator for TeiGenerator {
+    fn name(&self) -> &'static str {
+        "tei"
+    }
+
+    fn description(&self) -> &'static str {
+        "TEI Lex-0 XML format for scholarly reuse."
+    }
+
+    fn input_formats(&self) -> &'static [InputFormat] {
+        &[InputFormat::Dpd]
+    }
+
+    fn options(&self) -> &'static [&'static str] {
+        &["concise", "sutta-links"]
+    }
+
+    fn build_files(
+        &self,
+        dict_info: &DictionaryInfo,
+        input_data_path: &Path,
+        igen: &dyn InflectionGenerator,
+        logger: &dyn PlsLogger,
+    ) -> Result<DictionaryFiles, String> {
+        run_for_ods_type::<DpdPaliWord>(dict_info, input_data_path, igen, logger)
+    }
+}
+
+pub trait TeiPaliWord {
+    fn id(&self) -> &str;
+    fn sort_key(&self) -> String;
+    fn entry(
+        &self,
+        inflections: &[String],
+        concise: bool,
+        sutta_link_pattern: Option<&str>,
+    ) -> Result<String, String>;
+}
+
+pub fn run_for_ods_type<'a, T: 'a + serde::de::DeserializeOwned + TeiPaliWord>(
+    dict_info: &DictionaryInfo,
+    input_data_path: &Path,
+    igen: &dyn InflectionGenerator,
+    logger: &dyn PlsLogger,
+) -> Result<DictionaryFiles, String> {
+    let words = input_parsers::load_words::<T>(input_data_path, logger)?;
+    let tei_files = output_generators::create_dictionary(dict_info, words, igen, logger)?;
+
+    Ok(tei_files)
+}
@@ -0,0 +1,177 @@
+use crate::input::make_group_id;
+use crate::tei::TeiPaliWord;
+use crate::{escape_xml, DictionaryData, DictionaryFile, DictionaryFiles, DictionaryInfo};
+use chrono::DateTime;
+use itertools::Itertools;
+use pls_core_extras::inflection_generator::InflectionGenerator;
+use pls_core_extras::logger::PlsLogger;
+
+const TEI_LEX0_SCHEMA: &str =
+    "https://dariah-eric.github.io/lexicalresources/pages/TEILex0/TEILex0.rng";
+
+fn create_entries(
+    dict_info: &DictionaryInfo,
+    words: impl Iterator<Item = impl TeiPaliWord>,
+    igen: &dyn InflectionGenerator,
+    logger: &dyn PlsLogger,
+) -> Result<Vec<String>, String> {
+    logger.info("Creating TEI entries.");
+
+    let mut entries: Vec<String> = Vec::new();
+    let words = words.sorted_by(|w1, w2| Ord::cmp(&w1.sort_key(), &w2.sort_key()));
+    for (n, word) in words.enumerate() {
+        let inflections: Vec<String> = if dict_info.concise {
+            vec![]
+        } else {
+            let lemma = make_group_id(word.id());
+            igen.generate_all_inflections(word.id())
+                .into_iter()
+                .filter(|i| i != &lemma)
+                .unique()
+                .collect()
+        };
+        entries.push(word.entry(
+            &inflections,
+            dict_info.concise,
+            dict_info.sutta_link_pattern,
+        )?);
+
+        if n % 1_000 == 0 && n != 0 {
+            logger.info(&format!(
+                "... created {:05} TEI entries, ending with '{}'.",
+                n,
+                word.id()
+            ));
+        }
+    }
+
+    logger.info(&format!("... done creating {} TEI entries.", entries.len()));
+
+    Ok(entries)
+}
+
+fn create_tei_header(dict_info: &DictionaryInfo) -> Result<String, String> {
+    let date = DateTime::parse_from_rfc3339(dict_info.time_stamp)
+        .map_err(|e| e.to_string())?
+        .format("%Y-%m-%d");
+
+    Ok(format!(
+        "<teiHeader>\n<fileDesc>\n<titleStmt><title>{}</title><author>{}</author></titleStmt>\n<editionStmt><edition n=\"{}\">{}</edition></editionStmt>\n<publicationStmt><publisher>{}</publisher><date when=\"{}\">{}</date></publicationStmt>\n<sourceDesc><p>{}</p></sourceDesc>\n</fileDesc>\n</teiHeader>\n",
+        escape_xml(dict_info.name),
+        escape_xml(dict_info.author),
+        escape_xml(dict_info.version),
+        escape_xml(dict_info.version),
+        escape_xml(dict_info.author),
+        date,
+        date,
+        escape_xml(dict_info.description),
+    ))
+}
+
+///
+/// See https://dariah-eric.github.io/lexicalresources/pages/TEILex0/TEILex0.html
+///
+fn create_tei_data(dict_info: &DictionaryInfo, entries: &[String]) -> Result<Vec<u8>, String> {
+    let mut tei = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<?xml-model href=\"{}\" type=\"application/xml\" schematypens=\"http://relaxng.org/ns/structure/1.0\"?>\n<TEI xmlns=\"http://www.tei-c.org/ns/1.0\" xml:lang=\"en\">\n",
+        TEI_LEX0_SCHEMA
+    );
+    tei.push_str(&create_tei_header(dict_info)?);
+    tei.push_str("<text>\n<body>\n");
+    for entry in entries {
+        tei.push_str(entry);
+    }
+    tei.push_str("</body>\n</text>\n</TEI>\n");
+
+    Ok(tei.into_bytes())
+}
+
+pub fn create_dictionary(
+    dict_info: &DictionaryInfo,
+    words: impl Iterator<Item = impl TeiPaliWord>,
+    igen: &dyn InflectionGenerator,
+    logger: &dyn PlsLogger,
+) -> Result<DictionaryFiles, String> {
+    let entries = create_entries(dict_info, words, igen, logger)?;
+    let tei = create_tei_data(dict_info, &entries)?;
+
+    Ok(DictionaryFiles {
+        word_count: entries.len(),
+        files: vec![DictionaryFile {
+            extension: "tei.xml".to_string(),
+            bom: vec![],
+            data: DictionaryData::InMemory(tei),
+            can_be_empty: false,
+        }],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::dpd::DpdPaliWord;
+    use crate::resolve_file_in_manifest_dir;
+    use crate::tei::input_parsers::load_words;
+    use crate::tests::{validate_with_xmllint, TestInflectionGenerator, TestLogger};
+    use crate::OutputFormat;
+    use test_case::test_case;
+
+    const XML_NS: &str = "http://www.w3.org/XML/1998/namespace";
+
+    /// The TEI Lex-0 RELAX NG schema, from https://github.com/DARIAH-ERIC/lexicalresources.
+    const TEI_LEX0_RNG: &str = "src/tei/output_generators/test_data/TEILex0.rng";
+
+    fn create_dict_info(concise: bool) -> DictionaryInfo<'static> {
+        DictionaryInfo {
+            concise,
+            sutta_link_pattern: Some("https://suttacentral.net/{id}"),
+            ..crate::tests::create_dict_info(&OutputFormat(&crate::tei::TeiGenerator))
+        }
+    }
+
+    fn create_tei(concise: bool) -> (String, usize) {
+        let path = resolve_file_in_manifest_dir("Pali_English_Dictionary_10_rows-full.csv")
+            .expect("must exist!");
+        let l = TestLogger::new();
+        let words = load_words::<DpdPaliWord>(&path, &l).expect("Unexpected");
+        let dict_info = create_dict_info(concise);
+
+        let entries = create_entries(&dict_info, words, &TestInflectionGenerator::new(), &l)
+            .expect("Unexpected");
+        let tei = create_tei_data(&dict_info, &entries).expect("Unexpected");
+
+        (String::from_utf8(tei).expect("Unexpected"), entries.len())
+    }
+
+    #[test_case(false)]
+    #[test_case(true)]
+    fn tei_is_valid_test(concise: bool) {
+        let (tei, entry_count) = create_tei(concise);
+
+        validate_with_xmllint("--relaxng", TEI_LEX0_RNG, &tei);
+        let doc = roxmltree::Document::parse(&tei).expect("Unexpected");
+        let entries: Vec<_> = doc
+            .descendants()
+            .filter(|n| n.has_tag_name("entry"))
+            .collect();
+        assert_eq!(entries.len(), entry_count);
+        let ids: Vec<_> = entries
+            .iter()
+            .filter_map(|e| e.attribute((XML_NS, "id")))
+            .unique()
+            .collect();
+        assert_eq!(ids.len(), entry_count);
+    }
+
+    #[test]
+    fn tei_header_test() {
+        let (tei, _) = create_tei(true);
+
+        let doc = roxmltree::Document::parse(&tei).expect("Unexpected");
+        let header = doc
+            .descendants()
+            .find(|n| n.has_tag_name("teiHeader"))
+            .expect("Unexpected");
+        insta::assert_snapshot!(&tei[header.range()]);
+    }
+}
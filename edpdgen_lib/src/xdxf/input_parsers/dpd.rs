@@ -0,0 +1,80 @@
+use crate::input::dpd::DpdPaliWord;
+use crate::input::{make_group_id, make_sort_key};
+use crate::xdxf::input_parsers::xdxf_filter;
+use crate::xdxf::XdxfPaliWord;
+use crate::xml_filter;
+use tera::{Context, Tera};
+
+lazy_static! {
+    static ref TEMPLATES: Tera = {
+        let mut tera = Tera::default();
+        tera.add_raw_templates(vec![(
+            "dpd_definition",
+            include_str!("templates/dpd_definition.xml"),
+        )])
+        .expect("Unexpected failure adding template");
+        tera.register_filter("xdxf", xdxf_filter);
+        tera.register_filter("xml", xml_filter);
+        tera
+    };
+}
+
+#[derive(Serialize)]
+struct DefinitionViewModel<'a> {
+    word: &'a DpdPaliWord,
+    concise: bool,
+}
+
+impl XdxfPaliWord for DpdPaliWord {
+    fn id(&self) -> &str {
+        &self.pali1
+    }
+
+    fn sort_key(&self) -> String {
+        make_sort_key(self.id())
+    }
+
+    fn group_id(&self) -> String {
+        make_group_id(self.id())
+    }
+
+    fn definition(&self, concise: bool) -> Result<String, String> {
+        let vm = DefinitionViewModel {
+            word: self,
+            concise,
+        };
+
+        let context = Context::from_serialize(&vm).map_err(|e| e.to_string())?;
+        TEMPLATES
+            .render("dpd_definition", &context)
+            .map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resolve_file_in_manifest_dir;
+    use crate::tests::TestLogger;
+    use crate::xdxf::input_parsers::load_words;
+    use test_case::test_case;
+
+    #[test_case(0, false)]
+    #[test_case(4, false)]
+    #[test_case(9, false)]
+    #[test_case(0, true)]
+    #[test_case(9, true)]
+    fn definition_tests(rec_number: usize, concise: bool) {
+        let path = resolve_file_in_manifest_dir("Pali_English_Dictionary_10_rows-full.csv")
+            .expect("must exist!");
+        let l = TestLogger::new();
+        let mut recs = load_words::<DpdPaliWord>(&path, &l).expect("unexpected");
+
+        let definition = recs
+            .nth(rec_number)
+            .map(|r| r.definition(concise).expect("unexpected"))
+            .expect("unexpected");
+
+        insta::assert_snapshot!(definition);
+    }
+}
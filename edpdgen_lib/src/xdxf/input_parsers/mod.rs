@@ -0,0 +1,83 @@
+use crate::escape_xml;
+use crate::input::create_csv_reader;
+use crate::xdxf::XdxfPaliWord;
+use pls_core_extras::logger::PlsLogger;
+use regex::Regex;
+use std::collections::HashMap;
+use std::path::Path;
+use tera::Value;
+
+pub mod dpd;
+pub mod dps;
+
+lazy_static! {
+    static ref HTML_TAG: Regex = Regex::new(r"<[^>]*>").expect("Malformed regex string");
+}
+
+pub fn load_words<'a, T: 'a + serde::de::DeserializeOwned + XdxfPaliWord>(
+    path: &Path,
+    logger: &'a dyn PlsLogger,
+) -> Result<impl Iterator<Item = impl XdxfPaliWord> + 'a, String> {
+    let rdr = create_csv_reader(path, logger)?;
+
+    let words = rdr
+        .into_deserialize::<T>()
+        .enumerate()
+        .filter_map(move |(i, r)| match r {
+            Ok(w) => Some(w),
+            Err(e) => {
+                logger.error(&format!(
+                    "Unable to deserialize record #{}. Error: {}.",
+                    i, e
+                ));
+                None
+            }
+        });
+
+    logger.info(&format!("... done loading words from {:?}.", &path));
+    Ok(words)
+}
+
+///
+/// Keeps the bold, italics and line breaks of the html in the input data, which are also
+/// XDXF formatting, and escapes everything else.
+///
+pub fn html_to_xdxf(s: &str) -> String {
+    let s = s.replace("\r", "").replace("\n", "<br/>");
+
+    let mut xdxf = String::with_capacity(s.len());
+    let mut last = 0;
+    for tag in HTML_TAG.find_iter(&s) {
+        xdxf.push_str(&escape_xml(&s[last..tag.start()]));
+        match tag.as_str().replace(" ", "").as_str() {
+            t @ "<b>" | t @ "</b>" | t @ "<i>" | t @ "</i>" => xdxf.push_str(t),
+            "<br>" | "<br/>" => xdxf.push_str("<br/>"),
+            _ => {}
+        }
+        last = tag.end();
+    }
+    xdxf.push_str(&escape_xml(&s[last..]));
+
+    xdxf
+}
+
+pub fn xdxf_filter(value: &Value, _args: &HashMap<String, Value>) -> tera::Result<Value> {
+    value
+        .as_str()
+        .map(|s| Value::String(html_to_xdxf(s)))
+        .ok_or_else(|| tera::Error::msg("Filter 'xdxf' expects a string"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("abala", "abala")]
+    #[test_case("a & b < c", "a &amp; b &lt; c"; "markup characters")]
+    #[test_case("<b>dhammo</b><br/>dhammaṃ\n", "<b>dhammo</b><br/>dhammaṃ<br/>"; "bold and breaks")]
+    #[test_case("<i>x</i> <span>y</span>", "<i>x</i> y"; "other tags are removed")]
+    fn html_to_xdxf_test(html: &str, expected: &str) {
+        assert_eq!(html_to_xdxf(html), expected);
+    }
+}
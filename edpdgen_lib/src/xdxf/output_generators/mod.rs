@@ -0,0 +1,189 @@
+use crate::input::input_format::InputFormat;
+use crate::xdxf::XdxfPaliWord;
+use crate::{escape_xml, DictionaryData, DictionaryFile, DictionaryFiles, DictionaryInfo};
+use chrono::DateTime;
+use itertools::Itertools;
+use pls_core_extras::inflection_generator::InflectionGenerator;
+use pls_core_extras::logger::PlsLogger;
+
+const XDXF_DOCTYPE: &str = "<!DOCTYPE xdxf SYSTEM \"https://raw.github.com/soshial/xdxf_makedict/master/format_standard/xdxf_strict.dtd\">";
+
+fn create_articles(
+    words: impl Iterator<Item = impl XdxfPaliWord>,
+    igen: &dyn InflectionGenerator,
+    logger: &dyn PlsLogger,
+    concise: bool,
+) -> Result<Vec<String>, String> {
+    logger.info("Creating xdxf articles.");
+
+    let word_groups = words
+        .sorted_by(|w1, w2| Ord::cmp(&w1.sort_key(), &w2.sort_key()))
+        .group_by(|pw| pw.group_id());
+
+    let mut articles: Vec<String> = Vec::new();
+    for (n, (group_id, words)) in word_groups.into_iter().enumerate() {
+        let words: Vec<_> = words.collect();
+
+        let mut article = format!("<ar>\n<k>{}</k>\n", escape_xml(&group_id));
+        let inflections = words
+            .iter()
+            .flat_map(|w| igen.generate_all_inflections(w.id()))
+            .filter(|i| i != &group_id)
+            .unique();
+        for inflection in inflections {
+            article.push_str(&format!("<k>{}</k>\n", escape_xml(&inflection)));
+        }
+        article.push_str("<def>\n");
+        for w in &words {
+            article.push_str(&w.definition(concise)?);
+        }
+        article.push_str("</def>\n</ar>\n");
+        articles.push(article);
+
+        if n % 1_000 == 0 && n != 0 {
+            logger.info(&format!(
+                "... created {:05} xdxf articles, ending with '{}'.",
+                n, group_id
+            ));
+        }
+    }
+
+    logger.info(&format!(
+        "... done creating {} xdxf articles.",
+        articles.len()
+    ));
+
+    Ok(articles)
+}
+
+fn lang_to(input_format: &InputFormat) -> &'static str {
+    match input_format {
+        InputFormat::Dpd => "ENG",
+        InputFormat::Dps => "RUS",
+    }
+}
+
+fn create_meta_info(dict_info: &DictionaryInfo) -> Result<String, String> {
+    let creation_date = DateTime::parse_from_rfc3339(dict_info.time_stamp)
+        .map_err(|e| e.to_string())?
+        .format("%d-%m-%Y");
+
+    Ok(format!(
+        "<meta_info>\n<languages><from xml:lang=\"PLI\"/><to xml:lang=\"{}\"/></languages>\n<title>{}</title>\n<full_title>{}</full_title>\n<description>{}</description>\n<authors><author role=\"compiler\">{}</author></authors>\n<file_ver>{}</file_ver>\n<creation_date>{}</creation_date>\n</meta_info>\n",
+        lang_to(dict_info.input_format),
+        escape_xml(dict_info.short_name),
+        escape_xml(dict_info.name),
+        escape_xml(dict_info.description),
+        escape_xml(dict_info.author),
+        escape_xml(dict_info.version),
+        creation_date
+    ))
+}
+
+///
+/// See https://github.com/soshial/xdxf_makedict/tree/master/format_standard
+///
+fn create_xdxf_data(dict_info: &DictionaryInfo, articles: &[String]) -> Result<Vec<u8>, String> {
+    let mut xdxf = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n{}\n<xdxf lang_from=\"PLI\" lang_to=\"{}\" format=\"visual\" revision=\"34\">\n",
+        XDXF_DOCTYPE,
+        lang_to(dict_info.input_format)
+    );
+    xdxf.push_str(&create_meta_info(dict_info)?);
+    xdxf.push_str("<lexicon>\n");
+    for article in articles {
+        xdxf.push_str(article);
+    }
+    xdxf.push_str("</lexicon>\n</xdxf>\n");
+
+    Ok(xdxf.into_bytes())
+}
+
+pub fn create_dictionary(
+    dict_info: &DictionaryInfo,
+    words: impl Iterator<Item = impl XdxfPaliWord>,
+    igen: &dyn InflectionGenerator,
+    logger: &dyn PlsLogger,
+) -> Result<DictionaryFiles, String> {
+    let articles = create_articles(words, igen, logger, dict_info.concise)?;
+    let xdxf = create_xdxf_data(dict_info, &articles)?;
+
+    Ok(DictionaryFiles {
+        word_count: articles.len(),
+        files: vec![DictionaryFile {
+            extension: "xdxf".to_string(),
+            bom: vec![],
+            data: DictionaryData::InMemory(xdxf),
+            can_be_empty: false,
+        }],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::dpd::DpdPaliWord;
+    use crate::input::dps::DpsPaliWord;
+    use crate::resolve_file_in_manifest_dir;
+    use crate::tests::{validate_with_xmllint, TestInflectionGenerator, TestLogger};
+    use crate::xdxf::input_parsers::load_words;
+    use crate::OutputFormat;
+    use test_case::test_case;
+
+    /// xdxf_strict.dtd, from https://github.com/soshial/xdxf_makedict/tree/master/format_standard.
+    const XDXF_STRICT_DTD: &str = "src/xdxf/output_generators/test_data/xdxf_strict.dtd";
+
+    fn create_dict_info<'a>(input_format: &'a InputFormat, concise: bool) -> DictionaryInfo<'a> {
+        DictionaryInfo {
+            input_format,
+            concise,
+            ..crate::tests::create_dict_info(&OutputFormat(&crate::xdxf::XdxfGenerator))
+        }
+    }
+
+    fn create_xdxf(
+        dict_info: &DictionaryInfo,
+        words: impl Iterator<Item = impl XdxfPaliWord>,
+    ) -> (String, usize) {
+        let articles = create_articles(
+            words,
+            &TestInflectionGenerator::new(),
+            &TestLogger::new(),
+            dict_info.concise,
+        )
+        .expect("Unexpected");
+        let xdxf = create_xdxf_data(dict_info, &articles).expect("Unexpected");
+
+        (String::from_utf8(xdxf).expect("Unexpected"), articles.len())
+    }
+
+    #[test_case(false)]
+    #[test_case(true)]
+    fn dpd_xdxf_is_valid_test(concise: bool) {
+        let path = resolve_file_in_manifest_dir("Pali_English_Dictionary_10_rows-full.csv")
+            .expect("must exist!");
+        let l = TestLogger::new();
+        let words = load_words::<DpdPaliWord>(&path, &l).expect("Unexpected");
+
+        let (xdxf, word_count) = create_xdxf(&create_dict_info(&InputFormat::Dpd, concise), words);
+
+        validate_with_xmllint("--dtdvalid", XDXF_STRICT_DTD, &xdxf);
+        let doc = roxmltree::Document::parse(&xdxf).expect("Unexpected");
+        assert_eq!(
+            doc.descendants().filter(|n| n.has_tag_name("ar")).count(),
+            word_count
+        );
+    }
+
+    #[test]
+    fn dps_xdxf_is_valid_test() {
+        let path = resolve_file_in_manifest_dir("dps_sample.csv").expect("must exist!");
+        let l = TestLogger::new();
+        let words = load_words::<DpsPaliWord>(&path, &l).expect("Unexpected");
+
+        let (xdxf, _) = create_xdxf(&create_dict_info(&InputFormat::Dps, false), words);
+
+        validate_with_xmllint("--dtdvalid", XDXF_STRICT_DTD, &xdxf);
+        insta::assert_snapshot!(xdxf);
+    }
+}